@@ -3,7 +3,9 @@ use {
     strum_macros,
 };
 
-#[derive(strum_macros::Display, Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(
+    strum_macros::Display, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum MediaType {
     Audio,
@@ -25,12 +27,16 @@ pub enum MediaFormat {
     FLV,
 }
 
-#[derive(strum_macros::Display, Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[derive(
+    strum_macros::Display, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum MediaReceiver {
     FFMPEG,
     RUSTRTMP,
     RUSTSRT,
+    // runner::mock, registered by ECHO_MOCK_RECEIVER
+    MOCK,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
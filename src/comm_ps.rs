@@ -2,7 +2,7 @@
 // > process utility
 use crate::{
     comm::{EchoTimeDuration, EchoTimeInstant},
-    ECHO_ASYNC_SLEEP_MS,
+    metrics, ECHO_ASYNC_SLEEP_MS,
 };
 use thiserror::Error;
 pub type EchoPSCmd = tokio::process::Command;
@@ -37,6 +37,12 @@ pub enum EchoPSError {
     //
     #[error("error on terminate process={0}")]
     TerminateErr(String),
+
+    //
+    // signal process
+    //
+    #[error("error on signal process={0}")]
+    SignalErr(String),
 }
 
 //
@@ -63,6 +69,10 @@ pub fn terminate(pid: EchoPSID) -> Result<EchoPSChild, EchoPSError> {
         .args([pid.to_string().as_str()])
         .spawn()
         .map_err(|e| {
+            metrics::PROC_SIGNAL_TOTAL
+                .with_label_values(&["TERM", "err"])
+                .inc();
+
             // proc.start_kill()  // non-block, kill -9
             EchoPSError::TerminateErr(format!(
                 "failed to execute 'kill', pid={}, e={}",
@@ -71,6 +81,10 @@ pub fn terminate(pid: EchoPSID) -> Result<EchoPSChild, EchoPSError> {
             ))
         })?;
 
+    metrics::PROC_SIGNAL_TOTAL
+        .with_label_values(&["TERM", "ok"])
+        .inc();
+
     // cmd_kill.try_wait()
     // cmd_kill.wait()          ; async wait
     // cmd_kill.try_wait()      ; nonblock wait
@@ -78,6 +92,44 @@ pub fn terminate(pid: EchoPSID) -> Result<EchoPSChild, EchoPSError> {
     Ok(cmd_kill)
 }
 
+//
+// pause/resume
+//
+
+// SIGSTOP: pause running process, SIGCONT: resume paused process
+// > paused process does not handle SIGTERM until resumed
+pub fn signal(pid: EchoPSID, sig: &str) -> Result<EchoPSChild, EchoPSError> {
+    let cmd_kill = EchoPSCmd::new("kill")
+        .args(["-s", sig, pid.to_string().as_str()])
+        .spawn()
+        .map_err(|e| {
+            metrics::PROC_SIGNAL_TOTAL
+                .with_label_values(&[sig, "err"])
+                .inc();
+
+            EchoPSError::SignalErr(format!(
+                "failed to execute 'kill -s {}', pid={}, e={}",
+                sig,
+                pid,
+                e.to_string()
+            ))
+        })?;
+
+    metrics::PROC_SIGNAL_TOTAL
+        .with_label_values(&[sig, "ok"])
+        .inc();
+
+    Ok(cmd_kill)
+}
+
+pub fn pause(pid: EchoPSID) -> Result<EchoPSChild, EchoPSError> {
+    signal(pid, "STOP")
+}
+
+pub fn resume(pid: EchoPSID) -> Result<EchoPSChild, EchoPSError> {
+    signal(pid, "CONT")
+}
+
 //
 // polling process
 //
@@ -156,6 +208,8 @@ pub async fn poll_exit_status(
 
         if now_ms > en_time_ms {
             // termination signal was sented, but process is still running
+            metrics::PROC_POLL_EXIT_TIMEOUT_TOTAL.inc();
+
             return Err(EchoPSError::PollingStatusTimeout);
         }
 
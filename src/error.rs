@@ -1,3 +1,4 @@
+use crate::runner::admission::AdmissionRejection;
 use thiserror::Error;
 
 const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
@@ -34,4 +35,32 @@ pub enum Error {
 
     #[error("{0}")]
     MsgChanRstErrJson(String),
+
+    // requested resource(ex: session) does not exist
+    #[error("not found: {0}")]
+    NotFound(String),
+
+    // publish is rejected, host is at capacity
+    #[error("publish is rejected: {0}")]
+    AdmissionRejected(AdmissionRejection),
+
+    // denied by on_publish authorization, (reason)
+    #[error("publish is denied: {0}")]
+    PublishDenied(String),
+
+    // app_name is published on other node of cluster
+    #[error("publish is rejected, session is owned by other node: {0}")]
+    SessionOwnedByOtherNode(String),
+
+    // codec, muxer or protocol of publish is missing in ffmpeg, (reason)
+    #[error("publish is refused, unsupported by ffmpeg: {0}")]
+    UnsupportedCapability(String),
+}
+
+impl Error {
+    // fatal: subsystem(RecvWorkerManager, VSvrServ) behind the channel is gone
+    // > otherwise failure of a single request(ex: client disconnected before response)
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Error::MsgChanErrChannelClosed(_))
+    }
 }
@@ -0,0 +1,286 @@
+// health
+// > liveness(/healthz) and readiness(/readyz) of node, readiness checks dependencies of publish
+use crate::comm::EchoUtc;
+use crate::config::Config;
+use crate::message::{ServMsg, ServMsgSend};
+use crate::runner::admission;
+use crate::runner::message::{RecvWorkerManagerMsg, RecvWorkerManagerMsgSend};
+use crate::service::vsvr::message::VSvrServMsgSend;
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+// manager loop is not ready without stamping watchdog within
+pub const MANAGER_WATCHDOG_STALE_SEC: i64 = 10;
+// manager loop is stuck, liveness fails(node is restarted)
+pub const MANAGER_WATCHDOG_DEAD_SEC: i64 = 60;
+
+// free disk of output directories, default: READY_MIN_FREE_DISK_MB_DEFAULT
+pub const READY_MIN_FREE_DISK_MB_ENV: &str = "ECHO_READY_MIN_FREE_DISK_MB";
+pub const READY_MIN_FREE_DISK_MB_DEFAULT: u64 = 512;
+
+pub const READY_QUERY_TIMEOUT_MS: u64 = 2000;
+pub const READY_FFMPEG_TIMEOUT_MS: u64 = 2000;
+
+// written and removed in output directories
+const READY_PROBE_FILE_NAME: &str = ".echo_readyz";
+
+//
+// watchdog
+//
+
+// epoch(ms) of last loop of RecvWorkerManager, 0: not started
+static MANAGER_WATCHDOG_EPOCH_MS: AtomicI64 = AtomicI64::new(0);
+
+// by RecvWorkerManager on each loop
+pub fn stamp_manager_watchdog() {
+    MANAGER_WATCHDOG_EPOCH_MS.store(EchoUtc::now().timestamp_millis(), Ordering::Relaxed);
+}
+
+// none: manager is not started yet
+pub fn manager_watchdog_age_sec() -> Option<i64> {
+    match MANAGER_WATCHDOG_EPOCH_MS.load(Ordering::Relaxed) {
+        0 => None,
+        x => Some((EchoUtc::now().timestamp_millis() - x) / 1000),
+    }
+}
+
+//
+// readiness
+//
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessCheck {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+// answer of ServMsg::QueryReadiness
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub checks: Vec<ReadinessCheck>,
+}
+
+impl ReadinessReport {
+    fn push(&mut self, name: &str, rst: Result<String, String>) {
+        let (ok, detail) = match rst {
+            Ok(x) => (true, x),
+            Err(x) => (false, x),
+        };
+
+        if !ok {
+            self.ready = false;
+        }
+
+        self.checks.push(ReadinessCheck {
+            name: name.to_string(),
+            ok,
+            detail,
+        });
+    }
+}
+
+pub async fn check_readiness(
+    config: &Config,
+    worker_man_msg_send: &RecvWorkerManagerMsgSend,
+    vsvr_serv_msg_send: &VSvrServMsgSend,
+) -> ReadinessReport {
+    let mut report = ReadinessReport {
+        ready: true,
+        checks: Vec::new(),
+    };
+
+    report.push("manager_watchdog", check_manager_watchdog());
+    report.push("port_pool", check_port_pool(worker_man_msg_send).await);
+
+    report.push(
+        "vsvr",
+        if vsvr_serv_msg_send.is_closed() {
+            Err(format!("vsvr service is not running"))
+        } else {
+            Ok(format!("running"))
+        },
+    );
+
+    report.push("ffmpeg", check_ffmpeg(&config.echo_ffmpeg_path).await);
+
+    let min_free_disk_mb = std::env::var(READY_MIN_FREE_DISK_MB_ENV)
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(READY_MIN_FREE_DISK_MB_DEFAULT);
+
+    for (name, dir) in [
+        ("hls_root_dir", &config.echo_hls_root_dir),
+        ("rec_root_dir", &config.echo_rec_root_dir),
+        ("ffmpeg_log_root_dir", &config.echo_ffmpeg_log_root_dir),
+    ] {
+        report.push(name, check_output_dir(dir, min_free_disk_mb).await);
+    }
+
+    report
+}
+
+fn check_manager_watchdog() -> Result<String, String> {
+    match manager_watchdog_age_sec() {
+        None => Err(format!("manager is not started")),
+        Some(x) if x > MANAGER_WATCHDOG_STALE_SEC => {
+            Err(format!("manager loop is stalled, age_sec={}", x))
+        }
+        Some(x) => Ok(format!("age_sec={}", x)),
+    }
+}
+
+async fn check_port_pool(worker_man_msg_send: &RecvWorkerManagerMsgSend) -> Result<String, String> {
+    let (responder, rst) = tokio::sync::oneshot::channel();
+
+    worker_man_msg_send
+        .send(RecvWorkerManagerMsg::QueryCapacity(responder))
+        .map_err(|_| format!("manager is not running"))?;
+
+    let report = tokio::time::timeout(Duration::from_millis(READY_QUERY_TIMEOUT_MS), rst)
+        .await
+        .map_err(|_| format!("manager is not answering"))?
+        .map_err(|_| format!("manager is not answering"))?
+        .map_err(|e| format!("failed to query capacity, e={}", e.to_string()))?;
+
+    if report.avail_publish_ports == 0 {
+        return Err(format!(
+            "publish ports are exhausted, sessions={}",
+            report.sessions
+        ));
+    }
+
+    Ok(format!(
+        "avail_publish_ports={}",
+        report.avail_publish_ports
+    ))
+}
+
+// 'ffmpeg -version' exits normally
+async fn check_ffmpeg(ffmpeg_path: &str) -> Result<String, String> {
+    let output = tokio::process::Command::new(ffmpeg_path)
+        .arg("-version")
+        .kill_on_drop(true)
+        .output();
+
+    let output = tokio::time::timeout(Duration::from_millis(READY_FFMPEG_TIMEOUT_MS), output)
+        .await
+        .map_err(|_| format!("ffmpeg is not answering, path={}", ffmpeg_path))?
+        .map_err(|e| format!("failed to run ffmpeg, path={}, e={}", ffmpeg_path, e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "ffmpeg is exited with error, path={}, status={}",
+            ffmpeg_path, output.status
+        ));
+    }
+
+    // ffmpeg version 5.1.2 Copyright (c) 2000-2022 the FFmpeg developers
+    let version = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+
+    Ok(version)
+}
+
+// exists, writable, and has free disk
+async fn check_output_dir(dir: &PathBuf, min_free_disk_mb: u64) -> Result<String, String> {
+    let probe_path = dir.join(READY_PROBE_FILE_NAME);
+
+    tokio::fs::write(&probe_path, b"")
+        .await
+        .map_err(|e| format!("not writable, path={:?}, e={}", dir, e))?;
+
+    let _ = tokio::fs::remove_file(&probe_path).await;
+
+    let path = dir.to_string_lossy().to_string();
+
+    let free_mb = tokio::task::spawn_blocking(move || admission::measure_free_disk_mb(&path))
+        .await
+        .ok()
+        .flatten();
+
+    match free_mb {
+        Some(x) if x < min_free_disk_mb => Err(format!(
+            "not enough free disk, path={:?}, free_mb={}, min_free_mb={}",
+            dir, x, min_free_disk_mb
+        )),
+        Some(x) => Ok(format!("free_mb={}", x)),
+        // not measured(df is not available), not a reason to stop publish
+        None => Ok(format!("free_mb=unknown")),
+    }
+}
+
+//
+// endpoint
+//
+
+// GET handler of api server, 503: process should be restarted
+// > route: web::get().to(health::healthz_handler)
+pub async fn healthz_handler() -> HttpResponse {
+    match manager_watchdog_age_sec() {
+        Some(x) if x > MANAGER_WATCHDOG_DEAD_SEC => {
+            log::error!(
+                "[health::healthz_handler] manager loop is stuck, age_sec={}",
+                x
+            );
+
+            HttpResponse::ServiceUnavailable().json(json!({
+                "live" : false,
+                "manager_watchdog_age_sec" : x,
+            }))
+        }
+        x => HttpResponse::Ok().json(json!({
+            "live" : true,
+            "manager_watchdog_age_sec" : x,
+        })),
+    }
+}
+
+// GET handler of api server, 503: publish must not be routed to this node
+// > route: web::get().to(health::readyz_handler), main_msg_send is app data
+pub async fn readyz_handler(main_msg_send: web::Data<ServMsgSend>) -> HttpResponse {
+    let (responder, rst) = tokio::sync::oneshot::channel();
+
+    if main_msg_send
+        .send(ServMsg::QueryReadiness(responder))
+        .is_err()
+    {
+        return HttpResponse::ServiceUnavailable().json(json!({
+            "ready" : false,
+            "error" : "main message handler is not running",
+        }));
+    }
+
+    match rst.await {
+        Ok(Ok(report)) if report.ready => HttpResponse::Ok().json(report),
+        Ok(Ok(report)) => {
+            log::warn!(
+                "[health::readyz_handler] not ready, failed={:?}",
+                report
+                    .checks
+                    .iter()
+                    .filter(|x| !x.ok)
+                    .map(|x| x.name.as_str())
+                    .collect::<Vec<_>>()
+            );
+
+            HttpResponse::ServiceUnavailable().json(report)
+        }
+        Ok(Err(e)) => HttpResponse::ServiceUnavailable().json(json!({
+            "ready" : false,
+            "error" : e.to_string(),
+        })),
+        Err(_) => HttpResponse::ServiceUnavailable().json(json!({
+            "ready" : false,
+            "error" : "readiness is not answered",
+        })),
+    }
+}
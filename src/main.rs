@@ -13,7 +13,9 @@ mod comm_ps;
 
 mod config;
 mod error;
+mod health;
 mod message;
+mod metrics;
 //mod receiver;
 mod kv;
 mod mlog;
@@ -33,7 +35,12 @@ use {
         error::Error,
         error::Error::*,
         message::*,
-        runner::{message::*, RecvWorkerManager},
+        runner::{
+            ffmpeg::capability,
+            message::*,
+            publish_auth::{PublishAuthRequest, PublishAuthorizer, PublishAuthorizerShared},
+            webhook, RecvWorkerManager, RecvWorkerManagerHandoverSlot, ShutdownMode, ShutdownPrm,
+        },
         service::api::serv::ApiServ,
     },
     anyhow::Result,
@@ -58,7 +65,7 @@ use {
     thiserror::Error,
     // tokio::sync::Mutex/RwLock : async lock, for async function, await
     tokio::{
-        sync::{broadcast, mpsc, oneshot, RwLock},
+        sync::{broadcast, mpsc, oneshot, watch, RwLock},
         task,
         task::JoinHandle,
         time::timeout,
@@ -101,7 +108,6 @@ struct MainContext {
 
 #[actix_rt::main]
 async fn main() -> Result<()> {
-
     let config = match Config::load() {
         Ok(c) => c,
         Err(err) => return Err(err.into()),
@@ -126,12 +132,36 @@ async fn main() -> Result<()> {
 
     log::info!("config= {:?}", config);
 
+    //
+    // ffmpeg capability
+    //
+
+    // misconfigured ffmpeg fails startup, instead of failing on first publish
+    let ffmpeg_caps = capability::init(&config.echo_ffmpeg_path)
+        .await
+        .map_err(|e| {
+            log::error!("[main] failed to probe ffmpeg, e={}", e);
+            e
+        })?;
+
+    if let Err(e) = ffmpeg_caps.validate_config(&config) {
+        log::error!("[main] ffmpeg does not support config, e={}", e);
+        return Err(anyhow::anyhow!("ffmpeg does not support config, {}", e));
+    }
+
     //
     // create main message channel
     //
 
     let (main_msg_send, main_msg_recv) = mpsc::unbounded_channel();
 
+    //
+    // webhooks
+    //
+
+    // started before manager, events of recovered sessions are delivered too
+    let webhook_handle = webhook::start();
+
     //
     // create managers
     //
@@ -139,32 +169,108 @@ async fn main() -> Result<()> {
     let svr_public_ip: EchoPublishIp = resolve_public_ip().await;
     let svr_publish_ip_str = EchoPublishIpStr!(svr_public_ip);
 
+    //
+    // supervision
+    //
+
+    // true on shutdown, exited subsystems are not restarted from then
+    let (shutdown_send, shutdown_recv) = watch::channel(false);
+
     //
     // vsvr service
     //
 
     let vsvr_serv_inst = VSvrServ::new(main_msg_send.clone(), config.clone()).unwrap();
 
-    let vsvr_serv_msg_send = vsvr_serv_inst.get_msg_sender_ref().clone();
+    let (vsvr_serv_msg_send_tx, vsvr_serv_msg_send_rx) =
+        watch::channel(vsvr_serv_inst.get_msg_sender_ref().clone());
 
-    tokio::spawn(vsvr_serv_inst.run());
+    let vsvr_serv_handle = tokio::spawn(async move {
+        let _ = vsvr_serv_inst.run().await;
+    });
 
     //
     // RecvWorkerManager
     //
 
+    // running workers, ports and channel of dropped manager, taken by respawned one
+    let worker_man_handover: RecvWorkerManagerHandoverSlot = Default::default();
+
     let worker_man_inst = RecvWorkerManager::new(
         main_msg_send.clone(),
-        vsvr_serv_msg_send.clone(),
+        vsvr_serv_msg_send_rx.borrow().clone(),
         config.clone(),
         svr_publish_ip_str.clone(),
+        worker_man_handover.clone(),
     );
 
-    //let worker_man_msg_send = worker_man_inst.get_msg_sender_ref().clone();
-    let worker_man_msg_send = worker_man_inst.get_msg_send_ref().clone();
+    let (worker_man_msg_send_tx, worker_man_msg_send_rx) =
+        watch::channel(worker_man_inst.get_msg_send_ref().clone());
 
     // ownership is moved to RecvWorkerManager::run()
-    tokio::spawn(worker_man_inst.run());
+    // > exits after all workers are joined on shutdown
+    let worker_man_handle = tokio::spawn(worker_man_inst.run());
+
+    //
+    // restart of subsystems
+    //
+
+    // running manager is switched to new vsvr service
+    let vsvr_serv_respawn =
+        {
+            let main_msg_send = main_msg_send.clone();
+            let config = config.clone();
+            let worker_man_msg_send_rx = worker_man_msg_send_rx.clone();
+
+            move || {
+                let inst = match VSvrServ::new(main_msg_send.clone(), config.clone()) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        log::error!("[main] failed to create VSvrServ, e={:?}", e);
+                        return None;
+                    }
+                };
+
+                let vsvr_serv_msg_send = inst.get_msg_sender_ref().clone();
+
+                vsvr_serv_msg_send_tx.send_replace(vsvr_serv_msg_send.clone());
+
+                if let Err(e) = worker_man_msg_send_rx.borrow().send(
+                    RecvWorkerManagerMsg::UpdateVSvrServSender(vsvr_serv_msg_send),
+                ) {
+                    log::error!(
+                        "[main] failed to send msg 'UpdateVSvrServSender' to manager, e={}",
+                        e
+                    );
+                }
+
+                Some(tokio::spawn(async move {
+                    let _ = inst.run().await;
+                }))
+            }
+        };
+
+    // sessions of previous manager are adopted by new one, same channel is kept
+    let worker_man_respawn = {
+        let main_msg_send = main_msg_send.clone();
+        let config = config.clone();
+        let svr_publish_ip_str = svr_publish_ip_str.clone();
+        let vsvr_serv_msg_send_rx = vsvr_serv_msg_send_rx.clone();
+
+        move || {
+            let inst = RecvWorkerManager::new(
+                main_msg_send.clone(),
+                vsvr_serv_msg_send_rx.borrow().clone(),
+                config.clone(),
+                svr_publish_ip_str.clone(),
+                worker_man_handover.clone(),
+            );
+
+            worker_man_msg_send_tx.send_replace(inst.get_msg_send_ref().clone());
+
+            Some(tokio::spawn(inst.run()))
+        }
+    };
 
     //
     // build main context
@@ -184,203 +290,829 @@ async fn main() -> Result<()> {
         async_task_handles: Vec::new(),
     };
 
+    main_ctx.async_task_handles.extend(webhook_handle);
+
     //
     // running
     //
 
-    //main_ctx.async_task_handles.push(
-    tokio::spawn(run_message_handler(
-        main_msg_recv, // passover ownership,
-        worker_man_msg_send.clone(),
-        svr_publish_ip_str,
-        vsvr_serv_msg_send.clone(),
+    let worker_man_supervisor = tokio::spawn(supervise(
+        "RecvWorkerManager",
+        worker_man_handle,
+        worker_man_respawn,
+        shutdown_recv.clone(),
     ));
-    //);
+
+    main_ctx.async_task_handles.push(tokio::spawn(supervise(
+        "VSvrServ",
+        vsvr_serv_handle,
+        vsvr_serv_respawn,
+        shutdown_recv.clone(),
+    )));
+
+    let publish_authorizer = EchoArc::new(PublishAuthorizer::from_env());
+
+    log::info!("[main] on_publish authorizer={}", publish_authorizer.name());
+
+    let msg_handler_ctx = ServMsgHandlerCtx {
+        worker_man_msg_send: worker_man_msg_send_rx.clone(),
+        vsvr_serv_msg_send: vsvr_serv_msg_send_rx.clone(),
+        svr_publish_ip_str,
+        publish_authorizer,
+        config: main_ctx.config.clone(),
+    };
+
+    main_ctx.async_task_handles.push(tokio::spawn(async move {
+        if let Err(e) = run_message_handler(
+            main_msg_recv, // passover ownership,
+            msg_handler_ctx,
+        )
+        .await
+        {
+            log::error!("[main] message handler is exited, e={:?}", e);
+        }
+    }));
 
     tokio::time::sleep(Duration::from_millis(200)).await;
 
-    let mut api_serv = ApiServ::new(main_msg_send.clone(), main_ctx.config.clone())
-        .run()
-        .await?;
+    // keeps serving while sessions are drained(SIGUSR1), publish is rejected by manager
+    let mut api_serv_handle = actix_rt::spawn({
+        let api_serv = ApiServ::new(main_msg_send.clone(), main_ctx.config.clone());
+        async move { api_serv.run().await }
+    });
+
+    let shutdown_mode = tokio::select! {
+        rst = &mut api_serv_handle => {
+            // api server is stopped by itself(signal handling of actix)
+            match rst {
+                Ok(Err(e)) => log::error!("[main] api server is exited with error, e={:?}", e),
+                Err(e) => log::error!("[main] api server is panicked, e={:?}", e),
+                Ok(Ok(_)) => {}
+            }
+
+            ShutdownMode::Terminate
+        }
+
+        mode = wait_shutdown_signal() => mode,
+    };
 
     //
     // cleanup
     //
 
-    // await handles
-    // XXX graceful shutdown
-    // wait for all spawned processes to complete
+    shutdown_send.send_replace(true);
+
+    // sessions are finished before other tasks, vsvr is notified unpublish through manager
+    shutdown_recv_workers(&main_msg_send, shutdown_mode).await;
+
+    // sessions are gone, api server is not needed anymore
+    api_serv_handle.abort();
+    let _ = api_serv_handle.await;
+
+    if let Err(_) = timeout(
+        Duration::from_secs(SHUTDOWN_JOIN_MANAGER_TIMEOUT_SEC),
+        worker_man_supervisor,
+    )
+    .await
+    {
+        log::error!("[main] timeout on joining RecvWorkerManager");
+    }
+
+    // events of finished sessions are kept in outbox, delivered on next start if not sent yet
+    if !webhook::flush(Duration::from_secs(SHUTDOWN_FLUSH_WEBHOOK_TIMEOUT_SEC)).await {
+        log::error!("[main] timeout on flushing webhooks");
+    }
+
+    // message handler, vsvr service and webhook dispatcher are looping forever
     for handle in main_ctx.async_task_handles {
-        handle.await?;
+        handle.abort();
+        let _ = handle.await;
     }
 
+    log::info!("[main] shutdown is completed..");
+
     Ok(())
 }
 
+//
+// supervision
+//
+
+// waiting time before restarting exited subsystem
+const SUPERVISE_RESTART_INTERVAL_MS: u64 = 1000;
+
+// await subsystem task, and restart it by respawn when exited(or panicked) unexpectedly
+// > returns when task is exited on shutdown
+async fn supervise<F>(
+    name: &'static str,
+    mut handle: JoinHandle<()>,
+    mut respawn: F,
+    shutdown_recv: watch::Receiver<bool>,
+) where
+    F: FnMut() -> Option<JoinHandle<()>>,
+{
+    let mut restart_cnt = 0;
+
+    loop {
+        let rst = (&mut handle).await;
+
+        if *shutdown_recv.borrow() {
+            log::info!("[main::supervise] {} is exited on shutdown", name);
+            return;
+        }
+
+        match rst {
+            Err(e) if e.is_panic() => {
+                log::error!("[main::supervise] {} is panicked, e={}", name, e)
+            }
+            _ => log::error!("[main::supervise] {} is exited unexpectedly", name),
+        }
+
+        loop {
+            tokio::time::sleep(Duration::from_millis(SUPERVISE_RESTART_INTERVAL_MS)).await;
+
+            if *shutdown_recv.borrow() {
+                return;
+            }
+
+            restart_cnt += 1;
+
+            log::info!(
+                "[main::supervise] restarting {}.., restart_cnt={}",
+                name,
+                restart_cnt
+            );
+
+            if let Some(h) = respawn() {
+                handle = h;
+                break;
+            }
+        }
+    }
+}
+
+//
+// shutdown
+//
+
+const SHUTDOWN_JOIN_MANAGER_TIMEOUT_SEC: u64 = 5;
+const SHUTDOWN_FLUSH_WEBHOOK_TIMEOUT_SEC: u64 = 3;
+
+// SIGTERM, SIGINT: terminate sessions now, SIGUSR1: drain sessions(rolling deploy)
+async fn wait_shutdown_signal() -> ShutdownMode {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sig_term = signal(SignalKind::terminate()).unwrap();
+    let mut sig_int = signal(SignalKind::interrupt()).unwrap();
+    let mut sig_usr1 = signal(SignalKind::user_defined1()).unwrap();
+
+    let mode = tokio::select! {
+        _ = sig_term.recv() => ShutdownMode::Terminate,
+        _ = sig_int.recv() => ShutdownMode::Terminate,
+        _ = sig_usr1.recv() => ShutdownMode::Drain,
+    };
+
+    log::info!("[main] got shutdown signal, mode={}", mode);
+
+    mode
+}
+
+// request shutdown of all sessions, and await all ffmpegs are dead
+// > while draining, SIGTERM/SIGINT cuts draining short
+async fn shutdown_recv_workers(main_msg_send: &ServMsgSend, mode: ShutdownMode) {
+    let (shutdown_responder, mut shutdown_recv) = tokio::sync::oneshot::channel();
+
+    if let Err(e) = main_msg_send.send(ServMsg::ShutdownRecvWorkers(
+        shutdown_responder,
+        ShutdownPrm {
+            mode,
+            deadline_sec: 0,
+        },
+    )) {
+        log::error!("[main] failed to send msg 'ShutdownRecvWorkers', e={}", e);
+        return;
+    }
+
+    let shutdown_rst = if mode == ShutdownMode::Drain {
+        tokio::select! {
+            rst = &mut shutdown_recv => rst,
+            mode = wait_shutdown_signal() => {
+                let (terminate_responder, terminate_recv) = tokio::sync::oneshot::channel();
+
+                if let Err(e) = main_msg_send.send(ServMsg::ShutdownRecvWorkers(
+                    terminate_responder,
+                    ShutdownPrm { mode, deadline_sec: 0 },
+                )) {
+                    log::error!("[main] failed to send msg 'ShutdownRecvWorkers', e={}", e);
+                }
+
+                // both are answered with the same report
+                let _ = terminate_recv.await;
+                shutdown_recv.await
+            }
+        }
+    } else {
+        shutdown_recv.await
+    };
+
+    match shutdown_rst {
+        Ok(Ok(report)) => {
+            if report["unconfirmed"]
+                .as_array()
+                .map_or(false, |x| !x.is_empty())
+            {
+                log::error!(
+                    "[main] some sessions are not confirmed to be terminated, report={}",
+                    report
+                );
+            } else {
+                log::info!("[main] all sessions are terminated, report={}", report);
+            }
+        }
+        Ok(Err(e)) => log::error!("[main] failed to shutdown sessions, e={:?}", e),
+        Err(e) => log::error!("[main] failed to receive shutdown result, e={:?}", e),
+    }
+}
+
+// subsystem senders, replaced by supervisor on restart of subsystem
+#[derive(Clone)]
+pub struct ServMsgHandlerCtx {
+    worker_man_msg_send: watch::Receiver<RecvWorkerManagerMsgSend>,
+    vsvr_serv_msg_send: watch::Receiver<VSvrServMsgSend>,
+    svr_publish_ip_str: String,
+    publish_authorizer: PublishAuthorizerShared,
+    config: Config,
+}
+
+// each message is handled in its own task, failure of a request does not affect others
 pub async fn run_message_handler(
     mut main_msg_recv: ServMsgRecv,
-    worker_man_msg_send: RecvWorkerManagerMsgSend,
-    svr_publish_ip_str: String,
-    vsvr_serv_msg_send: VSvrServMsgSend,
+    ctx: ServMsgHandlerCtx,
 ) -> Result<()> {
-    let mut worker_man_msg_send = worker_man_msg_send.clone();
-
     log::debug!("[ServMsgHandler] starting..");
 
-    loop {
-        let msg = main_msg_recv.recv().await;
+    while let Some(msg) = main_msg_recv.recv().await {
+        let ctx = ctx.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = handle_serv_msg(msg, ctx).await {
+                if e.is_fatal() {
+                    // supervisor restarts subsystem, following requests are served by new one
+                    log::error!("[ServMsgHandler] subsystem is unavailable, e={}", e);
+                } else {
+                    log::warn!("[ServMsgHandler] failed to handle request, e={}", e);
+                }
+            }
+        });
+    }
+
+    log::error!("[ServMsgHandler] main message channel is closed, exiting..");
+
+    Ok(())
+}
+
+async fn handle_serv_msg(msg: ServMsg, ctx: ServMsgHandlerCtx) -> Result<(), Error> {
+    let worker_man_msg_send = ctx.worker_man_msg_send.borrow().clone();
+    let vsvr_serv_msg_send = ctx.vsvr_serv_msg_send.borrow().clone();
 
-        let msg_proc_status: Result<(), Error> = match msg {
-            Some(ServMsg::TerminateRecvWorker(responder, prm_type, app_name, sess_key)) => {
-                log::debug!(
-                    "[ServMsg::TerminateWorker] terminating receive worker..,\
+    match msg {
+        ServMsg::TerminateRecvWorker(responder, prm_type, app_name, sess_key) => {
+            log::debug!(
+                "[ServMsg::TerminateWorker] terminating receive worker..,\
                     prm_type={}, app_name={}, sess_key={}",
+                prm_type,
+                app_name,
+                sess_key
+            );
+
+            let (man_term_responder, man_term_recv) = tokio::sync::oneshot::channel();
+
+            worker_man_msg_send
+                .send(RecvWorkerManagerMsg::TerminateRecvWorker(
+                    man_term_responder,
                     prm_type,
-                    app_name,
-                    sess_key
+                    app_name.clone(),
+                    sess_key,
+                ))
+                .map_err(|e| {
+                    MsgChanErrChannelClosed(format!(
+                        "{},e={}",
+                        "RecvWorkerManager,TerminateRecvWorker", e
+                    ))
+                })?;
+
+            let man_term_rst = man_term_recv.await.map_err(|e| {
+                MsgChanErrRecvFail(format!(
+                    "{},e={:?}",
+                    "RecvWorkerManager,TerminateRecvWorker", e
+                ))
+            })?;
+
+            let resp = match man_term_rst {
+                Err(RunnerError::FailedToTerminateRecvWorker(e)) => {
+                    Err(Error::MsgChanRstErrJson(e.to_string()))
+                }
+                Err(RunnerError::RecvWorkerNotFound(e)) => Err(Error::NotFound(e)),
+                Err(e) => Err(error::Error::MsgChanRstErrJson(e.to_string())),
+                Ok(mut stopped) => {
+                    // successfully, enqueued terminate worker message
+                    stopped["name"] = json!(app_name);
+                    Ok(stopped)
+                }
+            };
+
+            responder.send(resp).map_err(|e| {
+                MsgChanErrSendFail(format!("{},e={:?}", "MainServ,TerminateRecvWorker", e))
+            })?;
+
+            Ok(())
+        }
+
+        ServMsg::SpawnRecvWorker(responder, req_publish, client_ip) => {
+            log::debug!(
+                "[ServMsg][SpawnRecvWorker] creating receive worker.., client_ip={:?}, {:?}",
+                client_ip,
+                req_publish
+            );
+
+            // on_publish, before publish port is leased by manager
+            let auth_req = PublishAuthRequest {
+                app_name: req_publish.app_name.clone(),
+                sess_key: req_publish.sess_key.clone(),
+                media_type: req_publish.media.media_type,
+                profile: req_publish.receiver_prm.args.clone(),
+                client_ip,
+                publish_ip: ctx.svr_publish_ip_str.clone(),
+            };
+
+            let decision = ctx.publish_authorizer.authorize(&auth_req).await;
+
+            if !decision.allow {
+                let reason = decision.reason.unwrap_or(format!("denied by on_publish"));
+
+                log::warn!(
+                    "[ServMsg][SpawnRecvWorker] publish is denied, app_name={}, client_ip={:?}, reason={}",
+                    auth_req.app_name,
+                    auth_req.client_ip,
+                    reason
                 );
 
-                let (man_term_responder, man_term_recv) = tokio::sync::oneshot::channel();
+                responder
+                    .send(Err(Error::PublishDenied(reason)))
+                    .map_err(|e| MsgChanErrSendFail(format!("{},e={:?}", "SpawnRecvWorker", e)))?;
+
+                return Ok(());
+            }
+
+            let (spawn_responder, spawn_rst) = tokio::sync::oneshot::channel();
+
+            worker_man_msg_send
+                .send(RecvWorkerManagerMsg::SpawnRecvWorker(
+                    spawn_responder,
+                    req_publish.clone(),
+                    decision.overrides,
+                ))
+                .map_err(|e| MsgChanErrChannelClosed(format!("{},e={}", "SpawnRecvWorker", e)))?;
+
+            let worker_info = spawn_rst
+                .await
+                .map_err(|e| MsgChanErrRecvFail(format!("{},e={:?}", "SpawnRecvWorker", e)))?;
+
+            let spawn_rst = match worker_info {
+                Err(RunnerError::FailedToCreateRecvWorker(e)) => Err(Error::MsgChanRstErrJson(e)),
+                Err(RunnerError::AdmissionRejected(rejection)) => {
+                    Err(Error::AdmissionRejected(rejection))
+                }
+                Err(RunnerError::SessionOwnedByOtherNode(e)) => {
+                    Err(Error::SessionOwnedByOtherNode(e))
+                }
+                Err(RunnerError::UnsupportedCapability(e)) => Err(Error::UnsupportedCapability(e)),
+                Err(e) => Err(error::Error::MsgChanRstErrJson(e.to_string())),
+                Ok((publish_ip, publish_port, backup_publish_port, _)) => {
+                    // successfully created receive worker
+                    let app_name = req_publish.app_name.clone();
+                    let echo_api_sess_key = req_publish.sess_key.clone();
+
+                    //let publish_ip = svr_publish_ip_str.clone();
+                    //let publish_port : u16 = 30000;
+
+                    let prop_transport = PropTransport {
+                        ip_type: format!("IPv4"),
+                        address: publish_ip.clone(),
+                        port: publish_port,
+                    };
+
+                    let propReciver = PropReceiver {
+                        receiver_type: MediaReceiver::FFMPEG,
+                    };
+
+                    let prop_rtmp = PropRtmp {
+                        url: format!(
+                            "rtmp://{}:{}/{}",
+                            publish_ip.clone(),
+                            publish_port,
+                            app_name
+                        ),
+                        name: echo_api_sess_key.clone(),
+                    };
+
+                    // backup publisher of same session, PublishOverrides.backup
+                    let backup_publish_url = backup_publish_port
+                        .map(|port| format!("rtmp://{}:{}/{}", publish_ip, port, app_name));
+
+                    if let Some(url) = backup_publish_url.as_ref() {
+                        log::info!(
+                            "[ServMsg][SpawnRecvWorker] backup publisher is accepted, app_name={}, backup_url={}",
+                            app_name,
+                            url
+                        );
+                    }
+
+                    let response = crate::service::api::reqres::publish::res::gen_response_ok(
+                        // format!("success"),
+                        app_name,
+                        // echo_api_sess_key.clone(),
+                        prop_transport,
+                        req_publish.media,
+                        prop_rtmp,
+                        //  propReciver,
+                    );
+
+                    Ok(ResPublish {
+                        res: response,
+                        backup_publish_url,
+                        backup_publish_port,
+                    })
+                }
+            };
+
+            responder
+                .send(spawn_rst)
+                .map_err(|e| MsgChanErrSendFail(format!("{},e={:?}", "SpawnRecvWorker", e)))?;
+
+            Ok(())
+            //Err(Error::MsgChanRstErr(format!("error-test")))
+        }
+
+        ServMsg::ChangeRecvWorkerExpiry(responder, prm_type, app_name, sess_key, change_prm) => {
+            log::debug!(
+                "[ServMsg::ChangeRecvWorkerExpiry] changing expiry of receive worker..,\
+                    prm_type={}, app_name={}, sess_key={}, op={}",
+                prm_type,
+                app_name,
+                sess_key,
+                change_prm.op
+            );
+
+            let (man_change_responder, man_change_recv) = tokio::sync::oneshot::channel();
+
+            let resp = relay_worker_oper(
+                &worker_man_msg_send,
+                RecvWorkerManagerMsg::ChangeRecvWorkerExpiry(
+                    man_change_responder,
+                    prm_type,
+                    app_name,
+                    sess_key,
+                    change_prm,
+                ),
+                man_change_recv,
+                "RecvWorkerManager,ChangeRecvWorkerExpiry",
+            )
+            .await?;
+
+            responder.send(resp).map_err(|e| {
+                MsgChanErrSendFail(format!("{},e={:?}", "MainServ,ChangeRecvWorkerExpiry", e))
+            })?;
+
+            Ok(())
+        }
+
+        ServMsg::PauseRecvWorker(responder, prm_type, app_name, sess_key, pause_prm) => {
+            log::debug!(
+                "[ServMsg::PauseRecvWorker] pausing receive worker..,\
+                    prm_type={}, app_name={}, sess_key={}, target={}",
+                prm_type,
+                app_name,
+                sess_key,
+                pause_prm.target
+            );
+
+            let (man_pause_responder, man_pause_recv) = tokio::sync::oneshot::channel();
+
+            let resp = relay_worker_oper(
+                &worker_man_msg_send,
+                RecvWorkerManagerMsg::PauseRecvWorker(
+                    man_pause_responder,
+                    prm_type,
+                    app_name,
+                    sess_key,
+                    pause_prm,
+                ),
+                man_pause_recv,
+                "RecvWorkerManager,PauseRecvWorker",
+            )
+            .await?;
+
+            responder.send(resp).map_err(|e| {
+                MsgChanErrSendFail(format!("{},e={:?}", "MainServ,PauseRecvWorker", e))
+            })?;
+
+            Ok(())
+        }
+
+        ServMsg::ResumeRecvWorker(responder, prm_type, app_name, sess_key) => {
+            log::debug!(
+                "[ServMsg::ResumeRecvWorker] resuming receive worker..,\
+                    prm_type={}, app_name={}, sess_key={}",
+                prm_type,
+                app_name,
+                sess_key
+            );
+
+            let (man_resume_responder, man_resume_recv) = tokio::sync::oneshot::channel();
+
+            let resp = relay_worker_oper(
+                &worker_man_msg_send,
+                RecvWorkerManagerMsg::ResumeRecvWorker(
+                    man_resume_responder,
+                    prm_type,
+                    app_name,
+                    sess_key,
+                ),
+                man_resume_recv,
+                "RecvWorkerManager,ResumeRecvWorker",
+            )
+            .await?;
+
+            responder.send(resp).map_err(|e| {
+                MsgChanErrSendFail(format!("{},e={:?}", "MainServ,ResumeRecvWorker", e))
+            })?;
+
+            Ok(())
+        }
 
-                worker_man_msg_send
-                    .send(RecvWorkerManagerMsg::TerminateRecvWorker(
-                        man_term_responder,
-                        prm_type,
-                        app_name.clone(),
-                        sess_key,
+        ServMsg::TerminateRecvWorkers(responder, filter) => {
+            log::debug!(
+                "[ServMsg::TerminateRecvWorkers] terminating receive workers.., filter={:?}",
+                filter
+            );
+
+            let (man_term_responder, man_term_recv) = tokio::sync::oneshot::channel();
+
+            worker_man_msg_send
+                .send(RecvWorkerManagerMsg::TerminateRecvWorkers(
+                    man_term_responder,
+                    filter,
+                ))
+                .map_err(|e| {
+                    MsgChanErrChannelClosed(format!(
+                        "{},e={}",
+                        "RecvWorkerManager,TerminateRecvWorkers", e
                     ))
-                    .map_err(|e| {
-                        MsgChanErrSendFail(format!(
-                            "{},e={}",
-                            "RecvWorkerManager,TerminateRecvWorker", e
-                        ))
-                    })?;
-
-                let man_term_rst = man_term_recv.await.map_err(|e| {
-                    MsgChanErrRecvFail(format!(
-                        "{},e={:?}",
-                        "RecvWorkerManager,TerminateRecvWorker", e
+                })?;
+
+            let man_term_rst = man_term_recv.await.map_err(|e| {
+                MsgChanErrRecvFail(format!(
+                    "{},e={:?}",
+                    "RecvWorkerManager,TerminateRecvWorkers", e
+                ))
+            })?;
+
+            let resp = man_term_rst.map_err(|e| Error::MsgChanRstErrJson(e.to_string()));
+
+            responder.send(resp).map_err(|e| {
+                MsgChanErrSendFail(format!("{},e={:?}", "MainServ,TerminateRecvWorkers", e))
+            })?;
+
+            Ok(())
+        }
+
+        ServMsg::QuerySessions(responder, filter) => {
+            log::debug!("[ServMsg::QuerySessions] filter={:?}", filter);
+
+            let (man_query_responder, man_query_recv) = tokio::sync::oneshot::channel();
+
+            worker_man_msg_send
+                .send(RecvWorkerManagerMsg::QuerySessions(
+                    man_query_responder,
+                    filter,
+                ))
+                .map_err(|e| {
+                    MsgChanErrChannelClosed(format!(
+                        "{},e={}",
+                        "RecvWorkerManager,QuerySessions", e
                     ))
                 })?;
 
-                let resp = match man_term_rst {
-                    Err(RunnerError::FailedToTerminateRecvWorker(e)) => {
-                        Err(Error::MsgChanRstErrJson(e.to_string()))
-                    }
-                    Err(e) => Err(error::Error::MsgChanRstErrJson(e.to_string())),
-                    Ok(_) => {
-                        // successfully, enqueued terminate worker message
-                        Ok(json!({ "name": app_name }))
-                    }
-                };
+            let man_query_rst = man_query_recv.await.map_err(|e| {
+                MsgChanErrRecvFail(format!("{},e={:?}", "RecvWorkerManager,QuerySessions", e))
+            })?;
 
-                responder.send(resp).map_err(|e| {
-                    MsgChanErrSendFail(format!("{},e={:?}", "MainServ,TerminateRecvWorker", e))
+            let resp = match man_query_rst {
+                Err(e) => Err(Error::MsgChanRstErrJson(e.to_string())),
+                Ok(session_list) => serde_json::to_value(session_list)
+                    .map_err(|e| Error::ParsingFailed(e.to_string())),
+            };
+
+            responder.send(resp).map_err(|e| {
+                MsgChanErrSendFail(format!("{},e={:?}", "MainServ,QuerySessions", e))
+            })?;
+
+            Ok(())
+        }
+
+        ServMsg::QuerySession(responder, app_name) => {
+            log::debug!("[ServMsg::QuerySession] app_name={}", app_name);
+
+            let (man_query_responder, man_query_recv) = tokio::sync::oneshot::channel();
+
+            worker_man_msg_send
+                .send(RecvWorkerManagerMsg::QuerySession(
+                    man_query_responder,
+                    app_name,
+                ))
+                .map_err(|e| {
+                    MsgChanErrChannelClosed(format!("{},e={}", "RecvWorkerManager,QuerySession", e))
                 })?;
 
-                Ok(())
-            }
+            let man_query_rst = man_query_recv.await.map_err(|e| {
+                MsgChanErrRecvFail(format!("{},e={:?}", "RecvWorkerManager,QuerySession", e))
+            })?;
 
-            Some(ServMsg::SpawnRecvWorker(responder, req_publish)) => {
-                log::debug!(
-                    "[ServMsg][SpawnRecvWorker] creating receive worker.., {:?}",
-                    req_publish
-                );
+            let resp = match man_query_rst {
+                Err(RunnerError::RecvWorkerNotFound(e)) => Err(Error::NotFound(e)),
+                Err(e) => Err(Error::MsgChanRstErrJson(e.to_string())),
+                Ok(session_info) => serde_json::to_value(session_info)
+                    .map_err(|e| Error::ParsingFailed(e.to_string())),
+            };
+
+            responder.send(resp).map_err(|e| {
+                MsgChanErrSendFail(format!("{},e={:?}", "MainServ,QuerySession", e))
+            })?;
 
-                let (spawn_responder, spawn_rst) = tokio::sync::oneshot::channel();
+            Ok(())
+        }
 
-                worker_man_msg_send
-                    .send(RecvWorkerManagerMsg::SpawnRecvWorker(
-                        spawn_responder,
-                        req_publish.clone(),
+        ServMsg::ShutdownRecvWorkers(responder, shutdown_prm) => {
+            log::info!(
+                "[ServMsg::ShutdownRecvWorkers] shutting down receive workers.., mode={}",
+                shutdown_prm.mode
+            );
+
+            let (man_shutdown_responder, man_shutdown_recv) = tokio::sync::oneshot::channel();
+
+            worker_man_msg_send
+                .send(RecvWorkerManagerMsg::ShutdownRecvWorkers(
+                    man_shutdown_responder,
+                    shutdown_prm,
+                ))
+                .map_err(|e| {
+                    MsgChanErrChannelClosed(format!(
+                        "{},e={}",
+                        "RecvWorkerManager,ShutdownRecvWorkers", e
                     ))
-                    .map_err(|e| MsgChanErrSendFail(format!("{},e={}", "SpawnRecvWorker", e)))?;
+                })?;
 
-                let worker_info = spawn_rst
-                    .await
-                    .map_err(|e| MsgChanErrRecvFail(format!("{},e={:?}", "SpawnRecvWorker", e)))?;
+            // draining may take hours, request is awaited in its own task
+            let resp = match man_shutdown_recv.await {
+                Ok(rst) => rst.map_err(|e| Error::MsgChanRstErrJson(e.to_string())),
+                Err(e) => Err(MsgChanErrRecvFail(format!(
+                    "{},e={:?}",
+                    "RecvWorkerManager,ShutdownRecvWorkers", e
+                ))),
+            };
 
-                let spawn_rst = match worker_info {
-                    Err(RunnerError::FailedToCreateRecvWorker(e)) => {
-                        Err(Error::MsgChanRstErrJson(e))
-                    }
-                    Err(e) => Err(error::Error::MsgChanRstErrJson(e.to_string())),
-                    Ok((publish_ip, publish_port, _)) => {
-                        // successfully created receive worker
-                        let app_name = req_publish.app_name.clone();
-                        let echo_api_sess_key = req_publish.sess_key.clone();
-
-                        //let publish_ip = svr_publish_ip_str.clone();
-                        //let publish_port : u16 = 30000;
-
-                        let prop_transport = PropTransport {
-                            ip_type: format!("IPv4"),
-                            address: publish_ip.clone(),
-                            port: publish_port,
-                        };
-
-                        let propReciver = PropReceiver {
-                            receiver_type: MediaReceiver::FFMPEG,
-                        };
-
-                        let prop_rtmp = PropRtmp {
-                            url: format!(
-                                "rtmp://{}:{}/{}",
-                                publish_ip.clone(),
-                                publish_port,
-                                app_name
-                            ),
-                            name: echo_api_sess_key.clone(),
-                        };
-
-                        let mut response =
-                            crate::service::api::reqres::publish::res::gen_response_ok(
-                                // format!("success"),
-                                app_name,
-                                // echo_api_sess_key.clone(),
-                                prop_transport,
-                                req_publish.media,
-                                prop_rtmp,
-                                //  propReciver,
-                            );
-
-                        Ok(response)
-                    }
-                };
+            responder.send(resp).map_err(|e| {
+                MsgChanErrSendFail(format!("{},e={:?}", "MainServ,ShutdownRecvWorkers", e))
+            })?;
 
-                responder
-                    .send(spawn_rst)
-                    .map_err(|e| MsgChanErrSendFail(format!("{},e={:?}", "SpawnRecvWorker", e)))?;
+            Ok(())
+        }
 
-                Ok(())
-                //Err(Error::MsgChanRstErr(format!("error-test")))
-            }
+        ServMsg::QueryCapacity(responder) => {
+            let (man_query_responder, man_query_recv) = tokio::sync::oneshot::channel();
 
-            Some(ServMsg::GetVSvrServSender(responder)) => {
-                responder
-                    .send(Ok(vsvr_serv_msg_send.clone()))
-                    .map_err(|e| {
-                        MsgChanErrSendFail(format!("{},e={:?}", "GetVSvrServSender", e))
-                    })?;
+            worker_man_msg_send
+                .send(RecvWorkerManagerMsg::QueryCapacity(man_query_responder))
+                .map_err(|e| {
+                    MsgChanErrChannelClosed(format!(
+                        "{},e={}",
+                        "RecvWorkerManager,QueryCapacity", e
+                    ))
+                })?;
 
-                Ok(())
-            }
+            let man_query_rst = man_query_recv.await.map_err(|e| {
+                MsgChanErrRecvFail(format!("{},e={:?}", "RecvWorkerManager,QueryCapacity", e))
+            })?;
 
-            Some(_) => Ok(()),
-            None => {
-                log::error!("internal error: main_event");
-                Ok(())
-            }
-        };
+            let resp = match man_query_rst {
+                Err(e) => Err(Error::MsgChanRstErrJson(e.to_string())),
+                Ok(report) => {
+                    serde_json::to_value(report).map_err(|e| Error::ParsingFailed(e.to_string()))
+                }
+            };
+
+            responder.send(resp).map_err(|e| {
+                MsgChanErrSendFail(format!("{},e={:?}", "MainServ,QueryCapacity", e))
+            })?;
 
-        if let Err(e) = msg_proc_status {
-            log::error!("internal error: {:?}", e);
-            panic!("internal error: {:?}", e);
+            Ok(())
         }
 
-        tokio::time::sleep(Duration::from_millis(100)).await;
-        //tokio::task::yield_now().await;
+        ServMsg::NotifyRecordingArchived(responder, app_name, file_name) => {
+            let (man_responder, man_recv) = tokio::sync::oneshot::channel();
+
+            worker_man_msg_send
+                .send(RecvWorkerManagerMsg::NotifyRecordingArchived(
+                    man_responder,
+                    app_name,
+                    file_name,
+                ))
+                .map_err(|e| {
+                    MsgChanErrChannelClosed(format!(
+                        "{},e={}",
+                        "RecvWorkerManager,NotifyRecordingArchived", e
+                    ))
+                })?;
+
+            let man_rst = man_recv.await.map_err(|e| {
+                MsgChanErrRecvFail(format!(
+                    "{},e={:?}",
+                    "RecvWorkerManager,NotifyRecordingArchived", e
+                ))
+            })?;
+
+            let resp = man_rst.map_err(|e| Error::MsgChanRstErrJson(e.to_string()));
+
+            responder.send(resp).map_err(|e| {
+                MsgChanErrSendFail(format!("{},e={:?}", "MainServ,NotifyRecordingArchived", e))
+            })?;
+
+            Ok(())
+        }
+
+        ServMsg::QueryCluster(responder) => {
+            let (man_query_responder, man_query_recv) = tokio::sync::oneshot::channel();
+
+            worker_man_msg_send
+                .send(RecvWorkerManagerMsg::QueryCluster(man_query_responder))
+                .map_err(|e| {
+                    MsgChanErrChannelClosed(format!("{},e={}", "RecvWorkerManager,QueryCluster", e))
+                })?;
+
+            let man_query_rst = man_query_recv.await.map_err(|e| {
+                MsgChanErrRecvFail(format!("{},e={:?}", "RecvWorkerManager,QueryCluster", e))
+            })?;
+
+            let resp = man_query_rst.map_err(|e| Error::MsgChanRstErrJson(e.to_string()));
+
+            responder.send(resp).map_err(|e| {
+                MsgChanErrSendFail(format!("{},e={:?}", "MainServ,QueryCluster", e))
+            })?;
+
+            Ok(())
+        }
+
+        ServMsg::QueryReadiness(responder) => {
+            let report =
+                health::check_readiness(&ctx.config, &worker_man_msg_send, &vsvr_serv_msg_send)
+                    .await;
+
+            responder.send(Ok(report)).map_err(|e| {
+                MsgChanErrSendFail(format!("{},e={:?}", "MainServ,QueryReadiness", e))
+            })?;
+
+            Ok(())
+        }
+
+        ServMsg::GetVSvrServSender(responder) => {
+            responder
+                .send(Ok(vsvr_serv_msg_send.clone()))
+                .map_err(|e| MsgChanErrSendFail(format!("{},e={:?}", "GetVSvrServSender", e)))?;
+
+            Ok(())
+        }
     }
+}
 
-    Ok(())
+// send a worker operation message to RecvWorkerManager, and await rst_json
+async fn relay_worker_oper(
+    worker_man_msg_send: &RecvWorkerManagerMsgSend,
+    msg: RecvWorkerManagerMsg,
+    oper_recv: EchoOCRst<Result<PrmJson, RunnerError>>,
+    msg_name: &str,
+) -> Result<Result<PrmJson, Error>, Error> {
+    worker_man_msg_send
+        .send(msg)
+        .map_err(|e| MsgChanErrChannelClosed(format!("{},e={}", msg_name, e)))?;
+
+    let oper_rst = oper_recv
+        .await
+        .map_err(|e| MsgChanErrRecvFail(format!("{},e={:?}", msg_name, e)))?;
+
+    Ok(match oper_rst {
+        Err(RunnerError::RecvWorkerNotFound(e)) => Err(Error::NotFound(e)),
+        Err(e) => Err(Error::MsgChanRstErrJson(e.to_string())),
+        Ok(r) => Ok(r),
+    })
 }
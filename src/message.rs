@@ -1,9 +1,12 @@
 use crate::comm::*;
 use crate::error::Error;
+use crate::health::ReadinessReport;
 use crate::runner::message::TerminateRecvWorkerPrmType;
+use crate::runner::{ExpiryChangePrm, SessionPausePrm, SessionQueryFilter, ShutdownPrm};
 use crate::service::api::reqres::publish::{req::ReqPublishV3, res::ResPublishV3};
 use crate::service::vsvr::message::VSvrServMsgSend;
 use anyhow::Result;
+use serde::Serialize;
 use tokio::sync::{mpsc, oneshot};
 
 //
@@ -18,11 +21,30 @@ pub type ServMsgResponseJson<T> = oneshot::Sender<Result<T, Error>>;
 
 //pub type SpawnRecvWorkerMsgRst = PrmJsonValue;
 
+// answer of ServMsg::SpawnRecvWorker, ResPublishV3 is serialized as it is
+// > backup endpoint of session is added, PublishOverrides.backup
+#[derive(Debug, Serialize)]
+pub struct ResPublish {
+    #[serde(flatten)]
+    pub res: ResPublishV3,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_publish_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_publish_port: Option<u16>,
+}
+
 #[derive(Debug)]
 pub enum ServMsg {
-    SpawnRecvWorker(ServMsgResponseJson<ResPublishV3>, ReqPublishV3),
+    // (rst, req_publish, client_ip), client_ip is checked by on_publish authorization
+    SpawnRecvWorker(
+        ServMsgResponseJson<ResPublish>,
+        ReqPublishV3,
+        Option<EchoIpStr>,
+    ),
 
-    // (rst_json, 'TerminateRecvWorkerPrmType::AppName' , app_name, sess_key)
+    // (rst_json, prm_type, app_name or worker_uuid, sess_key)
+    // > prm_type=WorkerUUID, app_name is worker_uuid
     TerminateRecvWorker(
         ServMsgResponseJson<PrmJson>,
         TerminateRecvWorkerPrmType,
@@ -31,6 +53,62 @@ pub enum ServMsg {
     ),
 
     GetVSvrServSender(GetVSvrServSenderMsgOCResponder),
+
+    // (rst_json, prm_type, app_name or worker_uuid, sess_key, change)
+    // > rst_json: { "expire_epoch", "remain_sec", "paused" }
+    ChangeRecvWorkerExpiry(
+        ServMsgResponseJson<PrmJson>,
+        TerminateRecvWorkerPrmType,
+        String,
+        String,
+        ExpiryChangePrm,
+    ),
+
+    // (rst_json, prm_type, app_name or worker_uuid, sess_key, pause)
+    // > rst_json: { "paused", "expiry_paused", "expire_epoch" }
+    PauseRecvWorker(
+        ServMsgResponseJson<PrmJson>,
+        TerminateRecvWorkerPrmType,
+        String,
+        String,
+        SessionPausePrm,
+    ),
+
+    // (rst_json, prm_type, app_name or worker_uuid, sess_key)
+    ResumeRecvWorker(
+        ServMsgResponseJson<PrmJson>,
+        TerminateRecvWorkerPrmType,
+        String,
+        String,
+    ),
+
+    // bulk termination by filter, pagination is ignored
+    // > rst_json: { "stopped": [..], "failed": [..] }
+    TerminateRecvWorkers(ServMsgResponseJson<PrmJson>, SessionQueryFilter),
+
+    // GET /sessions, rst_json=runner::SessionList
+    QuerySessions(ServMsgResponseJson<PrmJson>, SessionQueryFilter),
+
+    // GET /sessions/{app_name}, rst_json=runner::SessionInfo
+    QuerySession(ServMsgResponseJson<PrmJson>, String),
+
+    // GET /capacity, rst_json=runner::admission::CapacityReport
+    QueryCapacity(ServMsgResponseJson<PrmJson>),
+
+    // GET /cluster, rst_json: { "node_id", "nodes": [runner::cluster::ClusterNode] }
+    // > owner of app_name is the node which lists it in sessions
+    QueryCluster(ServMsgResponseJson<PrmJson>),
+
+    // GET /readyz, report is answered even when not ready
+    QueryReadiness(ServMsgResponseJson<ReadinessReport>),
+
+    // POST /recordings/{app_name}/{file_name}/archived, by archiver(ex: s3 uploader)
+    // > rst_json: { "file" }, file name after archived
+    NotifyRecordingArchived(ServMsgResponseJson<PrmJson>, String, String),
+
+    // server shutdown(SIGTERM) or drain(SIGUSR1), new publish is rejected from now
+    // > rst_json: { "mode", "stopped": [..], "drained": [..], "unconfirmed": [..] }
+    ShutdownRecvWorkers(ServMsgResponseJson<PrmJson>, ShutdownPrm),
 }
 
 pub type ServMsgSend = mpsc::UnboundedSender<ServMsg>;
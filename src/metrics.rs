@@ -0,0 +1,280 @@
+// metrics
+// > prometheus metrics of runner and processes, exposed on /metrics of api server
+use crate::runner::ffmpeg::recorder::RecFileStatus;
+use crate::runner::message::{RecvWorkerManagerMsg, RecvWorkerManagerMsgSend};
+use crate::runner::store;
+use crate::runner::RecvWorkerStateSnapshot;
+use actix_web::HttpResponse;
+use lazy_static::lazy_static;
+use prometheus::{
+    register_gauge_vec, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge, register_int_gauge_vec, Encoder, GaugeVec, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// gauges of sessions, ports and archive queue are refreshed within
+pub const METRICS_COLLECT_INTERVAL_SEC: u64 = 5;
+
+lazy_static! {
+    //
+    // sessions, ports
+    //
+
+    // state: driver status, or "paused", "expired"
+    pub static ref SESSIONS: IntGaugeVec = register_int_gauge_vec!(
+        "echo_sessions",
+        "active sessions by state and media type",
+        &["state", "media_type"]
+    )
+    .unwrap();
+
+    // state: "free", "leased"
+    pub static ref PUBLISH_PORTS: IntGaugeVec = register_int_gauge_vec!(
+        "echo_publish_ports",
+        "publish ports by state",
+        &["state"]
+    )
+    .unwrap();
+
+    //
+    // ffmpeg processes
+    //
+
+    // result: "ok", "err"
+    pub static ref FFM_SPAWN_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "echo_ffmpeg_spawn_total",
+        "spawns of ffmpeg process",
+        &["ffm_type", "result"]
+    )
+    .unwrap();
+
+    pub static ref FFM_RESPAWN_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "echo_ffmpeg_respawn_total",
+        "respawns of exited ffmpeg stage",
+        &["ffm_type"]
+    )
+    .unwrap();
+
+    pub static ref FFM_TERM_DURATION: HistogramVec = register_histogram_vec!(
+        "echo_ffmpeg_term_duration_seconds",
+        "from termination signal until ffmpeg process is exited",
+        &["ffm_type"],
+        vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]
+    )
+    .unwrap();
+
+    // FFMpegTermTimeout, process is killed
+    pub static ref FFM_TERM_TIMEOUT_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "echo_ffmpeg_term_timeout_total",
+        "ffmpeg processes not exited by termination signal in time",
+        &["ffm_type"]
+    )
+    .unwrap();
+
+    // driver of session is restarted by RecvWorker
+    pub static ref SESSION_RESPAWN_TOTAL: IntCounter = register_int_counter!(
+        "echo_session_respawn_total",
+        "restarts of session driver"
+    )
+    .unwrap();
+
+    //
+    // comm_ps
+    //
+
+    // signal: "TERM", "STOP", "CONT"
+    pub static ref PROC_SIGNAL_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "echo_proc_signal_total",
+        "signals sent to processes",
+        &["signal", "result"]
+    )
+    .unwrap();
+
+    pub static ref PROC_POLL_EXIT_TIMEOUT_TOTAL: IntCounter = register_int_counter!(
+        "echo_proc_poll_exit_timeout_total",
+        "processes still running after polling exit status"
+    )
+    .unwrap();
+
+    //
+    // ingest, hls, recording
+    //
+
+    pub static ref INGEST_BITRATE_KBPS: GaugeVec = register_gauge_vec!(
+        "echo_session_ingest_bitrate_kbps",
+        "ingest bitrate of receiver",
+        &["app_name"]
+    )
+    .unwrap();
+
+    pub static ref INGEST_FPS: GaugeVec = register_gauge_vec!(
+        "echo_session_ingest_fps",
+        "ingest fps of receiver",
+        &["app_name"]
+    )
+    .unwrap();
+
+    // from segment is closed by receiver(mtime) until it's listed on live playlist, ffmpeg::playlist
+    pub static ref HLS_SEGMENT_WRITE_LATENCY: HistogramVec = register_histogram_vec!(
+        "echo_hls_segment_write_latency_seconds",
+        "latency of new hls segment until listed on playlist",
+        &["media_type"],
+        vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0]
+    )
+    .unwrap();
+
+    pub static ref REC_BYTES_WRITTEN_TOTAL: IntCounter = register_int_counter!(
+        "echo_recording_bytes_written_total",
+        "bytes written to recording files(_NEW)"
+    )
+    .unwrap();
+
+    // kind: "corrupt", "quarantined", "crc", "gap", "format_change"
+    pub static ref AUDIO_SEGMENT_ISSUE_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "echo_audio_segment_issue_total",
+        "issues of adts in hls segments of audio sessions",
+        &["kind"]
+    )
+    .unwrap();
+
+    // finalized recordings(_FIN) not yet archived(_ARC)
+    pub static ref REC_ARCHIVE_QUEUE: IntGauge = register_int_gauge!(
+        "echo_recording_archive_queue",
+        "recordings waiting for archive"
+    )
+    .unwrap();
+}
+
+//
+// collector
+//
+
+// refreshes gauges from manager and recording directory
+pub async fn run_metrics_collector(
+    manager_msg_send: RecvWorkerManagerMsgSend,
+    rec_root_dir: PathBuf,
+) {
+    let mut interval = tokio::time::interval(Duration::from_secs(METRICS_COLLECT_INTERVAL_SEC));
+
+    loop {
+        interval.tick().await;
+
+        let (responder, rst) = tokio::sync::oneshot::channel();
+
+        if manager_msg_send
+            .send(RecvWorkerManagerMsg::QueryRecvWorkerStates(responder))
+            .is_err()
+        {
+            break;
+        }
+
+        match rst.await {
+            Ok(Ok(x)) => update_session_gauges(&x),
+            Ok(Err(e)) => log::error!(
+                "[metrics::run_metrics_collector] failed to query worker states, e={}",
+                e.to_string()
+            ),
+            // manager is dropped before answering, respawned one takes over channel
+            Err(_) => continue,
+        }
+
+        let (responder, rst) = tokio::sync::oneshot::channel();
+
+        if manager_msg_send
+            .send(RecvWorkerManagerMsg::QueryCapacity(responder))
+            .is_err()
+        {
+            break;
+        }
+
+        match rst.await {
+            Ok(Ok(x)) => {
+                PUBLISH_PORTS
+                    .with_label_values(&["free"])
+                    .set(x.avail_publish_ports as i64);
+                PUBLISH_PORTS
+                    .with_label_values(&["leased"])
+                    .set(x.leased_publish_ports as i64);
+            }
+            Ok(Err(e)) => log::error!(
+                "[metrics::run_metrics_collector] failed to query capacity, e={}",
+                e.to_string()
+            ),
+            Err(_) => continue,
+        }
+
+        REC_ARCHIVE_QUEUE.set(count_unarchived_recordings(&rec_root_dir).await as i64);
+    }
+
+    log::debug!("[metrics::run_metrics_collector] exited");
+}
+
+// ended sessions are removed from labeled gauges
+fn update_session_gauges(snapshots: &[RecvWorkerStateSnapshot]) {
+    SESSIONS.reset();
+    INGEST_BITRATE_KBPS.reset();
+    INGEST_FPS.reset();
+
+    for x in snapshots {
+        let state = if x.paused.is_some() {
+            String::from("paused")
+        } else if x.expired {
+            String::from("expired")
+        } else {
+            x.driver_status.to_string()
+        };
+
+        SESSIONS
+            .with_label_values(&[state.as_str(), x.media_type.to_string().as_str()])
+            .inc();
+
+        if let Some(ingest) = x.ingest.as_ref() {
+            INGEST_BITRATE_KBPS
+                .with_label_values(&[x.app_name.as_str()])
+                .set(ingest.bitrate_kbps as f64);
+            INGEST_FPS
+                .with_label_values(&[x.app_name.as_str()])
+                .set(ingest.fps as f64);
+        }
+    }
+}
+
+// '{rec_root}/{app_name}/{name}_FIN[.ext]'
+async fn count_unarchived_recordings(rec_root_dir: &PathBuf) -> usize {
+    let mut cnt = 0;
+
+    let mut app_dirs = match tokio::fs::read_dir(rec_root_dir).await {
+        Ok(x) => x,
+        Err(_) => return 0,
+    };
+
+    while let Ok(Some(app_dir)) = app_dirs.next_entry().await {
+        let rec_out_path = app_dir.path().to_string_lossy().to_string();
+
+        cnt += store::count_record_files(&rec_out_path, RecFileStatus::FIN).await;
+    }
+
+    cnt
+}
+
+//
+// endpoint
+//
+
+// GET handler of api server, prometheus text format
+// > route: web::get().to(metrics::metrics_handler)
+pub async fn metrics_handler() -> HttpResponse {
+    let mut buf = Vec::new();
+
+    if let Err(e) = TextEncoder::new().encode(&prometheus::gather(), &mut buf) {
+        log::error!("[metrics::metrics_handler] failed to encode, e={}", e);
+
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    HttpResponse::Ok()
+        .content_type(prometheus::TEXT_FORMAT)
+        .body(buf)
+}
@@ -0,0 +1,300 @@
+// runner::admission
+// > admission control of new session(publish), and capacity(headroom) of this host
+use crate::comm_media::MediaType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+// json of AdmissionPolicy, unset: no limit
+// > ex) {"max_sessions":100,"max_sessions_per_media_type":{"video":20},"min_free_disk_mb":2048}
+pub const ADMISSION_POLICY_ENV: &str = "ECHO_ADMISSION_POLICY";
+
+//
+// rejection
+//
+
+#[derive(Error, Debug, Clone, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum AdmissionRejection {
+    #[error("max sessions is reached, limit={limit}")]
+    MaxSessions { limit: usize },
+
+    #[error("max sessions of media type is reached, media_type={media_type}, limit={limit}")]
+    MaxSessionsOfMediaType { media_type: MediaType, limit: usize },
+
+    #[error("max sessions of profile is reached, profile={profile}, limit={limit}")]
+    MaxSessionsOfProfile { profile: String, limit: usize },
+
+    #[error("max sessions of app_name prefix is reached, prefix={prefix}, limit={limit}")]
+    MaxSessionsOfPrefix { prefix: String, limit: usize },
+
+    #[error("cpu load is too high, load_per_cpu={load_per_cpu:.2}, limit={limit:.2}")]
+    CpuLoad { load_per_cpu: f64, limit: f64 },
+
+    #[error("free disk is too low, path={path}, free_mb={free_mb}, limit_mb={limit_mb}")]
+    DiskSpace {
+        path: String,
+        free_mb: u64,
+        limit_mb: u64,
+    },
+
+    #[error("no available publish port")]
+    NoPublishPort,
+}
+
+//
+// policy
+//
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdmissionPolicy {
+    pub max_sessions: Option<usize>,
+    pub max_sessions_per_media_type: HashMap<MediaType, usize>,
+
+    // key: receiver args of publish request, sessions with same args share transcoding settings
+    pub max_sessions_per_profile: HashMap<String, usize>,
+
+    // key: app_name prefix(tenant)
+    pub max_sessions_per_prefix: HashMap<String, usize>,
+
+    // load average(1 min) / cpu count
+    pub max_load_per_cpu: Option<f64>,
+
+    // checked on echo_hls_root_dir, and echo_rec_root_dir if recording is enabled
+    pub min_free_disk_mb: Option<u64>,
+}
+
+impl AdmissionPolicy {
+    pub fn from_env() -> Self {
+        let policy_json = match std::env::var(ADMISSION_POLICY_ENV) {
+            Ok(x) => x,
+            Err(_) => return Self::default(),
+        };
+
+        serde_json::from_str(&policy_json).unwrap_or_else(|e| {
+            log::error!(
+                "[AdmissionPolicy::from_env] invalid {}, admission is not limited, e={}",
+                ADMISSION_POLICY_ENV,
+                e.to_string()
+            );
+
+            Self::default()
+        })
+    }
+}
+
+//
+// session counting
+//
+
+// attributes of running session, counted against limits
+pub struct AdmissionSession<'a> {
+    pub app_name: &'a str,
+    pub media_type: MediaType,
+    pub profile: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CapacityUsage {
+    pub key: String,
+    pub sessions: usize,
+    pub limit: usize,
+    pub remain: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskUsage {
+    pub path: String,
+    pub free_mb: Option<u64>,
+}
+
+// answer of RecvWorkerManagerMsg::QueryCapacity
+// > remain_sessions: min of headroom by max_sessions and publish ports
+#[derive(Debug, Clone, Serialize)]
+pub struct CapacityReport {
+    pub accepting: bool,
+    pub rejection: Option<AdmissionRejection>,
+
+    pub sessions: usize,
+    pub max_sessions: Option<usize>,
+    pub remain_sessions: usize,
+    pub avail_publish_ports: usize,
+    // publish and backup ports held by running sessions
+    pub leased_publish_ports: usize,
+
+    pub per_media_type: Vec<CapacityUsage>,
+    pub per_profile: Vec<CapacityUsage>,
+    pub per_prefix: Vec<CapacityUsage>,
+
+    pub load_per_cpu: Option<f64>,
+    pub max_load_per_cpu: Option<f64>,
+
+    pub disks: Vec<DiskUsage>,
+    pub min_free_disk_mb: Option<u64>,
+}
+
+// check counting limits for a new session
+pub fn check_session_limits(
+    policy: &AdmissionPolicy,
+    sessions: &[AdmissionSession],
+    new_session: &AdmissionSession,
+) -> Result<(), AdmissionRejection> {
+    if let Some(limit) = policy.max_sessions {
+        if sessions.len() >= limit {
+            return Err(AdmissionRejection::MaxSessions { limit });
+        }
+    }
+
+    if let Some(limit) = policy
+        .max_sessions_per_media_type
+        .get(&new_session.media_type)
+    {
+        let cnt = sessions
+            .iter()
+            .filter(|x| x.media_type == new_session.media_type)
+            .count();
+
+        if cnt >= *limit {
+            return Err(AdmissionRejection::MaxSessionsOfMediaType {
+                media_type: new_session.media_type,
+                limit: *limit,
+            });
+        }
+    }
+
+    if let Some(limit) = policy.max_sessions_per_profile.get(new_session.profile) {
+        let cnt = sessions
+            .iter()
+            .filter(|x| x.profile == new_session.profile)
+            .count();
+
+        if cnt >= *limit {
+            return Err(AdmissionRejection::MaxSessionsOfProfile {
+                profile: new_session.profile.to_string(),
+                limit: *limit,
+            });
+        }
+    }
+
+    for (prefix, limit) in policy.max_sessions_per_prefix.iter() {
+        if !new_session.app_name.starts_with(prefix.as_str()) {
+            continue;
+        }
+
+        let cnt = sessions
+            .iter()
+            .filter(|x| x.app_name.starts_with(prefix.as_str()))
+            .count();
+
+        if cnt >= *limit {
+            return Err(AdmissionRejection::MaxSessionsOfPrefix {
+                prefix: prefix.clone(),
+                limit: *limit,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// check resource limits of host, (load_per_cpu, disks) are measured ones
+pub fn check_resource_limits(
+    policy: &AdmissionPolicy,
+    load_per_cpu: Option<f64>,
+    disks: &[DiskUsage],
+) -> Result<(), AdmissionRejection> {
+    if let (Some(limit), Some(load)) = (policy.max_load_per_cpu, load_per_cpu) {
+        if load > limit {
+            return Err(AdmissionRejection::CpuLoad {
+                load_per_cpu: load,
+                limit,
+            });
+        }
+    }
+
+    if let Some(limit_mb) = policy.min_free_disk_mb {
+        for disk in disks.iter() {
+            if let Some(free_mb) = disk.free_mb {
+                if free_mb < limit_mb {
+                    return Err(AdmissionRejection::DiskSpace {
+                        path: disk.path.clone(),
+                        free_mb,
+                        limit_mb,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// usage of each limited key, ordered by key
+pub fn collect_usages<F>(
+    limits: &HashMap<String, usize>,
+    sessions: &[AdmissionSession],
+    is_counted: F,
+) -> Vec<CapacityUsage>
+where
+    F: Fn(&str, &AdmissionSession) -> bool,
+{
+    let mut usages: Vec<CapacityUsage> = limits
+        .iter()
+        .map(|(key, limit)| {
+            let cnt = sessions.iter().filter(|x| is_counted(key, x)).count();
+
+            CapacityUsage {
+                key: key.clone(),
+                sessions: cnt,
+                limit: *limit,
+                remain: limit.saturating_sub(cnt),
+            }
+        })
+        .collect();
+
+    usages.sort_by(|a, b| a.key.cmp(&b.key));
+
+    usages
+}
+
+//
+// host resources
+//
+
+// load average(1 min) / cpu count, from /proc/loadavg
+pub fn measure_load_per_cpu() -> Option<f64> {
+    let loadavg = std::fs::read_to_string("/proc/loadavg").ok()?;
+    let load_1min: f64 = loadavg.split_whitespace().next()?.parse().ok()?;
+
+    let cpu_cnt = std::thread::available_parallelism()
+        .map(|x| x.get())
+        .unwrap_or(1);
+
+    Some(load_1min / cpu_cnt as f64)
+}
+
+// free disk of filesystem which path is on, 'df -Pk {path}'
+// > runs child process and blocks, call it on blocking thread(tokio::task::spawn_blocking)
+pub fn measure_free_disk_mb(path: &str) -> Option<u64> {
+    let output = std::process::Command::new("df")
+        .args(["-Pk", path])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    // Filesystem 1024-blocks Used Available Capacity Mounted-on
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let avail_kb: u64 = stdout
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse()
+        .ok()?;
+
+    Some(avail_kb / 1024)
+}
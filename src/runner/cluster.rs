@@ -0,0 +1,659 @@
+// runner::cluster
+// > optional cluster mode, nodes register themselves and claim ownership of app_name on redis
+use super::admission::CapacityReport;
+use super::event_bus;
+use super::message::{RecvWorkerManagerMsg, RecvWorkerManagerMsgSend, TerminateRecvWorkerPrmType};
+use super::RunnerError;
+use crate::comm::{EchoArc, EchoUtc, PrmJson};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// "redis://{host}:{port}/{db}", unset: standalone
+pub const CLUSTER_REDIS_ENV: &str = "ECHO_CLUSTER_REDIS";
+// default: publish ip
+pub const CLUSTER_NODE_ID_ENV: &str = "ECHO_CLUSTER_NODE_ID";
+
+// {prefix}:nodes                      set of node_id
+// {prefix}:node:{node_id}             ClusterNode json, expires without heartbeat
+// {prefix}:node:{node_id}:sessions    set of app_name claimed by node
+// {prefix}:session:{app_name}         node_id of owner, expires without heartbeat
+pub const CLUSTER_KEY_PREFIX: &str = "echo:cluster";
+
+pub const CLUSTER_HEARTBEAT_INTERVAL_SEC: u64 = 5;
+// node and its sessions are dead after missing heartbeats
+pub const CLUSTER_NODE_TTL_SEC: u64 = 15;
+// capacity of busy manager is not waited, last one is published
+pub const CLUSTER_NODE_STATE_TIMEOUT_MS: u64 = 2000;
+
+//
+// node
+//
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterNode {
+    pub node_id: String,
+    pub publish_ip: String,
+    pub heartbeat_epoch: i64,
+
+    // runner::admission::CapacityReport
+    pub capacity: PrmJson,
+    // app_name of active sessions
+    pub sessions: Vec<String>,
+}
+
+// answer of RecvWorkerManagerMsg::QueryNodeState, published by heartbeat
+// > sessions are tracked by ClusterRegistry, claims are renewed without manager
+#[derive(Debug, Clone)]
+pub struct NodeState {
+    pub capacity: CapacityReport,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionClaim {
+    Claimed,
+    // node_id of owner
+    OwnedBy(String),
+}
+
+//
+// lua scripts, executed atomically by redis
+//
+
+// KEYS[1]: session key, KEYS[2]: sessions of node
+// ARGV[1]: node_id, ARGV[2]: ttl_ms, ARGV[3]: app_name
+// > owner is returned, claimed if it is node_id
+const LUA_CLAIM_SESSION: &str = r"
+local owner = redis.call('GET', KEYS[1])
+if owner and owner ~= ARGV[1] then
+    return owner
+end
+redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2])
+redis.call('SADD', KEYS[2], ARGV[3])
+redis.call('PEXPIRE', KEYS[2], ARGV[2])
+return ARGV[1]
+";
+
+// KEYS[1]: session key, KEYS[2]: sessions of node
+// ARGV[1]: node_id, ARGV[2]: app_name
+const LUA_RELEASE_SESSION: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    redis.call('DEL', KEYS[1])
+end
+redis.call('SREM', KEYS[2], ARGV[2])
+return 1
+";
+
+// KEYS[1]: nodes, KEYS[2]: node key, KEYS[3]: sessions of node, KEYS[4..]: session key
+// ARGV[1]: node_id, ARGV[2]: ttl_ms, ARGV[3]: node json, ARGV[4..]: app_name of KEYS[4..]
+// > claims are renewed, expired ones are claimed again, sessions claimed by other node are returned
+const LUA_HEARTBEAT: &str = r"
+redis.call('SADD', KEYS[1], ARGV[1])
+redis.call('SET', KEYS[2], ARGV[3], 'PX', ARGV[2])
+local lost = {}
+for i = 4, #KEYS do
+    local owner = redis.call('GET', KEYS[i])
+    if owner and owner ~= ARGV[1] then
+        table.insert(lost, ARGV[i])
+    else
+        redis.call('SET', KEYS[i], ARGV[1], 'PX', ARGV[2])
+        redis.call('SADD', KEYS[3], ARGV[i])
+    end
+end
+redis.call('PEXPIRE', KEYS[3], ARGV[2])
+return lost
+";
+
+// KEYS[1]: nodes, KEYS[2]: node key, KEYS[3]: sessions of node, KEYS[4..]: session key
+// ARGV[1]: node_id, ARGV[2..]: app_name of KEYS[4..], members of KEYS[3] read before
+// > sessions of dead node are expired and returned, alive node is not touched
+// > node is removed once no session is left, members added meanwhile are swept next time
+const LUA_SWEEP_NODE: &str = r"
+if redis.call('EXISTS', KEYS[2]) == 1 then
+    return {}
+end
+local apps = {}
+for i = 4, #KEYS do
+    if redis.call('GET', KEYS[i]) == ARGV[1] then
+        redis.call('DEL', KEYS[i])
+    end
+    redis.call('SREM', KEYS[3], ARGV[i - 2])
+    table.insert(apps, ARGV[i - 2])
+end
+if redis.call('SCARD', KEYS[3]) == 0 then
+    redis.call('SREM', KEYS[1], ARGV[1])
+end
+return apps
+";
+
+//
+// registry
+// > blocking connection on blocking thread pool
+//
+
+pub struct ClusterRegistry {
+    client: redis::Client,
+    node_id: String,
+    publish_ip: String,
+
+    // app_name of sessions running on this node, claims are renewed by heartbeat
+    sessions: Mutex<BTreeSet<String>>,
+}
+
+pub type ClusterRegistryShared = EchoArc<ClusterRegistry>;
+
+impl ClusterRegistry {
+    // by CLUSTER_REDIS_ENV, none: standalone
+    pub fn from_env(publish_ip: &str) -> Option<ClusterRegistryShared> {
+        let url = std::env::var(CLUSTER_REDIS_ENV).ok()?;
+        let node_id = std::env::var(CLUSTER_NODE_ID_ENV).unwrap_or(publish_ip.to_string());
+
+        match Self::new(&url, &node_id, publish_ip) {
+            Ok(x) => Some(EchoArc::new(x)),
+            Err(e) => {
+                log::error!(
+                    "[ClusterRegistry] failed to create cluster registry, running standalone, e={}",
+                    e.to_string()
+                );
+                None
+            }
+        }
+    }
+
+    pub fn new(url: &str, node_id: &str, publish_ip: &str) -> Result<Self, RunnerError> {
+        let client = redis::Client::open(url)
+            .map_err(|e| RunnerError::ClusterErr(format!("url={}, e={}", url, e)))?;
+
+        Ok(Self {
+            client,
+            node_id: node_id.to_string(),
+            publish_ip: publish_ip.to_string(),
+            sessions: Mutex::new(BTreeSet::new()),
+        })
+    }
+
+    pub fn node_id(&self) -> &str {
+        self.node_id.as_str()
+    }
+
+    fn nodes_key() -> String {
+        format!("{}:nodes", CLUSTER_KEY_PREFIX)
+    }
+
+    fn node_key(node_id: &str) -> String {
+        format!("{}:node:{}", CLUSTER_KEY_PREFIX, node_id)
+    }
+
+    fn node_sessions_key(node_id: &str) -> String {
+        format!("{}:node:{}:sessions", CLUSTER_KEY_PREFIX, node_id)
+    }
+
+    fn session_key(app_name: &str) -> String {
+        format!("{}:session:{}", CLUSTER_KEY_PREFIX, app_name)
+    }
+
+    fn ttl_ms() -> u64 {
+        CLUSTER_NODE_TTL_SEC * 1000
+    }
+
+    async fn query<T, F>(&self, f: F) -> Result<T, RunnerError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut redis::Connection) -> redis::RedisResult<T> + Send + 'static,
+    {
+        let client = self.client.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut conn = client.get_connection()?;
+            f(&mut conn)
+        })
+        .await
+        .map_err(|e| RunnerError::ClusterErr(e.to_string()))?
+        .map_err(|e| RunnerError::ClusterErr(format!("node_id={}, e={}", self.node_id, e)))
+    }
+
+    //
+    // session ownership
+    //
+
+    // claimed atomically, re-claim of own session succeeds
+    pub async fn claim_session(&self, app_name: &str) -> Result<SessionClaim, RunnerError> {
+        let node_id = self.node_id.clone();
+        let app_name = app_name.to_string();
+        let app_name_claimed = app_name.clone();
+
+        let owner: String = self
+            .query(move |conn| {
+                redis::Script::new(LUA_CLAIM_SESSION)
+                    .key(Self::session_key(&app_name))
+                    .key(Self::node_sessions_key(&node_id))
+                    .arg(&node_id)
+                    .arg(Self::ttl_ms())
+                    .arg(&app_name)
+                    .invoke(conn)
+            })
+            .await?;
+
+        if owner == self.node_id {
+            self.track_session(&app_name_claimed);
+            Ok(SessionClaim::Claimed)
+        } else {
+            Ok(SessionClaim::OwnedBy(owner))
+        }
+    }
+
+    // running on this node without claim(ex: recovered), claimed by next heartbeat
+    pub fn track_session(&self, app_name: &str) {
+        lock_sessions(&self.sessions).insert(app_name.to_string());
+    }
+
+    // claim of other node is not touched
+    pub async fn release_session(&self, app_name: &str) -> Result<(), RunnerError> {
+        let node_id = self.node_id.clone();
+        let app_name = app_name.to_string();
+
+        // not renewed by heartbeat even if release fails, claim expires by itself
+        lock_sessions(&self.sessions).remove(&app_name);
+
+        self.query(move |conn| {
+            redis::Script::new(LUA_RELEASE_SESSION)
+                .key(Self::session_key(&app_name))
+                .key(Self::node_sessions_key(&node_id))
+                .arg(&node_id)
+                .arg(&app_name)
+                .invoke(conn)
+        })
+        .await
+    }
+
+    //
+    // node
+    //
+
+    // node and claims of sessions are renewed, app_name claimed by other node is returned
+    // > lost sessions are not tracked anymore
+    pub async fn heartbeat(&self, state: NodeState) -> Result<Vec<String>, RunnerError> {
+        let sessions: Vec<String> = lock_sessions(&self.sessions).iter().cloned().collect();
+
+        let node = ClusterNode {
+            node_id: self.node_id.clone(),
+            publish_ip: self.publish_ip.clone(),
+            heartbeat_epoch: EchoUtc::now().timestamp(),
+            capacity: serde_json::to_value(&state.capacity)
+                .map_err(|e| RunnerError::ClusterErr(e.to_string()))?,
+            sessions,
+        };
+
+        let node_json =
+            serde_json::to_string(&node).map_err(|e| RunnerError::ClusterErr(e.to_string()))?;
+
+        let lost: Vec<String> = self
+            .query(move |conn| {
+                let script = redis::Script::new(LUA_HEARTBEAT);
+                let mut invocation = script.key(Self::nodes_key());
+
+                invocation
+                    .key(Self::node_key(&node.node_id))
+                    .key(Self::node_sessions_key(&node.node_id))
+                    .arg(&node.node_id)
+                    .arg(Self::ttl_ms())
+                    .arg(node_json);
+
+                for app_name in node.sessions.iter() {
+                    invocation.key(Self::session_key(app_name)).arg(app_name);
+                }
+
+                invocation.invoke(conn)
+            })
+            .await?;
+
+        let mut sessions = lock_sessions(&self.sessions);
+
+        for app_name in lost.iter() {
+            sessions.remove(app_name);
+        }
+
+        Ok(lost)
+    }
+
+    // sessions of dead nodes(heartbeat expired) are expired
+    // > (node_id, app_names) of swept nodes are returned
+    pub async fn sweep_dead_nodes(&self) -> Result<Vec<(String, Vec<String>)>, RunnerError> {
+        let node_ids: Vec<String> = self
+            .query(|conn| redis::cmd("SMEMBERS").arg(Self::nodes_key()).query(conn))
+            .await?;
+
+        let mut swept = Vec::new();
+
+        for node_id in node_ids.into_iter().filter(|x| *x != self.node_id) {
+            let apps = self.sweep_node(node_id.clone()).await?;
+
+            if !apps.is_empty() {
+                swept.push((node_id, apps));
+            }
+        }
+
+        Ok(swept)
+    }
+
+    async fn sweep_node(&self, node_id: String) -> Result<Vec<String>, RunnerError> {
+        self.query(move |conn| {
+            // session keys are passed in KEYS, members are read first
+            let apps: Vec<String> = redis::cmd("SMEMBERS")
+                .arg(Self::node_sessions_key(&node_id))
+                .query(conn)?;
+
+            let script = redis::Script::new(LUA_SWEEP_NODE);
+            let mut invocation = script.key(Self::nodes_key());
+
+            invocation
+                .key(Self::node_key(&node_id))
+                .key(Self::node_sessions_key(&node_id))
+                .arg(&node_id);
+
+            for app_name in apps.iter() {
+                invocation.key(Self::session_key(app_name)).arg(app_name);
+            }
+
+            invocation.invoke(conn)
+        })
+        .await
+    }
+
+    // node leaves cluster, claims of its sessions are released
+    pub async fn deregister(&self) -> Result<Vec<String>, RunnerError> {
+        let node_id = self.node_id.clone();
+
+        let _: () = self
+            .query(move |conn| redis::cmd("DEL").arg(Self::node_key(&node_id)).query(conn))
+            .await?;
+
+        self.sweep_node(self.node_id.clone()).await
+    }
+
+    // alive nodes, ordered by node_id
+    pub async fn list_nodes(&self) -> Result<Vec<ClusterNode>, RunnerError> {
+        let node_ids: Vec<String> = self
+            .query(|conn| redis::cmd("SMEMBERS").arg(Self::nodes_key()).query(conn))
+            .await?;
+
+        if node_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let node_jsons: Vec<Option<String>> = self
+            .query(move |conn| {
+                let mut cmd = redis::cmd("MGET");
+
+                for node_id in node_ids.iter() {
+                    cmd.arg(Self::node_key(node_id));
+                }
+
+                cmd.query(conn)
+            })
+            .await?;
+
+        let mut nodes: Vec<ClusterNode> = node_jsons
+            .into_iter()
+            .flatten()
+            .filter_map(|x| {
+                serde_json::from_str(&x)
+                    .map_err(|e| {
+                        log::error!(
+                            "[ClusterRegistry::list_nodes] invalid node, e={}",
+                            e.to_string()
+                        )
+                    })
+                    .ok()
+            })
+            .collect();
+
+        nodes.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+
+        Ok(nodes)
+    }
+}
+
+//
+// heartbeat
+//
+
+// state of manager is published every CLUSTER_HEARTBEAT_INTERVAL_SEC, dead nodes are swept
+// > claims are renewed even while manager is busy, capacity of last answer is published
+// > sessions claimed by other node(split-brain) are terminated on this node
+// > node is deregistered when channel of manager is closed(exited on shutdown)
+pub async fn run_cluster_heartbeat(
+    registry: ClusterRegistryShared,
+    manager_msg_send: RecvWorkerManagerMsgSend,
+) {
+    log::info!(
+        "[cluster::run_cluster_heartbeat] joining cluster, node_id={}",
+        registry.node_id()
+    );
+
+    let mut interval = tokio::time::interval(Duration::from_secs(CLUSTER_HEARTBEAT_INTERVAL_SEC));
+    let mut last_state: Option<NodeState> = None;
+
+    loop {
+        interval.tick().await;
+
+        let (responder, rst) = tokio::sync::oneshot::channel();
+
+        if manager_msg_send
+            .send(RecvWorkerManagerMsg::QueryNodeState(responder))
+            .is_err()
+        {
+            break;
+        }
+
+        match tokio::time::timeout(Duration::from_millis(CLUSTER_NODE_STATE_TIMEOUT_MS), rst).await
+        {
+            Ok(Ok(Ok(x))) => last_state = Some(x),
+            Ok(Ok(Err(e))) => log::error!(
+                "[cluster::run_cluster_heartbeat] failed to query node state, e={}",
+                e.to_string()
+            ),
+            // manager is busy, or dropped before answering(respawned one takes over channel)
+            Ok(Err(_)) | Err(_) => log::warn!(
+                "[cluster::run_cluster_heartbeat] node state is not answered, last one is published"
+            ),
+        }
+
+        // not answered yet since start
+        let state = match last_state.clone() {
+            Some(x) => x,
+            None => continue,
+        };
+
+        match registry.heartbeat(state).await {
+            Ok(lost) => {
+                for app_name in lost {
+                    terminate_lost_session(&registry, &manager_msg_send, app_name);
+                }
+            }
+            Err(e) => {
+                log::error!(
+                    "[cluster::run_cluster_heartbeat] failed to heartbeat, e={}",
+                    e.to_string()
+                );
+                continue;
+            }
+        }
+
+        match registry.sweep_dead_nodes().await {
+            Ok(swept) => {
+                for (node_id, app_names) in swept {
+                    log::warn!(
+                        "[cluster::run_cluster_heartbeat] sessions of dead node are expired, \
+                        node_id={}, app_names={:?}",
+                        node_id,
+                        app_names
+                    );
+                }
+            }
+            Err(e) => log::error!(
+                "[cluster::run_cluster_heartbeat] failed to sweep dead nodes, e={}",
+                e.to_string()
+            ),
+        }
+    }
+
+    match registry.deregister().await {
+        Ok(released) => log::info!(
+            "[cluster::run_cluster_heartbeat] left cluster, node_id={}, released={:?}",
+            registry.node_id(),
+            released
+        ),
+        Err(e) => log::error!(
+            "[cluster::run_cluster_heartbeat] failed to leave cluster, e={}",
+            e.to_string()
+        ),
+    }
+}
+
+// session is claimed by other node while running here(ex: claim expired during redis outage)
+// > publisher may be on both nodes, session of this node gives way
+fn terminate_lost_session(
+    registry: &ClusterRegistryShared,
+    manager_msg_send: &RecvWorkerManagerMsgSend,
+    app_name: String,
+) {
+    log::warn!(
+        "[cluster::run_cluster_heartbeat] session is claimed by other node, terminating.., \
+        node_id={}, app_name={}",
+        registry.node_id(),
+        app_name
+    );
+
+    let (responder, rst) = tokio::sync::oneshot::channel();
+
+    if let Err(e) = manager_msg_send.send(RecvWorkerManagerMsg::TerminateRecvWorker(
+        responder,
+        TerminateRecvWorkerPrmType::AppName,
+        app_name.clone(),
+        String::new(),
+    )) {
+        log::error!(
+            "[cluster::run_cluster_heartbeat] failed to send msg 'TerminateRecvWorker', e={}",
+            e
+        );
+        return;
+    }
+
+    let node_id = registry.node_id().to_string();
+
+    // answered once worker is exited, heartbeat is not delayed
+    tokio::spawn(async move {
+        let (worker_uuid, rst_desc) = match rst.await {
+            Ok(Ok(x)) => (
+                x["stopped"][0]["worker_uuid"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                String::from("terminated"),
+            ),
+            Ok(Err(e)) => (String::new(), e.to_string()),
+            Err(e) => (String::new(), e.to_string()),
+        };
+
+        event_bus::session_event(
+            "error",
+            "cluster_session_lost",
+            &app_name,
+            &worker_uuid,
+            json!({
+                "node_id" : node_id,
+                "result" : rst_desc,
+            }),
+        );
+    });
+}
+
+fn lock_sessions(
+    sessions: &Mutex<BTreeSet<String>>,
+) -> std::sync::MutexGuard<'_, BTreeSet<String>> {
+    sessions.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// redis-backed, needs local redis-server
+// > cargo test cluster -- --ignored, ECHO_CLUSTER_REDIS overrides redis://127.0.0.1/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_registry(node_id: &str) -> ClusterRegistry {
+        let url = std::env::var(CLUSTER_REDIS_ENV).unwrap_or(String::from("redis://127.0.0.1/"));
+        ClusterRegistry::new(&url, node_id, "127.0.0.1").unwrap()
+    }
+
+    fn test_state() -> NodeState {
+        NodeState {
+            capacity: CapacityReport {
+                accepting: true,
+                rejection: None,
+                sessions: 0,
+                max_sessions: None,
+                remain_sessions: 1,
+                avail_publish_ports: 1,
+                leased_publish_ports: 0,
+                per_media_type: Vec::new(),
+                per_profile: Vec::new(),
+                per_prefix: Vec::new(),
+                load_per_cpu: None,
+                max_load_per_cpu: None,
+                disks: Vec::new(),
+                min_free_disk_mb: None,
+            },
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn claim_heartbeat_and_sweep() {
+        let suffix = EchoUtc::now().timestamp_millis();
+        let node_a = test_registry(&format!("test-a-{}", suffix));
+        let node_b = test_registry(&format!("test-b-{}", suffix));
+        let app_name = format!("test-app-{}", suffix);
+
+        assert_eq!(
+            node_a.claim_session(&app_name).await.unwrap(),
+            SessionClaim::Claimed
+        );
+        assert_eq!(
+            node_b.claim_session(&app_name).await.unwrap(),
+            SessionClaim::OwnedBy(node_a.node_id().to_string())
+        );
+
+        // split-brain, session runs on both nodes
+        node_b.track_session(&app_name);
+
+        assert!(node_a.heartbeat(test_state()).await.unwrap().is_empty());
+        assert_eq!(
+            node_b.heartbeat(test_state()).await.unwrap(),
+            vec![app_name.clone()]
+        );
+        // lost session is not renewed anymore
+        assert!(node_b.heartbeat(test_state()).await.unwrap().is_empty());
+
+        let node_ids: Vec<String> = node_b
+            .list_nodes()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|x| x.node_id)
+            .collect();
+        assert!(node_ids.contains(&node_a.node_id().to_string()));
+
+        // released by leaving node, claimable by other
+        assert_eq!(node_a.deregister().await.unwrap(), vec![app_name.clone()]);
+        assert_eq!(
+            node_b.claim_session(&app_name).await.unwrap(),
+            SessionClaim::Claimed
+        );
+
+        node_b.release_session(&app_name).await.unwrap();
+        assert!(node_b.deregister().await.unwrap().is_empty());
+    }
+}
@@ -1,16 +1,18 @@
 use super::ffmpeg;
-use super::DriverType;
+use super::mock;
 use super::RunnerError;
-use super::{Driver, DriverRst, DriverRstOk, DriverStatus};
-use crate::comm::*;
-use crate::comm_media::PropMedia;
-use crate::config::Config;
-use crate::runner::message::RecvWorkerMsgSend;
-use crate::service::api::reqres::publish::ReqPropReceiverPrm;
+use super::{
+    Driver, DriverCapabilities, DriverCreatePrm, DriverRst, DriverRstOk, DriverStats, DriverStatus,
+    PFnNewReceiverDriver, PauseTarget, ReceiverDriver, ReceiverDriverBox,
+};
+use crate::comm_media::MediaReceiver;
+use async_trait::async_trait;
+use std::collections::HashMap;
 use tokio::time::Duration;
 
-impl Driver {
-    pub async fn begin(&mut self) -> DriverRst {
+#[async_trait]
+impl ReceiverDriver for Driver {
+    async fn begin(&mut self) -> DriverRst {
         if self.status == DriverStatus::Creating {
             return Err(RunnerError::RecvDriverErr(format!("already creating")));
         }
@@ -24,16 +26,14 @@ impl Driver {
 
         self.status = DriverStatus::Creating;
 
-        if self.runner_ffmpeg.is_some() {
-            self.runner_ffmpeg.as_mut().unwrap().begin().await?;
-        }
+        self.backend.begin().await?;
 
         self.status = DriverStatus::ReceivingPublishStream;
 
         Ok(DriverRstOk::Ok)
     }
 
-    pub async fn tick(&mut self) -> DriverRst {
+    async fn tick(&mut self) -> DriverRst {
         if self.status != DriverStatus::ReceivingPublishStream {
             let em = format!(
                 "[Driver::tick] invalid calling state, status={}",
@@ -48,15 +48,10 @@ impl Driver {
         // DriverStatus::ReceivingPublishStream
         //
 
-        if self.runner_ffmpeg.is_some() {
-            let rst = self.runner_ffmpeg.as_mut().unwrap().tick().await?;
+        let rst = self.backend.tick().await?;
 
-            match rst {
-                DriverRstOk::Finished(do_respawn) => {
-                    return Ok(rst);
-                }
-                _ => {}
-            };
+        if let DriverRstOk::Finished(_) = rst {
+            return Ok(rst);
         }
 
         tokio::time::sleep(Duration::from_millis(100)).await;
@@ -64,7 +59,7 @@ impl Driver {
         Ok(DriverRstOk::Ok)
     }
 
-    pub async fn end(&mut self) -> DriverRst {
+    async fn end(&mut self) -> DriverRst {
         match self.status {
             DriverStatus::End => {
                 return Ok(DriverRstOk::Ok);
@@ -85,70 +80,142 @@ impl Driver {
 
         self.status = DriverStatus::Ending;
 
-        if self.runner_ffmpeg.is_some() {
-            self.runner_ffmpeg.as_mut().unwrap().end().await?;
-        }
+        self.backend.end().await?;
 
         self.status = DriverStatus::End;
 
         Ok(DriverRstOk::Ok)
     }
 
-    pub async fn restart(&mut self) -> DriverRst {
+    async fn reset(&mut self) -> DriverRst {
+        self.backend.reset().await?;
+
+        self.status = DriverStatus::Init;
+
+        Ok(DriverRstOk::Ok)
+    }
+
+    async fn restart(&mut self) -> DriverRst {
         log::debug!("[Driver::restart]");
 
         self.end().await?;
 
         self.status = DriverStatus::Restarting;
 
-        if self.runner_ffmpeg.is_some() {
-            self.runner_ffmpeg.as_mut().unwrap().reset().await?;
-        }
-
-        self.status = DriverStatus::Init;
+        self.reset().await?;
 
         self.begin().await?;
 
+        self.restart_cnt += 1;
+
         Ok(DriverRstOk::Ok)
     }
+
+    async fn stats(&self) -> DriverStats {
+        let mut stats = self.backend.stats().await;
+
+        stats.status = self.status.clone();
+        stats.restart_cnt = self.restart_cnt;
+
+        stats
+    }
+
+    fn capabilities(&self) -> DriverCapabilities {
+        self.backend.capabilities()
+    }
+
+    async fn pause(&mut self, target: PauseTarget) -> DriverRst {
+        if self.status != DriverStatus::ReceivingPublishStream {
+            return Err(RunnerError::RecvDriverErr(format!(
+                "[Driver::pause] invalid calling state, status={}",
+                self.status
+            )));
+        }
+
+        self.backend.pause(target).await
+    }
+
+    async fn resume(&mut self) -> DriverRst {
+        if self.status != DriverStatus::ReceivingPublishStream {
+            return Err(RunnerError::RecvDriverErr(format!(
+                "[Driver::resume] invalid calling state, status={}",
+                self.status
+            )));
+        }
+
+        self.backend.resume().await
+    }
 }
 
 //
-// runner driver
+// driver registry
 //
 
-pub fn new(
-    config: Config,
-    worker_msg_send: RecvWorkerMsgSend,
-    runner_type: DriverType,
-    runner_prms: ReqPropReceiverPrm,
-    media_prms: PropMedia,
-    app_name: String,
-    sess_key: String,
-    publish_port: EchoPort,
-) -> Result<Driver, RunnerError> {
-    match runner_type {
-        FFMpeg => {
-            let runner = ffmpeg::create_runner_ffmpeg(
-                config,
-                worker_msg_send,
-                runner_prms,
-                media_prms,
-                app_name,
-                sess_key,
-                publish_port,
+pub struct DriverRegistry {
+    // key: receiver type given by publish request
+    factories: HashMap<MediaReceiver, PFnNewReceiverDriver>,
+}
+
+impl DriverRegistry {
+    pub fn new() -> Self {
+        DriverRegistry {
+            factories: HashMap::new(),
+        }
+    }
+
+    // registry with built-in backends
+    // > MediaReceiver::RUSTRTMP, MediaReceiver::RUSTSRT are not implemented yet
+    // > MediaReceiver::MOCK is registered only by mock::MOCK_RECEIVER_ENV
+    pub fn with_defaults() -> Self {
+        let mut registry = DriverRegistry::new();
+
+        registry.register(MediaReceiver::FFMPEG, ffmpeg::new_receiver_driver);
+
+        if mock::is_enabled() {
+            registry.register(MediaReceiver::MOCK, mock::new_receiver_driver);
+        }
+
+        registry
+    }
+
+    pub fn register(&mut self, receiver: MediaReceiver, pfn_new: PFnNewReceiverDriver) {
+        if self.factories.insert(receiver, pfn_new).is_some() {
+            log::info!(
+                "[DriverRegistry] replaced receiver driver, receiver={}",
+                receiver
             );
+        }
+    }
+
+    pub fn is_registered(&self, receiver: MediaReceiver) -> bool {
+        self.factories.contains_key(&receiver)
+    }
 
-            if (runner.is_ok()) {
-                return Ok(Driver {
-                    status: DriverStatus::Init,
-                    runner_ffmpeg: Some(runner.unwrap()),
-                });
-            } else {
-                return Err(RunnerError::InternalError(format!("error")));
+    //
+    // runner driver
+    //
+
+    pub fn new_driver(
+        &self,
+        receiver: MediaReceiver,
+        create_prm: DriverCreatePrm,
+    ) -> Result<ReceiverDriverBox, RunnerError> {
+        let pfn_new = match self.factories.get(&receiver) {
+            Some(f) => f,
+            None => {
+                return Err(RunnerError::UnSupportedRunnerDriverType(format!(
+                    "{:?}",
+                    receiver
+                )))
             }
-        }
+        };
+
+        let backend = pfn_new(create_prm)?;
 
-        e => return Err(RunnerError::UnSupportedRunnerDriverType(format!("{:?}", e))),
+        Ok(Box::new(Driver {
+            status: DriverStatus::Init,
+            restart_cnt: 0,
+            backend,
+        }))
     }
 }
@@ -1,4 +1,5 @@
 // RunnerError, runner::error
+use super::admission::AdmissionRejection;
 use crate::PrmJsonValue;
 use thiserror::Error;
 
@@ -38,6 +39,9 @@ pub enum RunnerError {
     #[error("receive worker handle error: {0}")]
     RecvWorkerHandleErr(String),
 
+    #[error("receive worker is not found: {0}")]
+    RecvWorkerNotFound(String),
+
     #[error("failed to create receive worker: {0}")]
     FailedToCreateRecvWorker(String),
 
@@ -58,6 +62,31 @@ pub enum RunnerError {
 
     #[error("manager already created")]
     ManagerAlreadyCreated,
+
+    #[error("publish is rejected by admission control: {0}")]
+    AdmissionRejected(AdmissionRejection),
+
+    #[error("session store error: {0}")]
+    SessionStoreErr(String),
+
+    #[error("cluster error: {0}")]
+    ClusterErr(String),
+
+    #[error("session is owned by other node: {0}")]
+    SessionOwnedByOtherNode(String),
+
+    #[error("unsupported by ffmpeg: {0}")]
+    UnsupportedCapability(String),
+}
+
+impl RunnerError {
+    // message loop of manager can't continue, others are answered to requester and logged
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            RunnerError::RunnerMsgChanErr(..) | RunnerError::MsgChanErrChannelClosed(..)
+        )
+    }
 }
 
 //pub type RunnerError = self::Error;
@@ -0,0 +1,213 @@
+// runner::event_bus
+// > in-process broadcast of session events(same as session log), streamed to api subscribers over sse
+use super::message::{RecvWorkerManagerMsg, RecvWorkerManagerMsgSend};
+use crate::comm::{EchoUtc, PrmJson};
+use actix_web::{web, HttpResponse};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::{BroadcastStream, IntervalStream};
+use tokio_stream::StreamExt;
+
+// slow subscriber misses events beyond(lagged)
+pub const EVENT_BUS_CAPACITY: usize = 1024;
+
+// "session_stats" of every session, published while subscribed
+pub const EVENT_STATS_INTERVAL_SEC: u64 = 5;
+pub const EVENT_STATS_NAME: &str = "session_stats";
+
+// sse comment, keeps idle connection through proxies
+pub const SSE_KEEPALIVE_INTERVAL_SEC: u64 = 15;
+
+//
+// event
+//
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEvent {
+    // increasing in process, sse "id"
+    pub seq: u64,
+    pub epoch_ms: i64,
+    pub level: String,
+    pub event: String,
+    pub app_name: String,
+    pub worker_uuid: String,
+    pub data: PrmJson,
+}
+
+static EVENT_BUS: OnceLock<broadcast::Sender<SessionEvent>> = OnceLock::new();
+static EVENT_SEQ: AtomicU64 = AtomicU64::new(0);
+
+fn bus() -> &'static broadcast::Sender<SessionEvent> {
+    EVENT_BUS.get_or_init(|| broadcast::channel(EVENT_BUS_CAPACITY).0)
+}
+
+// without subscriber, event is dropped
+pub fn publish(level: &str, event: &str, app_name: &str, worker_uuid: &str, data: PrmJson) {
+    let bus = bus();
+
+    if bus.receiver_count() == 0 {
+        return;
+    }
+
+    let _ = bus.send(SessionEvent {
+        seq: EVENT_SEQ.fetch_add(1, Ordering::Relaxed) + 1,
+        epoch_ms: EchoUtc::now().timestamp_millis(),
+        level: level.to_string(),
+        event: event.to_string(),
+        app_name: app_name.to_string(),
+        worker_uuid: worker_uuid.to_string(),
+        data,
+    });
+}
+
+// session log and event bus
+pub fn session_event(level: &str, event: &str, app_name: &str, worker_uuid: &str, data: PrmJson) {
+    crate::mlog::echo::session::event(level, event, app_name, worker_uuid, data.clone());
+
+    publish(level, event, app_name, worker_uuid, data);
+}
+
+pub fn subscribe() -> broadcast::Receiver<SessionEvent> {
+    bus().subscribe()
+}
+
+pub fn subscriber_count() -> usize {
+    bus().receiver_count()
+}
+
+//
+// filter
+//
+
+// query of sse endpoint, comma separated, empty: any
+// > ex) /events?app_name=live01,live02&event=session_created,session_exit
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SessionEventFilter {
+    pub app_name: String,
+    pub event: String,
+    // "session_stats" are excluded
+    pub no_stats: bool,
+}
+
+impl SessionEventFilter {
+    pub fn matches(&self, ev: &SessionEvent) -> bool {
+        if self.no_stats && ev.event == EVENT_STATS_NAME {
+            return false;
+        }
+
+        Self::list_matches(&self.app_name, &ev.app_name)
+            && Self::list_matches(&self.event, &ev.event)
+    }
+
+    fn list_matches(list: &str, value: &str) -> bool {
+        let mut items = list
+            .split(',')
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+            .peekable();
+
+        items.peek().is_none() || items.any(|x| x == value)
+    }
+}
+
+//
+// periodic stats
+//
+
+// publishes state of every session to the bus, skipped without subscriber
+pub async fn run_session_stats(manager_msg_send: RecvWorkerManagerMsgSend) {
+    let mut interval = tokio::time::interval(Duration::from_secs(EVENT_STATS_INTERVAL_SEC));
+
+    loop {
+        interval.tick().await;
+
+        if subscriber_count() == 0 {
+            if manager_msg_send.is_closed() {
+                break;
+            }
+            continue;
+        }
+
+        let (responder, rst) = tokio::sync::oneshot::channel();
+
+        if manager_msg_send
+            .send(RecvWorkerManagerMsg::QueryRecvWorkerStates(responder))
+            .is_err()
+        {
+            break;
+        }
+
+        let snapshots = match rst.await {
+            Ok(Ok(x)) => x,
+            Ok(Err(e)) => {
+                log::error!(
+                    "[event_bus::run_session_stats] failed to query worker states, e={}",
+                    e.to_string()
+                );
+                continue;
+            }
+            // manager is dropped before answering, respawned one takes over channel
+            Err(_) => continue,
+        };
+
+        for x in snapshots {
+            let data = serde_json::to_value(&x).unwrap_or_else(|_| json!({}));
+
+            publish("info", EVENT_STATS_NAME, &x.app_name, &x.worker_uuid, data);
+        }
+    }
+
+    log::debug!("[event_bus::run_session_stats] exited");
+}
+
+//
+// sse
+//
+
+fn to_sse_frame(ev: &SessionEvent) -> Bytes {
+    let data = serde_json::to_string(ev).unwrap_or_default();
+
+    Bytes::from(format!(
+        "id: {}\nevent: {}\ndata: {}\n\n",
+        ev.seq, ev.event, data
+    ))
+}
+
+// GET handler of api server, "text/event-stream" of SessionEvent
+// > route: web::get().to(runner::event_bus::sse_session_events)
+pub async fn sse_session_events(filter: web::Query<SessionEventFilter>) -> HttpResponse {
+    let filter = filter.into_inner();
+
+    log::info!(
+        "[event_bus::sse_session_events] subscribed, app_name={}, event={}, subscribers={}",
+        filter.app_name,
+        filter.event,
+        subscriber_count() + 1
+    );
+
+    let events = BroadcastStream::new(subscribe()).filter_map(move |x| match x {
+        Ok(ev) if filter.matches(&ev) => Some(Ok::<_, actix_web::Error>(to_sse_frame(&ev))),
+        Ok(_) => None,
+        Err(BroadcastStreamRecvError::Lagged(n)) => {
+            Some(Ok(Bytes::from(format!(": lagged, missed={}\n\n", n))))
+        }
+    });
+
+    let keepalive = IntervalStream::new(tokio::time::interval(Duration::from_secs(
+        SSE_KEEPALIVE_INTERVAL_SEC,
+    )))
+    .map(|_| Ok(Bytes::from_static(b": keepalive\n\n")));
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(events.merge(keepalive))
+}
@@ -0,0 +1,583 @@
+// runner::ffmpeg::adts_inspect
+// > aac(adts) of hls segments of audio session, inspected before segments are listed on live playlist
+// > mpeg-ts is demuxed here, adts frames are validated by adts-reader
+// > corrupt segment is quarantined by policy, listed with #EXT-X-GAP(player never fetches it)
+use super::error::RunnerFFMError;
+use super::playlist::PlaylistSegment;
+use adts_reader::{AdtsHeader, ProtectionIndicator};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+// "event"(default): corrupt segment is a session event, "quarantine": moved out of playlist
+pub const ADTS_CORRUPT_POLICY_ENV: &str = "ECHO_ADTS_CORRUPT_POLICY";
+
+// '{hls_dir}/{app_name}/quarantine/{segment}'
+pub const QUARANTINE_DIR_NAME: &str = "quarantine";
+// oldest quarantined segments are removed beyond
+pub const QUARANTINE_KEEP_MAX: usize = 20;
+
+const TS_PACKET_SIZE: usize = 188;
+const TS_SYNC_BYTE: u8 = 0x47;
+const TS_PID_PAT: u16 = 0x0000;
+// ISO/IEC 13818-7 audio with ADTS transport syntax
+const TS_STREAM_TYPE_ADTS: u8 = 0x0F;
+
+const PTS_CLOCK_HZ: u64 = 90000;
+const PTS_MASK: u64 = (1 << 33) - 1;
+
+const AAC_SAMPLES_PER_FRAME: u64 = 1024;
+const ADTS_HEADER_LEN: usize = 7;
+const ADTS_CRC_LEN: usize = 2;
+// crc_check covers adts header and first 192 bits of raw_data_block
+const ADTS_CRC_RAW_BYTES: usize = 24;
+
+//
+// report
+//
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AdtsSegmentReport {
+    pub segment: String,
+    pub frames: u32,
+    // frames * 1024 / sample_rate
+    pub duration_ms: u64,
+    // #EXTINF of playlist
+    pub listed_duration_ms: Option<u64>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u8>,
+    // sample rate or channel configuration is changed from previous frame
+    pub changes: Vec<String>,
+    // frames of which crc is present
+    pub crc_checked: u32,
+    pub crc_errors: u32,
+    // pts jump(ms) between pes packets, negative: overlapped
+    pub gaps_ms: Vec<i64>,
+    // continuity counter of ts packets, lost packets
+    pub cc_errors: u32,
+    // bad sync word, invalid header, truncated frame
+    pub invalid_frames: u32,
+    pub corrupt: bool,
+    pub quarantined: bool,
+}
+
+impl AdtsSegmentReport {
+    pub fn has_issue(&self) -> bool {
+        self.corrupt || !self.changes.is_empty() || !self.gaps_ms.is_empty()
+    }
+}
+
+// totals of session, RecvWorkerStateSnapshot
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AdtsInspectStats {
+    pub segments: u64,
+    pub corrupt_segments: u64,
+    pub quarantined_segments: u64,
+    pub crc_errors: u64,
+    pub gaps: u64,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u8>,
+    pub last_duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AdtsCorruptPolicy {
+    Event,
+    Quarantine,
+}
+
+impl AdtsCorruptPolicy {
+    pub fn from_env() -> Self {
+        match std::env::var(ADTS_CORRUPT_POLICY_ENV).as_deref() {
+            Ok("quarantine") => Self::Quarantine,
+            _ => Self::Event,
+        }
+    }
+}
+
+//
+// inspector
+//
+
+pub struct AdtsSegmentInspector {
+    // dir of live playlist, uri of segment is relative to it
+    playlist_dir: PathBuf,
+    policy: AdtsCorruptPolicy,
+
+    // carried over segments
+    sample_rate: Option<u32>,
+    channels: Option<u8>,
+    next_pts: Option<u64>,
+
+    stats: AdtsInspectStats,
+}
+
+impl AdtsSegmentInspector {
+    pub fn new(playlist_dir: PathBuf, policy: AdtsCorruptPolicy) -> Self {
+        AdtsSegmentInspector {
+            playlist_dir,
+            policy,
+            sample_rate: None,
+            channels: None,
+            next_pts: None,
+            stats: AdtsInspectStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> AdtsInspectStats {
+        self.stats.clone()
+    }
+
+    // call before segments are listed, blocking(file io)
+    // > quarantined segment is tagged gap, it is listed but never fetched by player
+    pub fn inspect(&mut self, segments: &mut [PlaylistSegment]) -> Vec<AdtsSegmentReport> {
+        let mut reports = Vec::new();
+
+        // taken segment is closed by receiver
+        for segment in segments.iter_mut() {
+            let segment_path = self.playlist_dir.join(&segment.uri);
+
+            let data = match std::fs::read(&segment_path) {
+                Ok(x) => x,
+                // deleted by receiver(hls_list_size)
+                Err(_) => continue,
+            };
+
+            let mut report = self.inspect_segment(&segment.uri, &data);
+            report.listed_duration_ms = Some((segment.duration * 1000.0) as u64);
+
+            if report.corrupt && self.policy == AdtsCorruptPolicy::Quarantine {
+                match quarantine_segment(&self.playlist_dir, &segment_path) {
+                    Ok(_) => {
+                        report.quarantined = true;
+                        segment.gap = true;
+                        self.stats.quarantined_segments += 1;
+                    }
+                    Err(e) => log::error!(
+                        "[AdtsSegmentInspector::inspect] failed to quarantine segment, e={}",
+                        e.to_string()
+                    ),
+                }
+            }
+
+            reports.push(report);
+        }
+
+        reports
+    }
+
+    pub fn inspect_segment(&mut self, segment: &str, data: &[u8]) -> AdtsSegmentReport {
+        let mut report = AdtsSegmentReport {
+            segment: segment.to_string(),
+            ..Default::default()
+        };
+
+        let (es, pes_starts) = demux_adts(data, &mut report);
+
+        // offset in es and sample rate of valid frames
+        let mut frames: Vec<(usize, u32)> = Vec::new();
+        let mut pos = 0;
+        let mut resyncing = false;
+
+        while pos < es.len() {
+            if es.len() - pos < ADTS_HEADER_LEN {
+                // truncated at the end of segment
+                report.invalid_frames += 1;
+                break;
+            }
+
+            let header = match AdtsHeader::from_bytes(&es[pos..]) {
+                Ok(x) => x,
+                // bad sync word, bad frame length
+                Err(_) => {
+                    // counted once until sync word is found again
+                    if !resyncing {
+                        report.invalid_frames += 1;
+                        resyncing = true;
+                    }
+                    pos += 1;
+                    continue;
+                }
+            };
+
+            let frame_len = header.frame_length() as usize;
+
+            if pos + frame_len > es.len() {
+                // truncated at the end of segment
+                report.invalid_frames += 1;
+                break;
+            }
+
+            let sample_rate = match header.sampling_frequency().freq() {
+                Some(x) => x,
+                None => {
+                    if !resyncing {
+                        report.invalid_frames += 1;
+                        resyncing = true;
+                    }
+                    pos += 1;
+                    continue;
+                }
+            };
+
+            resyncing = false;
+
+            let channels = header.channel_configuration() as u8;
+            let frame = &es[pos..pos + frame_len];
+
+            if matches!(header.protection(), ProtectionIndicator::CrcPresent) {
+                // number_of_raw_data_blocks_in_frame, crc of multiple blocks is not checked
+                if frame[6] & 0x03 == 0 {
+                    report.crc_checked += 1;
+
+                    if !check_adts_crc(frame) {
+                        report.crc_errors += 1;
+                    }
+                }
+            }
+
+            if let Some(prev) = self.sample_rate.filter(|x| *x != sample_rate) {
+                report
+                    .changes
+                    .push(format!("sample_rate {} -> {}", prev, sample_rate));
+            }
+            if let Some(prev) = self.channels.filter(|x| *x != channels) {
+                report
+                    .changes
+                    .push(format!("channels {} -> {}", prev, channels));
+            }
+
+            self.sample_rate = Some(sample_rate);
+            self.channels = Some(channels);
+
+            frames.push((pos, sample_rate));
+            report.frames += 1;
+            report.duration_ms += AAC_SAMPLES_PER_FRAME * 1000 / sample_rate as u64;
+
+            pos += frame_len;
+        }
+
+        report.sample_rate = self.sample_rate;
+        report.channels = self.channels;
+
+        self.check_pts_gaps(&pes_starts, &frames, &mut report);
+
+        report.corrupt = report.frames == 0
+            || report.crc_errors > 0
+            || report.cc_errors > 0
+            || report.invalid_frames > 0;
+
+        self.stats.segments += 1;
+        self.stats.crc_errors += report.crc_errors as u64;
+        self.stats.gaps += report.gaps_ms.len() as u64;
+        self.stats.sample_rate = self.sample_rate;
+        self.stats.channels = self.channels;
+        self.stats.last_duration_ms = report.duration_ms;
+
+        if report.corrupt {
+            self.stats.corrupt_segments += 1;
+        }
+
+        report
+    }
+
+    // pts of each pes against pts of previous pes and its frames
+    fn check_pts_gaps(
+        &mut self,
+        pes_starts: &[(usize, Option<u64>)],
+        frames: &[(usize, u32)],
+        report: &mut AdtsSegmentReport,
+    ) {
+        for (i, (start, pts)) in pes_starts.iter().enumerate() {
+            let end = pes_starts.get(i + 1).map(|x| x.0).unwrap_or(usize::MAX);
+
+            // frames of which header starts in this pes
+            let pes_frames: Vec<u32> = frames
+                .iter()
+                .filter(|(offset, _)| offset >= start && *offset < end)
+                .map(|(_, rate)| *rate)
+                .collect();
+
+            let pts = match pts {
+                Some(x) => *x,
+                // continued from previous pes
+                None => {
+                    self.next_pts = self
+                        .next_pts
+                        .map(|x| (x + get_frames_ticks(&pes_frames)) & PTS_MASK);
+                    continue;
+                }
+            };
+
+            if let (Some(expected), Some(rate)) = (self.next_pts, self.sample_rate) {
+                // 33 bits, wrapped
+                let diff = (((pts.wrapping_sub(expected) & PTS_MASK) << 31) as i64) >> 31;
+                let frame_ticks = (AAC_SAMPLES_PER_FRAME * PTS_CLOCK_HZ / rate as u64) as i64;
+
+                if diff.abs() > frame_ticks / 2 {
+                    report.gaps_ms.push(diff * 1000 / PTS_CLOCK_HZ as i64);
+                }
+            }
+
+            self.next_pts = Some((pts + get_frames_ticks(&pes_frames)) & PTS_MASK);
+        }
+    }
+}
+
+fn get_frames_ticks(rates: &[u32]) -> u64 {
+    rates
+        .iter()
+        .map(|x| AAC_SAMPLES_PER_FRAME * PTS_CLOCK_HZ / *x as u64)
+        .sum()
+}
+
+// moved into QUARANTINE_DIR_NAME before it is listed, player ignoring #EXT-X-GAP gets 404
+fn quarantine_segment(playlist_dir: &Path, segment_path: &Path) -> Result<(), RunnerFFMError> {
+    let quarantine_dir = playlist_dir.join(QUARANTINE_DIR_NAME);
+
+    let to_err = |e: std::io::Error| {
+        RunnerFFMError::FileOperErr(format!(
+            "failed to quarantine segment, path={:?}, e={}",
+            segment_path, e
+        ))
+    };
+
+    std::fs::create_dir_all(&quarantine_dir).map_err(to_err)?;
+
+    let file_name = segment_path.file_name().unwrap_or_default();
+    std::fs::rename(segment_path, quarantine_dir.join(file_name)).map_err(to_err)?;
+
+    // oldest first by modified time
+    let mut quarantined: Vec<(std::time::SystemTime, PathBuf)> = std::fs::read_dir(&quarantine_dir)
+        .map_err(to_err)?
+        .filter_map(|x| x.ok())
+        .filter_map(|x| Some((x.metadata().ok()?.modified().ok()?, x.path())))
+        .collect();
+
+    if quarantined.len() > QUARANTINE_KEEP_MAX {
+        quarantined.sort();
+
+        for (_, path) in quarantined[..quarantined.len() - QUARANTINE_KEEP_MAX].iter() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    Ok(())
+}
+
+//
+// mpeg-ts
+//
+
+struct TsPacket<'a> {
+    pid: u16,
+    // payload_unit_start_indicator
+    pusi: bool,
+    cc: u8,
+    payload: Option<&'a [u8]>,
+}
+
+fn parse_ts_packet(pkt: &[u8]) -> Option<TsPacket> {
+    if pkt.len() < TS_PACKET_SIZE || pkt[0] != TS_SYNC_BYTE {
+        return None;
+    }
+
+    let adaptation_field_control = (pkt[3] >> 4) & 0x03;
+
+    let mut offset = 4;
+
+    if adaptation_field_control & 0x02 != 0 {
+        offset += 1 + pkt[4] as usize;
+    }
+
+    let payload = if adaptation_field_control & 0x01 != 0 && offset < TS_PACKET_SIZE {
+        Some(&pkt[offset..TS_PACKET_SIZE])
+    } else {
+        None
+    };
+
+    Some(TsPacket {
+        pid: ((pkt[1] as u16 & 0x1f) << 8) | pkt[2] as u16,
+        pusi: pkt[1] & 0x40 != 0,
+        cc: pkt[3] & 0x0f,
+        payload,
+    })
+}
+
+// section of psi(pat, pmt) without pointer field, up to crc
+fn get_psi_section(payload: &[u8]) -> Option<&[u8]> {
+    let pointer = *payload.first()? as usize;
+    let section = payload.get(1 + pointer..)?;
+
+    if section.len() < 3 {
+        return None;
+    }
+
+    let section_len = ((section[1] as usize & 0x0f) << 8) | section[2] as usize;
+
+    section.get(..3 + section_len)
+}
+
+// pids of pmt
+fn parse_pat(section: &[u8]) -> Vec<u16> {
+    section
+        .get(8..section.len().saturating_sub(4))
+        .unwrap_or_default()
+        .chunks_exact(4)
+        // program_number 0: network pid
+        .filter(|x| x[0] != 0 || x[1] != 0)
+        .map(|x| ((x[2] as u16 & 0x1f) << 8) | x[3] as u16)
+        .collect()
+}
+
+// pid of adts elementary stream
+fn parse_pmt(section: &[u8]) -> Option<u16> {
+    let program_info_len = ((*section.get(10)? as usize & 0x0f) << 8) | *section.get(11)? as usize;
+    let end = section.len().saturating_sub(4);
+
+    let mut i = 12 + program_info_len;
+
+    while i + 5 <= end {
+        let stream_type = section[i];
+        let pid = ((section[i + 1] as u16 & 0x1f) << 8) | section[i + 2] as u16;
+        let es_info_len = ((section[i + 3] as usize & 0x0f) << 8) | section[i + 4] as usize;
+
+        if stream_type == TS_STREAM_TYPE_ADTS {
+            return Some(pid);
+        }
+
+        i += 5 + es_info_len;
+    }
+
+    None
+}
+
+// adts elementary stream, and (offset in it, pts) of each pes
+fn demux_adts(data: &[u8], report: &mut AdtsSegmentReport) -> (Vec<u8>, Vec<(usize, Option<u64>)>) {
+    let mut pmt_pids: Vec<u16> = Vec::new();
+    let mut adts_pid: Option<u16> = None;
+    let mut last_cc: Option<u8> = None;
+
+    // pes being assembled
+    let mut pes: Vec<u8> = Vec::new();
+
+    let mut es: Vec<u8> = Vec::new();
+    let mut pes_starts: Vec<(usize, Option<u64>)> = Vec::new();
+
+    for chunk in data.chunks(TS_PACKET_SIZE) {
+        let pkt = match parse_ts_packet(chunk) {
+            Some(x) => x,
+            None => {
+                report.invalid_frames += 1;
+                continue;
+            }
+        };
+
+        let payload = match pkt.payload {
+            Some(x) => x,
+            None => continue,
+        };
+
+        if pkt.pid == TS_PID_PAT && pkt.pusi {
+            if let Some(section) = get_psi_section(payload) {
+                pmt_pids = parse_pat(section);
+            }
+            continue;
+        }
+
+        if pmt_pids.contains(&pkt.pid) && pkt.pusi {
+            if adts_pid.is_none() {
+                adts_pid = get_psi_section(payload).and_then(parse_pmt);
+            }
+            continue;
+        }
+
+        if Some(pkt.pid) != adts_pid {
+            continue;
+        }
+
+        if let Some(last) = last_cc {
+            // same counter: duplicated packet
+            if pkt.cc != (last + 1) & 0x0f && pkt.cc != last {
+                report.cc_errors += 1;
+            }
+        }
+        last_cc = Some(pkt.cc);
+
+        if pkt.pusi {
+            append_pes(&pes, &mut es, &mut pes_starts);
+            pes.clear();
+        }
+
+        pes.extend_from_slice(payload);
+    }
+
+    append_pes(&pes, &mut es, &mut pes_starts);
+
+    (es, pes_starts)
+}
+
+// payload of pes is appended to es
+fn append_pes(pes: &[u8], es: &mut Vec<u8>, pes_starts: &mut Vec<(usize, Option<u64>)>) {
+    // packet_start_code_prefix
+    if pes.len() < 9 || pes[..3] != [0x00, 0x00, 0x01] {
+        return;
+    }
+
+    let header_data_len = pes[8] as usize;
+
+    let payload = match pes.get(9 + header_data_len..) {
+        Some(x) => x,
+        None => return,
+    };
+
+    // PTS_DTS_flags
+    let pts = if pes[7] & 0x80 != 0 {
+        pes.get(9..14).map(|x| {
+            ((x[0] as u64 >> 1) & 0x07) << 30
+                | (x[1] as u64) << 22
+                | (x[2] as u64 >> 1) << 15
+                | (x[3] as u64) << 7
+                | (x[4] as u64 >> 1)
+        })
+    } else {
+        None
+    };
+
+    pes_starts.push((es.len(), pts));
+    es.extend_from_slice(payload);
+}
+
+//
+// crc
+//
+
+// crc-16(0x8005) of header without crc field and first 192 bits of raw_data_block
+fn check_adts_crc(frame: &[u8]) -> bool {
+    let raw_start = ADTS_HEADER_LEN + ADTS_CRC_LEN;
+
+    if frame.len() < raw_start {
+        return false;
+    }
+
+    let expected = ((frame[ADTS_HEADER_LEN] as u16) << 8) | frame[ADTS_HEADER_LEN + 1] as u16;
+    let raw_end = frame.len().min(raw_start + ADTS_CRC_RAW_BYTES);
+
+    let mut crc: u16 = 0xffff;
+
+    for b in frame[..ADTS_HEADER_LEN]
+        .iter()
+        .chain(frame[raw_start..raw_end].iter())
+    {
+        crc ^= (*b as u16) << 8;
+
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc == expected
+}
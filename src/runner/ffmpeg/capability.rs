@@ -0,0 +1,281 @@
+// runner::ffmpeg::capability
+// > capabilities of ffmpeg binary probed on startup, publish requests are validated against them
+use super::cmd::FFMpegCmd;
+use super::error::RunnerFFMError;
+use crate::comm_media::Protocol;
+use crate::config::Config;
+use crate::service::api::reqres::publish::req::ReqPublishV3;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+pub const PROBE_TIMEOUT_MS: u64 = 5000;
+
+// output of every ffmpeg, hls playlist
+pub const REQUIRED_MUXER_HLS: &str = "hls";
+// LL-HLS(-lhls) is written by dash muxer
+pub const REQUIRED_MUXER_LLHLS: &str = "dash";
+// fmp4 segments(-hls_segment_type fmp4)
+pub const REQUIRED_MUXER_FMP4: &str = "mp4";
+
+// not an encoder, stream is copied
+const CODEC_COPY: &str = "copy";
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FFMpegCapabilities {
+    // first line of 'ffmpeg -version'
+    pub version: String,
+    pub encoders: HashSet<String>,
+    pub muxers: HashSet<String>,
+    // input options(before '-i') of receiver args
+    pub decoders: HashSet<String>,
+    pub demuxers: HashSet<String>,
+    // input and output
+    pub protocols: HashSet<String>,
+}
+
+static FFMPEG_CAPABILITIES: OnceLock<FFMpegCapabilities> = OnceLock::new();
+
+// none: not probed yet, publish is not validated
+pub fn get() -> Option<&'static FFMpegCapabilities> {
+    FFMPEG_CAPABILITIES.get()
+}
+
+// on startup, probed once
+pub async fn init(ffmpeg_path: &str) -> Result<&'static FFMpegCapabilities, RunnerFFMError> {
+    if let Some(x) = get() {
+        return Ok(x);
+    }
+
+    let caps = probe(ffmpeg_path).await?;
+
+    log::info!(
+        "[capability::init] ffmpeg is probed, path={}, version={}, encoders={}, muxers={}, \
+        decoders={}, demuxers={}, protocols={}",
+        ffmpeg_path,
+        caps.version,
+        caps.encoders.len(),
+        caps.muxers.len(),
+        caps.decoders.len(),
+        caps.demuxers.len(),
+        caps.protocols.len()
+    );
+
+    Ok(FFMPEG_CAPABILITIES.get_or_init(|| caps))
+}
+
+//
+// probe
+//
+
+pub async fn probe(ffmpeg_path: &str) -> Result<FFMpegCapabilities, RunnerFFMError> {
+    let version = run_ffmpeg(ffmpeg_path, "-version").await?;
+
+    Ok(FFMpegCapabilities {
+        version: version.lines().next().unwrap_or_default().to_string(),
+        encoders: parse_codec_list(&run_ffmpeg(ffmpeg_path, "-encoders").await?),
+        muxers: parse_format_list(&run_ffmpeg(ffmpeg_path, "-muxers").await?),
+        decoders: parse_codec_list(&run_ffmpeg(ffmpeg_path, "-decoders").await?),
+        demuxers: parse_format_list(&run_ffmpeg(ffmpeg_path, "-demuxers").await?),
+        protocols: parse_protocol_list(&run_ffmpeg(ffmpeg_path, "-protocols").await?),
+    })
+}
+
+async fn run_ffmpeg(ffmpeg_path: &str, arg: &str) -> Result<String, RunnerFFMError> {
+    let output = tokio::process::Command::new(ffmpeg_path)
+        .args(["-hide_banner", arg])
+        .kill_on_drop(true)
+        .output();
+
+    let output = tokio::time::timeout(Duration::from_millis(PROBE_TIMEOUT_MS), output)
+        .await
+        .map_err(|_| {
+            RunnerFFMError::InvalidConfigParameter(format!(
+                "ffmpeg is not answering, path={}, arg={}",
+                ffmpeg_path, arg
+            ))
+        })?
+        .map_err(|e| {
+            RunnerFFMError::InvalidConfigParameter(format!(
+                "failed to run ffmpeg, path={}, arg={}, e={}",
+                ffmpeg_path, arg, e
+            ))
+        })?;
+
+    if !output.status.success() {
+        return Err(RunnerFFMError::InvalidConfigParameter(format!(
+            "ffmpeg is exited with error, path={}, arg={}, status={}",
+            ffmpeg_path, arg, output.status
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// 'ffmpeg -encoders', 'ffmpeg -decoders', after " ------"
+// >  V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC (codec h264)
+fn parse_codec_list(out: &str) -> HashSet<String> {
+    out.lines()
+        .skip_while(|x| !x.trim_start().starts_with("---"))
+        .skip(1)
+        .filter_map(|x| x.split_whitespace().nth(1))
+        .map(|x| x.to_string())
+        .collect()
+}
+
+// 'ffmpeg -muxers', 'ffmpeg -demuxers', after " --"
+// >  E mov,mp4,m4a,3gp,3g2,mj2 QuickTime / MOV
+fn parse_format_list(out: &str) -> HashSet<String> {
+    out.lines()
+        .skip_while(|x| !x.trim_start().starts_with("--"))
+        .skip(1)
+        .filter_map(|x| x.split_whitespace().nth(1))
+        .flat_map(|x| x.split(','))
+        .map(|x| x.to_string())
+        .collect()
+}
+
+// 'ffmpeg -protocols', names under "Input:" and "Output:"
+fn parse_protocol_list(out: &str) -> HashSet<String> {
+    out.lines()
+        .skip_while(|x| x.trim() != "Input:")
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty() && !x.ends_with(':'))
+        .map(|x| x.to_string())
+        .collect()
+}
+
+//
+// validation
+//
+
+impl FFMpegCapabilities {
+    pub fn has_encoder(&self, codec: &str) -> bool {
+        codec == CODEC_COPY || self.encoders.contains(codec)
+    }
+
+    pub fn has_muxer(&self, muxer: &str) -> bool {
+        self.muxers.contains(muxer)
+    }
+
+    pub fn has_decoder(&self, codec: &str) -> bool {
+        codec == CODEC_COPY || self.decoders.contains(codec)
+    }
+
+    pub fn has_demuxer(&self, demuxer: &str) -> bool {
+        self.demuxers.contains(demuxer)
+    }
+
+    pub fn has_protocol(&self, protocol: &str) -> bool {
+        self.protocols.contains(protocol)
+    }
+
+    // on startup, codecs and output of config
+    pub fn validate_config(&self, config: &Config) -> Result<(), String> {
+        if !self.has_muxer(REQUIRED_MUXER_HLS) {
+            return Err(format!("'{}' muxer is not available", REQUIRED_MUXER_HLS));
+        }
+
+        for (name, codec) in [
+            ("echo_ffmpeg_vcodec", &config.echo_ffmpeg_vcodec),
+            ("echo_ffmpeg_acodec", &config.echo_ffmpeg_acodec),
+        ] {
+            if !self.has_encoder(codec) {
+                return Err(format!(
+                    "encoder of {} is not available, codec={}",
+                    name, codec
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // protocol of media and features given by receiver args(profile)
+    pub fn validate_publish(&self, req_publish: &ReqPublishV3) -> Result<(), String> {
+        let protocol = match req_publish.media.protocol {
+            Protocol::RTMP => "rtmp",
+            Protocol::SRT => "srt",
+        };
+
+        if !self.has_protocol(protocol) {
+            return Err(format!("'{}' protocol is not compiled in ffmpeg", protocol));
+        }
+
+        let args = req_publish.receiver_prm.args.trim();
+
+        // default args of media type
+        if args.is_empty() {
+            return Ok(());
+        }
+
+        let (args, _playlist) = FFMpegCmd::parse_args(args.to_string())
+            .map_err(|e| format!("invalid receiver args, {}", e))?;
+
+        // before '-i': format and codec of publish stream, kept before input by FFMpegCmd
+        let (input_args, args) = FFMpegCmd::split_input_args(args);
+
+        for x in input_args.iter() {
+            let val = match x.get(1) {
+                Some(v) => v.as_str(),
+                None => continue,
+            };
+
+            match x[0].as_str() {
+                "-c" | "-codec" | "-c:v" | "-codec:v" | "-c:a" | "-codec:a" => {
+                    if !self.has_decoder(val) {
+                        return Err(format!("decoder is not available, codec={}", val));
+                    }
+                }
+                "-f" => {
+                    if !self.has_demuxer(val) {
+                        return Err(format!("demuxer is not available, format={}", val));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for x in args.iter() {
+            let val = match x.get(1) {
+                Some(v) => v.as_str(),
+                None => continue,
+            };
+
+            match x[0].as_str() {
+                // -vcodec, -acodec are replaced by config, validated on startup
+                "-c" | "-codec" | "-c:v" | "-codec:v" | "-c:a" | "-codec:a" => {
+                    if !self.has_encoder(val) {
+                        return Err(format!("encoder is not available, codec={}", val));
+                    }
+                }
+                "-vcodec" | "-acodec" => {}
+                "-f" => {
+                    if !self.has_muxer(val) {
+                        return Err(format!("muxer is not available, format={}", val));
+                    }
+                }
+                "-lhls" | "-ldash" if val != "0" => {
+                    if !self.has_muxer(REQUIRED_MUXER_LLHLS) {
+                        return Err(format!(
+                            "LL-HLS is not supported, '{}' muxer is not available",
+                            REQUIRED_MUXER_LLHLS
+                        ));
+                    }
+                }
+                "-hls_segment_type" if val == "fmp4" => {
+                    if !self.has_muxer(REQUIRED_MUXER_FMP4) {
+                        return Err(format!(
+                            "fmp4 segment is not supported, '{}' muxer is not available",
+                            REQUIRED_MUXER_FMP4
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -66,6 +66,10 @@ timeout=
 const DEF_FFMARG_AUDIO: &str = r#"-v quiet -y -listen 1 -rw_timeout 10000000 -vn -acodec copy -flags -global_header -hls_time 1 -hls_list_size 10 -start_number 1 -hls_flags delete_segments -strftime 1 playlist.m3u8"#;
 const DEF_FFMARG_VIDEO: &str = r#"-v quiet -y -listen 1 -rw_timeout 10000000 -vcodec copy -acodec copy -flags -global_header -hls_time 5 -hls_list_size 10 -start_number 1 -hls_flags delete_segments -strftime 1 playlist.m3u8"#;
 
+// deleted segments are kept for a few more segments beyond hls_list_size (delete_segments)
+// > served playlist(ffmpeg::playlist) lists segments of receiver a little later than receiver does
+const HLS_DELETE_THRESHOLD: i32 = 3;
+
 use thiserror::Error;
 
 // Error=thiserror_impl proc_macro Error
@@ -232,6 +236,9 @@ impl FFMpegCmd {
         ffmpeg_rw_timeout: i64,
         ffmpeg_vcodec: String,
         ffmpeg_acodec: String,
+        // audio is encoded with filter(ex: loudnorm), instead of given
+        ffmpeg_audio_filter: Option<String>,
+        ffmpeg_audio_bitrate: Option<String>,
 
         ffmpeg_hls_init_time: String,
         ffmpeg_hls_time: String,
@@ -252,6 +259,8 @@ impl FFMpegCmd {
         let (args_given, playlist_file_path) = FFMpegCmd::parse_args(ffmpeg_args_given.clone())
             .map_err(|e| FFMpegCmdError::CmdOperErr(e))?;
 
+        let (input_args_given, args_given) = FFMpegCmd::split_input_args(args_given);
+
         let mut path = format!("{}/{}", app_name.clone(), sess_key.clone());
         let mut args = FFMpegArgs::new();
 
@@ -271,6 +280,14 @@ impl FFMpegCmd {
         // add listen addr
         //
 
+        // options of input given before '-i', ex) -f flv, -c:v h264_cuvid
+        for x in input_args_given.iter().filter(|x| x.len() > 1) {
+            match x[0].as_str() {
+                "-listen" | "-v" | "-y" | "-rw_timeout" => continue,
+                _ => args.push(x.join(" ")),
+            }
+        }
+
         // keep order
         args.push(format!("-listen 1"));
         args.push(format!(
@@ -303,6 +320,12 @@ impl FFMpegCmd {
                     "-acodec" => continue,
                     "-hls_time" => continue,
                     "-hls_list_size" => continue,
+                    "-hls_delete_threshold" => continue,
+                    "-af" | "-filter:a" | "-c:a" | "-codec:a" | "-b:a"
+                        if ffmpeg_audio_filter.is_some() =>
+                    {
+                        continue
+                    }
                     v => {
                         args.push(format!("{}", x.join(" ")));
                     }
@@ -317,16 +340,24 @@ impl FFMpegCmd {
         match media_props.media_type {
             MediaType::Video => {
                 args.push(format!("-vcodec {}", ffmpeg_vcodec));
-                args.push(format!("-acodec {}", ffmpeg_acodec));
             }
             MediaType::Audio => {
                 // discard video stream
                 args.push(format!("-vn "));
-
-                args.push(format!("-acodec {}", ffmpeg_acodec));
             }
         };
 
+        // 'copy' can not be filtered
+        if let Some(af) = ffmpeg_audio_filter.as_ref() {
+            args.push(format!("-af {}", af));
+        }
+
+        args.push(format!("-acodec {}", ffmpeg_acodec));
+
+        if let Some(bitrate) = ffmpeg_audio_bitrate.as_ref() {
+            args.push(format!("-b:a {}", bitrate));
+        }
+
         args.push(format!("-hls_init_time {}", ffmpeg_hls_init_time));
 
         // Segments will be cut at keyframes, so unless a keyframe exists each second,
@@ -335,6 +366,7 @@ impl FFMpegCmd {
         // > hls_time 유효값: 2, 4, 6, 8 ..
         args.push(format!("-hls_time {}", ffmpeg_hls_time));
         args.push(format!("-hls_list_size {}", ffmpeg_hls_list_size));
+        args.push(format!("-hls_delete_threshold {}", HLS_DELETE_THRESHOLD));
 
         //
         // add playlist
@@ -458,6 +490,18 @@ impl FFMpegCmd {
         Ok((args, playlist_path.to_string()))
     }
 
+    // (input options, output options) of parsed args, split at '-i'
+    // > all are output options if '-i' is not given, '-i' itself is in output options
+    pub fn split_input_args(mut args: FFMepgArgsInner) -> (FFMepgArgsInner, FFMepgArgsInner) {
+        match args.iter().position(|x| x[0] == "-i") {
+            Some(i) => {
+                let output = args.split_off(i);
+                (args, output)
+            }
+            None => (FFMepgArgsInner::new(), args),
+        }
+    }
+
     pub fn debug_print(&self) {
         let r = self.ffmpeg_args.join("");
 
@@ -28,6 +28,9 @@ pub enum RunnerFFMError {
     #[error("termination timeoutted")]
     FFMpegTermTimeout,
 
+    #[error("error on signal(pause/resume) ffmpeg={0}")]
+    FFMpegSignalErr(String),
+
     #[error("error in runnerffmpeg inner operation: {0}")]
     InnerOperErr(String),
 }
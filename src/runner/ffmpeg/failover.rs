@@ -0,0 +1,437 @@
+// runner::ffmpeg::failover
+// > backup publisher of session, PublishOverrides::backup
+// > backup receiver listens on its own publish port and writes hls into '{hls_out}/backup'
+//   , it is owned by RecvWorker, not taken down when primary receiver(driver) is respawned
+// > while backup is on air, new backup segments are copied and appended to live playlist(ffmpeg::playlist) as slate is
+// > switched back to primary by RecvWorker once primary publisher is connected(auto), or backup is lost
+// > backup must be encoded as primary is(codec, sample rate, resolution), only timestamps are discontinuous
+use super::cmd::FFMpegCmd;
+use super::create_ffmpeg_log_file;
+use super::error::RunnerFFMError;
+use super::imp::instance::new_receiver_cmd;
+use super::loudnorm::LoudnormPrm;
+use super::media_monitor::get_segment_durations;
+use super::playlist::{HlsLivePlaylist, PlaylistSegment};
+use crate::comm::{EchoTimeDuration, EchoTimeInstant};
+use crate::comm_fs;
+use crate::comm_media::PropMedia;
+use crate::config::Config;
+use crate::runner::{RunnerProcChild, RunnerProcCmd};
+use crate::EchoPathBufToString;
+use serde::Serialize;
+use std::path::Path;
+
+// when primary is switched back, default: auto
+// > auto: as soon as primary publisher is back
+// > backup_lost: backup stays on air until it is lost, primary receiver is not respawned meanwhile
+pub const FAILOVER_SWITCH_BACK_ENV: &str = "ECHO_FAILOVER_SWITCH_BACK";
+
+// backup is lost if its playlist is not updated within(plus target duration)
+pub const FAILOVER_STALL_SEC_ENV: &str = "ECHO_FAILOVER_STALL_SEC";
+pub const FAILOVER_STALL_SEC_DEFAULT: u64 = 3;
+
+// copied backup segments in hls out dir, '{prefix}{serial}.{ext}'
+pub const BACKUP_SEGMENT_PREFIX: &str = "backup_";
+
+// '{hls_out}/backup/playlist.m3u8'
+const BACKUP_DIR_NAME: &str = "backup";
+const BACKUP_PLAYLIST_FILE_NAME: &str = "playlist.m3u8";
+
+// exited backup receiver is respawned after, backup publisher may reconnect any time
+const BACKUP_RESPAWN_INTERVAL_MS: u64 = 1000;
+
+const TAG_ENDLIST: &str = "#EXT-X-ENDLIST";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailoverSwitchBack {
+    Auto,
+    BackupLost,
+}
+
+impl FailoverSwitchBack {
+    pub fn from_env() -> Self {
+        match std::env::var(FAILOVER_SWITCH_BACK_ENV).as_deref() {
+            Ok("backup_lost") => Self::BackupLost,
+            _ => Self::Auto,
+        }
+    }
+}
+
+//
+// backup receiver
+//
+
+struct BackupReceiver {
+    cmd: FFMpegCmd,
+    out_path: String,
+    log_file_path: String,
+
+    proc: Option<RunnerProcChild>,
+    spawned_at: Option<EchoTimeInstant>,
+}
+
+impl BackupReceiver {
+    fn spawn(&mut self) -> Result<(), RunnerFFMError> {
+        self.spawned_at = Some(EchoTimeInstant::now());
+
+        comm_fs::create_dir_sync(&self.out_path).map_err(|e| {
+            RunnerFFMError::FFMpegSpawnErr(format!(
+                "failed to create backup hls out path={}, e={}",
+                self.out_path, e
+            ))
+        })?;
+
+        // previous one is not live anymore
+        let _ = std::fs::remove_file(Path::new(&self.out_path).join(BACKUP_PLAYLIST_FILE_NAME));
+
+        let mut spawner = RunnerProcCmd::new(self.cmd.get_program());
+
+        spawner.stderr(create_ffmpeg_log_file(&self.log_file_path)?);
+        spawner.current_dir(&self.out_path);
+        spawner.args(self.cmd.get_ffmpeg_args());
+        spawner.kill_on_drop(true);
+
+        let proc = spawner
+            .spawn()
+            .map_err(|e| RunnerFFMError::FFMpegSpawnErr(e.to_string()))?;
+
+        self.proc = Some(proc);
+
+        Ok(())
+    }
+
+    // exited receiver is respawned, listens for backup publisher again
+    fn poll(&mut self) -> Result<(), RunnerFFMError> {
+        if let Some(proc) = self.proc.as_mut() {
+            match proc.try_wait() {
+                Ok(None) => return Ok(()),
+                Ok(Some(status)) => log::info!(
+                    "[BackupReceiver::poll] backup receiver is exited, status={}",
+                    status
+                ),
+                Err(e) => log::error!(
+                    "[BackupReceiver::poll] failed to poll backup receiver, e={}",
+                    e
+                ),
+            }
+
+            self.proc = None;
+        }
+
+        if let Some(x) = self.spawned_at {
+            if x.elapsed() < EchoTimeDuration::from_millis(BACKUP_RESPAWN_INTERVAL_MS) {
+                return Ok(());
+            }
+        }
+
+        self.spawn()
+    }
+
+    async fn terminate(&mut self) {
+        if let Some(mut proc) = self.proc.take() {
+            if let Err(e) = proc.kill().await {
+                log::error!(
+                    "[BackupReceiver::terminate] failed to kill backup receiver, e={}",
+                    e
+                );
+            }
+        }
+    }
+}
+
+//
+// failover
+//
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FailoverTick {
+    // primary is on air
+    Idle,
+    OnBackup,
+    // backup playlist is not updated within stall
+    BackupLost,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FailoverSummary {
+    pub on_backup_sec: u64,
+    pub segments: u32,
+}
+
+// RecvWorkerStateSnapshot
+#[derive(Debug, Clone, Serialize)]
+pub struct FailoverState {
+    pub backup_publish_port: u16,
+    pub switch_back: FailoverSwitchBack,
+    pub backup_live: bool,
+    pub on_backup: bool,
+    // switched to backup
+    pub switch_cnt: u32,
+}
+
+pub struct HlsFailover {
+    backup_publish_port: u16,
+    receiver: BackupReceiver,
+
+    switch_back: FailoverSwitchBack,
+    stall: EchoTimeDuration,
+
+    // some: backup is on air since
+    switched_at: Option<EchoTimeInstant>,
+    // last backup segment appended to live playlist
+    last_uri: Option<String>,
+    appended: u32,
+    switch_cnt: u32,
+
+    // serial of copied backup file, not reused within session
+    serial: u64,
+}
+
+impl HlsFailover {
+    pub fn new(
+        config: &Config,
+        media: &PropMedia,
+        app_name: String,
+        sess_key: String,
+        runner_args: String,
+        backup_publish_port: u16,
+        loudnorm: Option<&LoudnormPrm>,
+    ) -> Result<Self, RunnerFFMError> {
+        let hls_out_path = format!(
+            "{}/{}",
+            EchoPathBufToString!(config.echo_hls_root_dir.clone()),
+            app_name
+        );
+
+        let out_path = format!("{}/{}", hls_out_path, BACKUP_DIR_NAME);
+
+        let log_file_path = format!(
+            "{}/{}_receiver_backup.log",
+            EchoPathBufToString!(config.echo_ffmpeg_log_root_dir.clone()),
+            app_name
+        );
+
+        // backup playlist is written from its start, not appended
+        let cmd = new_receiver_cmd(
+            config,
+            media,
+            app_name,
+            sess_key,
+            runner_args,
+            backup_publish_port,
+            out_path.clone(),
+            log_file_path.clone(),
+            loudnorm,
+        )?;
+
+        let stall_sec = std::env::var(FAILOVER_STALL_SEC_ENV)
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(FAILOVER_STALL_SEC_DEFAULT);
+
+        Ok(HlsFailover {
+            backup_publish_port,
+            receiver: BackupReceiver {
+                cmd,
+                out_path,
+                log_file_path,
+                proc: None,
+                spawned_at: None,
+            },
+            switch_back: FailoverSwitchBack::from_env(),
+            stall: EchoTimeDuration::from_secs(stall_sec),
+            switched_at: None,
+            last_uri: None,
+            appended: 0,
+            switch_cnt: 0,
+            serial: 0,
+        })
+    }
+
+    pub fn begin(&mut self) -> Result<(), RunnerFFMError> {
+        self.receiver.spawn()
+    }
+
+    pub async fn end(&mut self) {
+        self.receiver.terminate().await;
+    }
+
+    pub fn is_on_backup(&self) -> bool {
+        self.switched_at.is_some()
+    }
+
+    pub fn is_backup_live(&self) -> bool {
+        self.read_backup_playlist().is_some()
+    }
+
+    pub fn switch_back(&self) -> FailoverSwitchBack {
+        self.switch_back
+    }
+
+    pub fn backup_publish_port(&self) -> u16 {
+        self.backup_publish_port
+    }
+
+    pub fn get_state(&self) -> FailoverState {
+        FailoverState {
+            backup_publish_port: self.backup_publish_port,
+            switch_back: self.switch_back,
+            backup_live: self.is_backup_live(),
+            on_backup: self.is_on_backup(),
+            switch_cnt: self.switch_cnt,
+        }
+    }
+
+    // newest backup segment is appended at once, with discontinuity
+    // > false: backup is not live, not switched
+    pub fn switch_to_backup(
+        &mut self,
+        playlist: &mut HlsLivePlaylist,
+    ) -> Result<bool, RunnerFFMError> {
+        if self.is_on_backup() {
+            return Ok(true);
+        }
+
+        let backup = match self.read_backup_playlist() {
+            Some(x) => x,
+            None => return Ok(false),
+        };
+
+        self.switched_at = Some(EchoTimeInstant::now());
+        self.last_uri = None;
+        self.appended = 0;
+        self.switch_cnt += 1;
+
+        if let Err(e) = self.append(playlist, &backup) {
+            self.switched_at = None;
+            return Err(e);
+        }
+
+        Ok(true)
+    }
+
+    // listed backup segments are removed by live playlist as they slide out
+    pub fn switch_to_primary(&mut self) -> Option<FailoverSummary> {
+        let switched_at = self.switched_at.take()?;
+
+        Some(FailoverSummary {
+            on_backup_sec: switched_at.elapsed().as_secs(),
+            segments: self.appended,
+        })
+    }
+
+    // call periodically, backup receiver is kept running, new backup segments are appended while on air
+    pub fn tick(&mut self, playlist: &mut HlsLivePlaylist) -> Result<FailoverTick, RunnerFFMError> {
+        if let Err(e) = self.receiver.poll() {
+            log::error!(
+                "[HlsFailover::tick] failed to respawn backup receiver, port={}, e={}",
+                self.backup_publish_port,
+                e
+            );
+        }
+
+        if !self.is_on_backup() {
+            return Ok(FailoverTick::Idle);
+        }
+
+        let backup = match self.read_backup_playlist() {
+            Some(x) => x,
+            None => return Ok(FailoverTick::BackupLost),
+        };
+
+        self.append(playlist, &backup)?;
+
+        Ok(FailoverTick::OnBackup)
+    }
+
+    // none: backup publisher is not live
+    // > ENDLIST is written by exited receiver, playlist is not updated by stalled publisher
+    fn read_backup_playlist(&self) -> Option<String> {
+        let path = Path::new(&self.receiver.out_path).join(BACKUP_PLAYLIST_FILE_NAME);
+
+        let modified = std::fs::metadata(&path).and_then(|x| x.modified()).ok()?;
+        let backup = std::fs::read_to_string(&path).ok()?;
+
+        if backup.contains(TAG_ENDLIST) {
+            return None;
+        }
+
+        let target_duration = get_segment_durations(&backup)
+            .iter()
+            .map(|(_, d)| *d)
+            .fold(0.0, f64::max);
+
+        // no segment yet
+        if target_duration <= 0.0 {
+            return None;
+        }
+
+        let age = modified.elapsed().unwrap_or_default();
+
+        if age > self.stall + EchoTimeDuration::from_secs_f64(target_duration) {
+            return None;
+        }
+
+        Some(backup)
+    }
+
+    // backup segments after last appended one
+    // > timestamps restart at switchover, and when backup receiver is respawned(last one is not listed)
+    fn append(
+        &mut self,
+        playlist: &mut HlsLivePlaylist,
+        backup: &str,
+    ) -> Result<(), RunnerFFMError> {
+        let segments: Vec<(String, f64)> = get_segment_durations(backup)
+            .into_iter()
+            .filter(|(_, d)| *d > 0.0)
+            .collect();
+
+        let listed = self
+            .last_uri
+            .as_ref()
+            .and_then(|x| segments.iter().position(|(uri, _)| uri == x));
+
+        let pending = match listed {
+            Some(idx) => segments[idx + 1..].to_vec(),
+            None => segments.last().cloned().into_iter().collect(),
+        };
+
+        let mut discontinuity = listed.is_none();
+
+        for (src, duration) in pending {
+            let ext = Path::new(&src)
+                .extension()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or(String::from("ts"));
+
+            let file_name = format!("{}{}.{}", BACKUP_SEGMENT_PREFIX, self.serial, ext);
+
+            std::fs::copy(
+                Path::new(&self.receiver.out_path).join(&src),
+                playlist.dir().join(&file_name),
+            )
+            .map_err(|e| {
+                RunnerFFMError::FileOperErr(format!(
+                    "failed to copy backup segment, src={}, e={}",
+                    src, e
+                ))
+            })?;
+
+            playlist.append(vec![PlaylistSegment::owned(
+                file_name,
+                duration,
+                discontinuity,
+            )])?;
+
+            discontinuity = false;
+
+            self.last_uri = Some(src);
+            self.appended += 1;
+            self.serial += 1;
+        }
+
+        Ok(())
+    }
+}
@@ -7,31 +7,12 @@ impl RunnerFFMpeg {
         log::debug!("[RunnerFFMpeg][begin] ");
 
         {
-            let mut create_ctx_ary: RunnerFFMCreateCtxAry = Vec::new();
-
-            let receiver_ctx_ref = self.receiver_ctx.as_ref();
-            let mut receiver_type: Option<RunnerFFMType> = None;
-
-            let recorder_ctx_ref = self.recorder_ctx.as_ref();
-            let mut recorder_type: Option<RunnerFFMType> = None;
-
-            if receiver_ctx_ref.is_some() {
-                receiver_type = Some(receiver_ctx_ref.unwrap().ffm_type);
-                create_ctx_ary.push(receiver_ctx_ref.unwrap().clone());
-            }
-
-            if recorder_ctx_ref.is_some() {
-                recorder_type = Some(recorder_ctx_ref.unwrap().ffm_type);
-                create_ctx_ary.push(recorder_ctx_ref.unwrap().clone());
-            }
-
             let responder = self.runner_msg_send.clone();
 
             let _inner = ffmpeg::create_runner_ffmpeg_inner(
                 responder,
-                receiver_type,
-                recorder_type,
-                create_ctx_ary,
+                self.create_ctxs.clone(),
+                self.stage_stats.clone(),
             );
 
             if let Err(e) = _inner {
@@ -44,6 +25,7 @@ impl RunnerFFMpeg {
             let inner = _inner.unwrap();
 
             self.inner_msg_send = Some(inner.inner_msg_send.clone());
+            self.paused = None;
 
             // ownership of 'inner' is moved to tokio
             let handle = tokio::spawn(inner.run());
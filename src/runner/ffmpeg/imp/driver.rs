@@ -0,0 +1,48 @@
+use crate::comm_media::{MediaReceiver, MediaType, Protocol};
+use crate::runner::ffmpeg::*;
+use crate::runner::*;
+use async_trait::async_trait;
+
+// RunnerFFMpeg as receiver backend, MediaReceiver::FFMPEG
+// > status is managed by runner::Driver
+#[async_trait]
+impl ReceiverDriver for RunnerFFMpeg {
+    async fn begin(&mut self) -> DriverRst {
+        RunnerFFMpeg::begin(self).await
+    }
+
+    async fn tick(&mut self) -> DriverRst {
+        RunnerFFMpeg::tick(self).await
+    }
+
+    async fn end(&mut self) -> DriverRst {
+        RunnerFFMpeg::end(self).await
+    }
+
+    async fn reset(&mut self) -> DriverRst {
+        RunnerFFMpeg::reset(self).await
+    }
+
+    async fn pause(&mut self, target: PauseTarget) -> DriverRst {
+        RunnerFFMpeg::pause(self, target).await
+    }
+
+    async fn resume(&mut self) -> DriverRst {
+        RunnerFFMpeg::resume(self).await
+    }
+
+    async fn stats(&self) -> DriverStats {
+        RunnerFFMpeg::stats(self).await
+    }
+
+    fn capabilities(&self) -> DriverCapabilities {
+        DriverCapabilities {
+            receiver: MediaReceiver::FFMPEG,
+            protocols: vec![Protocol::RTMP],
+            media_types: vec![MediaType::Audio, MediaType::Video],
+            auto_respawn: true,
+            recording: true,
+            pause: true,
+        }
+    }
+}
@@ -1,16 +1,20 @@
-use crate::comm::EchoTimeDuration;
+use crate::comm::{EchoTimeDuration, EchoTimeInstant};
 use crate::comm_ps;
 use crate::comm_ps::*;
+use crate::metrics;
+use crate::runner::ffmpeg::message::PauseMsgOCResponder;
 use crate::runner::ffmpeg::RunnerFFMError;
 use crate::runner::ffmpeg::RunnerFFMpegInner;
-use crate::runner::TERMINATE_TIMEOUT_MS;
 use crate::runner::{
     ffmpeg::message::{RunnerFFMpegInnerMsg, RunnerFFMpegMsg, RunnerFFMpegMsgSend},
     ffmpeg::{
-        imp::spawn::spawn_ffmpeg_from_creation_ctx, RunnerFFMCreateCtxAry, RunnerFFMType,
-        RunnerFFMpegInnerExitRst, RunnerFFMpegInnerProcCtx, RunnerFFMpegInnerProcCtxMap,
+        imp::spawn::spawn_ffmpeg_from_creation_ctx, RunnerFFMCreateCtx, RunnerFFMCreateCtxAry,
+        RunnerFFMStageStatsShared, RunnerFFMType, RunnerFFMTypeAry, RunnerFFMpegInnerExitRst,
+        RunnerFFMpegInnerProcCtx, RunnerFFMpegInnerProcCtxMap,
     },
 };
+use crate::runner::{DriverProcState, DriverProcStats, PauseTarget};
+use crate::runner::{STAGE_RESPAWN_INTERVAL_MS, STAGE_RESPAWN_MAX_CNT, TERMINATE_TIMEOUT_MS};
 use crate::ECHO_ASYNC_SLEEP_MS;
 use crate::ECHO_TIME_DURATION_MS;
 use crate::ECHO_TIME_DURATION_SEC;
@@ -19,11 +23,8 @@ use tokio::sync::mpsc;
 
 pub fn create_runner_ffmpeg_inner(
     responder: RunnerFFMpegMsgSend,
-
-    receiver_type: Option<RunnerFFMType>,
-    recorder_type: Option<RunnerFFMType>,
-
     create_ctxs: RunnerFFMCreateCtxAry,
+    stage_stats: RunnerFFMStageStatsShared,
 ) -> Result<RunnerFFMpegInner, RunnerFFMError> {
     //let mut proc_names = RunnerFFMpegInnerProcTypeAry::new();
     let mut proc_ctx_map = RunnerFFMpegInnerProcCtxMap::new();
@@ -32,12 +33,13 @@ pub fn create_runner_ffmpeg_inner(
         let info = RunnerFFMpegInnerProcCtx {
             create_ctx: x.clone(),
 
-            no_need_termination: x.no_need_termination,
-            auto_respawn: x.auto_respawn,
-
             is_spawned: false,
+            respawn_cnt: 0,
+            spawn_instant: None,
             spawn_err: None,
 
+            paused: false,
+
             proc: None,
             proc_cmd: None,
 
@@ -46,23 +48,104 @@ pub fn create_runner_ffmpeg_inner(
         };
 
         //proc_names.push(x.ffm_type);
-        proc_ctx_map.insert(x.ffm_type, info);
+        if proc_ctx_map.insert(x.ffm_type, info).is_some() {
+            return Err(RunnerFFMError::InvalidConfigParameter(format!(
+                "duplicated pipeline stage, ffm_type={}",
+                x.ffm_type
+            )));
+        }
     }
 
+    let start_order = resolve_start_order(&create_ctxs)?;
+    let stop_order = resolve_stop_order(&create_ctxs, &start_order);
+
     let (inner_msg_send, inner_msg_recv) = tokio::sync::mpsc::unbounded_channel();
 
     Ok(RunnerFFMpegInner {
         responder,
-        receiver_type,
-        recorder_type,
+        start_order,
+        stop_order,
         proc_ctx_map,
+        stage_stats,
         output: LinkedList::new(),
         inner_msg_send,
         inner_msg_recv,
         force_terminating: false,
+        session_down: false,
+        paused: None,
     })
 }
 
+//
+// pipeline order
+//
+
+// dependencies first, then RunnerFFMStagePolicy::start_order
+fn resolve_start_order(
+    create_ctxs: &RunnerFFMCreateCtxAry,
+) -> Result<RunnerFFMTypeAry, RunnerFFMError> {
+    for ctx in create_ctxs.iter() {
+        for dep in ctx.policy.depends_on.iter() {
+            if !create_ctxs.iter().any(|x| x.ffm_type == *dep) {
+                return Err(RunnerFFMError::InvalidConfigParameter(format!(
+                    "unknown pipeline stage dependency, ffm_type={}, depends_on={}",
+                    ctx.ffm_type, dep
+                )));
+            }
+        }
+    }
+
+    let mut pending: Vec<&RunnerFFMCreateCtx> = create_ctxs.iter().collect();
+    pending.sort_by_key(|x| x.policy.start_order);
+
+    let mut ordered = RunnerFFMTypeAry::new();
+
+    while !pending.is_empty() {
+        let pos = pending
+            .iter()
+            .position(|x| x.policy.depends_on.iter().all(|dep| ordered.contains(dep)));
+
+        match pos {
+            Some(i) => ordered.push(pending.remove(i).ffm_type),
+            None => {
+                return Err(RunnerFFMError::InvalidConfigParameter(format!(
+                    "cyclic pipeline stage dependency, stages={:?}",
+                    pending.iter().map(|x| x.ffm_type).collect::<Vec<_>>()
+                )));
+            }
+        }
+    }
+
+    Ok(ordered)
+}
+
+// RunnerFFMStagePolicy::stop_order, if same, reversed start order
+fn resolve_stop_order(
+    create_ctxs: &RunnerFFMCreateCtxAry,
+    start_order: &RunnerFFMTypeAry,
+) -> RunnerFFMTypeAry {
+    let mut stages: Vec<(u32, usize, RunnerFFMType)> = create_ctxs
+        .iter()
+        .map(|x| {
+            let start_pos = start_order
+                .iter()
+                .position(|y| *y == x.ffm_type)
+                .unwrap_or(0);
+
+            (x.policy.stop_order, usize::MAX - start_pos, x.ffm_type)
+        })
+        .collect();
+
+    stages.sort_by_key(|x| (x.0, x.1));
+
+    stages.into_iter().map(|x| x.2).collect()
+}
+
+fn is_ffm_running(ctx: &RunnerFFMpegInnerProcCtx) -> bool {
+    // not spawend or already exited
+    ctx.proc.is_some() && ctx.term_exit_rst.is_none()
+}
+
 async fn terminate_ffm(
     ffm_type: &RunnerFFMType,
     ctx_map: &mut RunnerFFMpegInnerProcCtxMap,
@@ -71,7 +154,15 @@ async fn terminate_ffm(
     let ctx = ctx_map.get_mut(ffm_type).unwrap();
     let poll_exit_rst;
 
-    if ctx.no_need_termination {
+    if !is_ffm_running(ctx) {
+        // not spawned(spawn error, dependency failed) or already exited
+        return Ok(ctx.term_exit_rst.clone().unwrap_or(PollExitStRst {
+            exit_code: 0,
+            exit_desc: String::from("not spawned"),
+        }));
+    }
+
+    if ctx.create_ctx.policy.no_need_termination {
         poll_exit_rst = PollExitStRst {
             exit_code: 0,
             exit_desc: String::from("no need to termination"),
@@ -81,6 +172,7 @@ async fn terminate_ffm(
         return Ok(poll_exit_rst);
     } else {
         let proc = ctx.proc.as_mut().unwrap();
+        let term_instant = EchoTimeInstant::now();
         let _term_rst = comm_ps::terminate(proc.id().unwrap());
         let mut _term_err = Option::<RunnerFFMError>::None;
 
@@ -95,9 +187,41 @@ async fn terminate_ffm(
                 ECHO_ASYNC_SLEEP_MS!(100);
 
                 match _poll_rst {
-                    Ok(r) => return Ok(r),
+                    Ok(r) => {
+                        metrics::FFM_TERM_DURATION
+                            .with_label_values(&[ffm_type.to_string().as_str()])
+                            .observe(term_instant.elapsed().as_secs_f64());
+
+                        ctx.term_exit_rst = Some(r.clone());
+                        return Ok(r);
+                    }
                     Err(EchoPSError::PollingStatusTimeout) => {
-                        _term_err = Some(RunnerFFMError::FFMpegTermTimeout)
+                        metrics::FFM_TERM_TIMEOUT_TOTAL
+                            .with_label_values(&[ffm_type.to_string().as_str()])
+                            .inc();
+
+                        // SIGTERM is ignored(ex: stuck on output), ffmpeg must not outlive session
+                        log::error!(
+                            "[RunnerFFMpegInner::terminate_ffm] termination timeoutted, killing.., \
+                            type={}, pid={:?}",
+                            ffm_type,
+                            proc.id()
+                        );
+
+                        match proc.kill().await {
+                            Ok(_) => {
+                                let r = PollExitStRst {
+                                    exit_code: PROC_EXIT_CODE_INTERRUPTED,
+                                    exit_desc: String::from("killed after termination timeout"),
+                                };
+
+                                ctx.term_exit_rst = Some(r.clone());
+                                return Ok(r);
+                            }
+                            Err(e) => {
+                                _term_err = Some(RunnerFFMError::FFMpegTermErr(e.to_string()))
+                            }
+                        }
                     }
                     Err(e) => _term_err = Some(RunnerFFMError::FFMpegTermErr(e.to_string())),
                 }
@@ -120,12 +244,58 @@ async fn terminate_ffm(
     }
 }
 
+// SIGSTOP(pause=true) or SIGCONT(pause=false) to running stage
+async fn signal_ffm(
+    ffm_type: &RunnerFFMType,
+    ctx_map: &mut RunnerFFMpegInnerProcCtxMap,
+    pause: bool,
+) -> Result<(), RunnerFFMError> {
+    let ctx = ctx_map.get_mut(ffm_type).unwrap();
+
+    let pid = match ctx.proc.as_ref().and_then(|x| x.id()) {
+        Some(pid) => pid,
+        None => {
+            // already exited
+            ctx.paused = false;
+            return Ok(());
+        }
+    };
+
+    let _signal_rst = if pause {
+        comm_ps::pause(pid)
+    } else {
+        comm_ps::resume(pid)
+    };
+
+    match _signal_rst {
+        Ok(mut _kill_proc) => {
+            _kill_proc.wait().await.map_err(|e| {
+                RunnerFFMError::FFMpegSignalErr(format!(
+                    "ffm_type={}, pid={}, e={}",
+                    ffm_type,
+                    pid,
+                    e.to_string()
+                ))
+            })?;
+
+            ctx.paused = pause;
+
+            Ok(())
+        }
+        Err(e) => Err(RunnerFFMError::FFMpegSignalErr(format!(
+            "ffm_type={}, pid={}, e={}",
+            ffm_type,
+            pid,
+            e.to_string()
+        ))),
+    }
+}
+
 fn get_ffm_running_cnt(proc_ctx_map: &RunnerFFMpegInnerProcCtxMap) -> u32 {
     let mut running_count = 0;
 
     for (_, ctx) in proc_ctx_map.iter() {
-        // not spawend or already exited
-        if ctx.proc.is_none() || ctx.term_exit_rst.is_some() {
+        if !is_ffm_running(ctx) {
             continue;
         }
         running_count += 1;
@@ -134,6 +304,17 @@ fn get_ffm_running_cnt(proc_ctx_map: &RunnerFFMpegInnerProcCtxMap) -> u32 {
     running_count
 }
 
+// critical stage which is exited, the session must be taken down
+fn get_exited_critical_ffm(proc_ctx_map: &RunnerFFMpegInnerProcCtxMap) -> Option<RunnerFFMType> {
+    for (ffm_type, ctx) in proc_ctx_map.iter() {
+        if ctx.create_ctx.policy.critical && ctx.proc.is_some() && ctx.term_exit_rst.is_some() {
+            return Some(*ffm_type);
+        }
+    }
+
+    None
+}
+
 async fn update_ffm_exit_status(
     proc_ctx_map: &mut RunnerFFMpegInnerProcCtxMap,
     poll_timeout_ms: EchoTimeDuration,
@@ -151,7 +332,8 @@ async fn update_ffm_exit_status(
             match poll_rst {
                 Ok(poll_exit_rst) => {
                     log::debug!(
-                        "ffm process is exited.., exit_code={}, desc={}",
+                        "ffm process is exited.., ffm_type={}, exit_code={}, desc={}",
+                        ctx.create_ctx.ffm_type,
                         poll_exit_rst.exit_code,
                         poll_exit_rst.exit_desc
                     );
@@ -174,89 +356,105 @@ async fn update_ffm_exit_status(
     Ok(())
 }
 
-pub async fn _spawn_ffmpegs_process(
-    receiver_type: Option<&RunnerFFMType>,
-    recorder_type: Option<&RunnerFFMType>,
-    proc_ctx_map: &mut RunnerFFMpegInnerProcCtxMap,
+async fn spawn_ffm(
+    proc_ctx: &mut RunnerFFMpegInnerProcCtx,
+    spawn_retry_max_time_ms: EchoTimeDuration,
 ) -> Result<(), RunnerFFMError> {
-    let mut spawn_retry_max_time_ms = EchoTimeDuration::from_millis(10000);
+    proc_ctx.spawn_instant = Some(EchoTimeInstant::now());
 
-    let mut err_no: i32 = 0;
+    let _spawn_rst =
+        spawn_ffmpeg_from_creation_ctx(&mut proc_ctx.create_ctx, spawn_retry_max_time_ms).await;
 
-    let mut last_err: Option<RunnerFFMError> = None;
-    let mut last_err_msg = Option::<String>::None;
-    let mut spawned_proc_ffm_types: Vec<RunnerFFMType> = Vec::new();
-
-    //
-    // spawn receiver
-    //
+    match _spawn_rst {
+        Ok((ffmpeg_proc, ffmpeg_proc_cmd)) => {
+            metrics::FFM_SPAWN_TOTAL
+                .with_label_values(&[proc_ctx.create_ctx.ffm_type.to_string().as_str(), "ok"])
+                .inc();
 
-    if last_err.is_none() {
-        if receiver_type.is_some() {
-            let mut proc_ctx = proc_ctx_map.get_mut(receiver_type.unwrap()).unwrap();
+            proc_ctx.is_spawned = true;
+            proc_ctx.spawn_err = None;
+            proc_ctx.paused = false;
 
-            let _spawn_rst =
-                spawn_ffmpeg_from_creation_ctx(&mut proc_ctx.create_ctx, spawn_retry_max_time_ms)
-                    .await;
+            proc_ctx.proc = Some(ffmpeg_proc);
+            proc_ctx.proc_cmd = Some(ffmpeg_proc_cmd);
 
-            if let Err(e) = _spawn_rst {
-                last_err = Some(e);
-
-                let _em = format!(
-                    "failed to spawn receiver ffmpeg process, ffm_type={}, app_name={}, cmd={}",
-                    proc_ctx.create_ctx.ffm_type,
-                    proc_ctx.create_ctx.owner_info.app_name,
-                    proc_ctx.create_ctx.ffmpeg_cmd.args_to_string()
-                );
-                log::error!("{}", _em);
+            proc_ctx.term_exit_rst = None;
+            proc_ctx.term_err = None;
 
-                proc_ctx.is_spawned = false;
-                proc_ctx.spawn_err = Some(RunnerFFMError::FFMpegSpawnErr(_em.to_string()));
+            Ok(())
+        }
+        Err(e) => {
+            let _em = format!(
+                "failed to spawn ffmpeg process, ffm_type={}, app_name={}, cmd={}, e={}",
+                proc_ctx.create_ctx.ffm_type,
+                proc_ctx.create_ctx.owner_info.app_name,
+                proc_ctx.create_ctx.ffmpeg_cmd.args_to_string(),
+                e.to_string()
+            );
+            log::error!("{}", _em);
 
-                last_err_msg = Some(_em);
-            } else {
-                let (ffmpeg_proc, ffmpeg_proc_cmd) = _spawn_rst.unwrap();
+            metrics::FFM_SPAWN_TOTAL
+                .with_label_values(&[proc_ctx.create_ctx.ffm_type.to_string().as_str(), "err"])
+                .inc();
 
-                proc_ctx.is_spawned = true;
-                proc_ctx.proc = Some(ffmpeg_proc);
-                proc_ctx.proc_cmd = Some(ffmpeg_proc_cmd);
+            proc_ctx.is_spawned = false;
+            proc_ctx.spawn_err = Some(RunnerFFMError::FFMpegSpawnErr(_em.to_string()));
 
-                spawned_proc_ffm_types.push(proc_ctx.create_ctx.ffm_type);
-            }
+            Err(RunnerFFMError::FFMpegSpawnErr(_em))
         }
     }
+}
+
+pub async fn _spawn_ffmpegs_process(
+    start_order: &RunnerFFMTypeAry,
+    proc_ctx_map: &mut RunnerFFMpegInnerProcCtxMap,
+) -> Result<(), RunnerFFMError> {
+    let spawn_retry_max_time_ms = EchoTimeDuration::from_millis(10000);
+
+    let mut last_err: Option<RunnerFFMError> = None;
+    let mut spawned_proc_ffm_types: Vec<RunnerFFMType> = Vec::new();
 
     //
-    // spawn recorder
+    // spawn stages by start order
     //
 
-    if last_err.is_none() {
-        if recorder_type.is_some() {
-            let mut proc_ctx = proc_ctx_map.get_mut(recorder_type.unwrap()).unwrap();
+    for ffm_type in start_order.iter() {
+        let proc_ctx = proc_ctx_map.get_mut(ffm_type).unwrap();
+        let critical = proc_ctx.create_ctx.policy.critical;
 
-            let _spawn_rst =
-                spawn_ffmpeg_from_creation_ctx(&mut proc_ctx.create_ctx, spawn_retry_max_time_ms)
-                    .await;
+        let deps_spawned = proc_ctx
+            .create_ctx
+            .policy
+            .depends_on
+            .iter()
+            .all(|dep| spawned_proc_ffm_types.contains(dep));
 
-            if let Err(e) = _spawn_rst {
-                last_err = Some(e);
+        if !deps_spawned {
+            let _em = format!(
+                "skipped spawning ffmpeg process, dependency is not spawned, ffm_type={}, depends_on={:?}",
+                ffm_type, proc_ctx.create_ctx.policy.depends_on
+            );
+            log::error!("{}", _em);
 
-                let _em = format!(
-                    "failed to spawn recorder ffmpeg process, ffm_type={}, app_name={}, cmd={}",
-                    proc_ctx.create_ctx.ffm_type,
-                    proc_ctx.create_ctx.owner_info.app_name,
-                    proc_ctx.create_ctx.ffmpeg_cmd.args_to_string()
-                );
+            proc_ctx.spawn_err = Some(RunnerFFMError::FFMpegSpawnErr(_em.to_string()));
+
+            if critical {
+                last_err = Some(RunnerFFMError::FFMpegSpawnErr(_em));
+                break;
+            }
 
-                log::error!("{}", _em);
+            continue;
+        }
 
-                last_err_msg = Some(_em);
-            } else {
-                let (ffmpeg_proc, ffmpeg_proc_cmd) = _spawn_rst.unwrap();
+        match spawn_ffm(proc_ctx, spawn_retry_max_time_ms).await {
+            Ok(_) => spawned_proc_ffm_types.push(*ffm_type),
+            Err(e) => {
+                if critical {
+                    last_err = Some(e);
+                    break;
+                }
 
-                proc_ctx.proc = Some(ffmpeg_proc);
-                proc_ctx.proc_cmd = Some(ffmpeg_proc_cmd);
-                spawned_proc_ffm_types.push(proc_ctx.create_ctx.ffm_type);
+                // non-critical stage, session keeps going without this stage
             }
         }
     }
@@ -270,15 +468,12 @@ pub async fn _spawn_ffmpegs_process(
         // terminate spawned ffmpeg
         //
 
-        log::debug!("[cleanup] error occured in spawning process, cleanup  spawned ffmpeg process by reversed order, e={}, desc={}", 
-            last_err.as_ref().unwrap().to_string(),
-            last_err_msg.as_ref().unwrap().to_string());
+        log::debug!("[cleanup] error occured in spawning process, cleanup  spawned ffmpeg process by reversed order, e={}",
+            last_err.as_ref().unwrap().to_string());
 
         // terminate ffm process by reversed spawned order
         for ffm_type in spawned_proc_ffm_types.iter().rev() {
-            // for (ffm_type, ctx) in proc_ctx_map.iter_mut() {
             let ctx = proc_ctx_map.get_mut(ffm_type).unwrap();
-            let desc = String::new();
 
             log::debug!("[cleanup] cur_ffm_type={}", ctx.create_ctx.ffm_type);
 
@@ -335,9 +530,8 @@ pub async fn _spawn_ffmpegs_process(
         }
 
         return Err(RunnerFFMError::FFMpegSpawnErr(format!(
-            "failed to spawn receiver/recorder ffmpeg process, e={}, desc={}",
-            last_err.as_ref().unwrap().to_string(),
-            last_err_msg.as_ref().unwrap().to_string()
+            "failed to spawn ffmpeg pipeline, e={}",
+            last_err.as_ref().unwrap().to_string()
         )));
     }
 
@@ -360,17 +554,13 @@ impl RunnerFFMpegInner {
         //
         self.responder.send(RunnerFFMpegMsg::Spawning).unwrap();
 
-        if let Err(e) = _spawn_ffmpegs_process(
-            self.receiver_type.as_ref(),
-            self.recorder_type.as_ref(),
-            &mut self.proc_ctx_map,
-        )
-        .await
-        {
+        if let Err(e) = _spawn_ffmpegs_process(&self.start_order, &mut self.proc_ctx_map).await {
             run_rst = Err(e);
             return self.on_exit(run_rst).await;
         }
 
+        self.publish_stage_stats().await;
+
         self.responder.send(RunnerFFMpegMsg::BeginRunning).unwrap();
 
         let poll_timeout_ms = ECHO_TIME_DURATION_MS!(50);
@@ -405,6 +595,23 @@ impl RunnerFFMpegInner {
                 break 'entry;
             }
 
+            if !self.session_down {
+                if let Some(ffm_type) = get_exited_critical_ffm(&self.proc_ctx_map) {
+                    // ex) publisher is disconnected, rtmp receiver is exited
+                    log::info!(
+                        "[FFMpegInner::run] critical stage is exited, taking session down.., ffm_type={}",
+                        ffm_type
+                    );
+
+                    self.session_down = true;
+                    self.terminate_stages(true).await;
+                } else {
+                    self.respawn_exited_stages().await;
+                }
+            }
+
+            self.publish_stage_stats().await;
+
             if get_ffm_running_cnt(&self.proc_ctx_map) == 0 {
                 run_rst = Ok(RunnerFFMpegInnerExitRst {
                     all_terminated: true,
@@ -424,6 +631,8 @@ impl RunnerFFMpegInner {
         &mut self,
         run_rst: Result<RunnerFFMpegInnerExitRst, RunnerFFMError>,
     ) -> Result<RunnerFFMpegInnerExitRst, RunnerFFMError> {
+        self.publish_stage_stats().await;
+
         let mut do_respawn: bool = false;
         let mut response_msg: RunnerFFMpegMsg;
 
@@ -462,13 +671,255 @@ impl RunnerFFMpegInner {
     }
 
     //
-    // proc_message
+    // stages
     //
 
-    async fn proc_message(&mut self) -> Result<Option<RunnerFFMpegInnerExitRst>, RunnerFFMError> {
+    // share process state of stages to RunnerFFMpeg::stats, by start order
+    async fn publish_stage_stats(&self) {
+        let mut stats = Vec::new();
+
+        for ffm_type in self.start_order.iter() {
+            let ctx = self.proc_ctx_map.get(ffm_type).unwrap();
+
+            let state = if ctx.proc.is_none() {
+                if ctx.spawn_err.is_some() {
+                    DriverProcState::SpawnFailed
+                } else {
+                    DriverProcState::NotSpawned
+                }
+            } else if ctx.term_exit_rst.is_some() {
+                DriverProcState::Exited
+            } else if ctx.paused {
+                DriverProcState::Paused
+            } else {
+                DriverProcState::Running
+            };
+
+            stats.push(DriverProcStats {
+                name: format!("{:?}", ffm_type),
+                pid: ctx.proc.as_ref().and_then(|x| x.id()),
+                state,
+                respawn_cnt: ctx.respawn_cnt,
+                exit_code: ctx.term_exit_rst.as_ref().map(|x| x.exit_code),
+                exit_desc: ctx.term_exit_rst.as_ref().map(|x| x.exit_desc.clone()),
+            });
+        }
+
+        *self.stage_stats.write().await = stats;
+    }
+
+    // terminate running stages by stop order
+    // > explicit_only, skip stages which exit by themselves (no_need_termination)
+    async fn terminate_stages(&mut self, explicit_only: bool) {
         // maximum 2 second
         let poll_term_timeout_ms = ECHO_TIME_DURATION_MS!(TERMINATE_TIMEOUT_MS);
 
+        // paused process does not handle SIGTERM,
+        // paused recorder does not exit by itself (no_need_termination)
+        if self.paused.is_some() {
+            if let Err(e) = self.resume_stages().await {
+                log::error!(
+                    "[FFMpegInner::terminate_stages] failed to resume paused stages, e={}",
+                    e.to_string()
+                );
+            }
+        }
+
+        for ffm_type in self.stop_order.clone().iter() {
+            let ctx = self.proc_ctx_map.get(ffm_type).unwrap();
+
+            if !is_ffm_running(ctx) {
+                continue;
+            }
+
+            if explicit_only && ctx.create_ctx.policy.no_need_termination {
+                continue;
+            }
+
+            let _term_rst =
+                terminate_ffm(ffm_type, &mut self.proc_ctx_map, poll_term_timeout_ms).await;
+
+            match _term_rst {
+                Err(RunnerFFMError::FFMpegTermTimeout) => {}
+                Err(e) => {
+                    log::error!(
+                        "[FFMpegInner::terminate_stages] failed to terminate, ffm_type={}, e={}",
+                        ffm_type,
+                        e.to_string()
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    //
+    // pause/resume
+    //
+
+    async fn pause_stages(&mut self, target: PauseTarget) -> Result<(), RunnerFFMError> {
+        if self.force_terminating || self.session_down {
+            return Err(RunnerFFMError::InnerOperErr(format!(
+                "session is going down, can't pause"
+            )));
+        }
+
+        if let Some(paused) = self.paused {
+            return Err(RunnerFFMError::InnerOperErr(format!(
+                "already paused, target={}",
+                paused
+            )));
+        }
+
+        // consumers are paused first
+        let ffm_types: RunnerFFMTypeAry = self
+            .stop_order
+            .iter()
+            .filter(|x| match target {
+                PauseTarget::Recorder => matches!(x, RunnerFFMType::RUNNER_FFM_RECORDER(_)),
+                PauseTarget::Pipeline => true,
+            })
+            .filter(|x| is_ffm_running(self.proc_ctx_map.get(*x).unwrap()))
+            .cloned()
+            .collect();
+
+        if ffm_types.is_empty() {
+            return Err(RunnerFFMError::InnerOperErr(format!(
+                "no running stage to pause, target={}",
+                target
+            )));
+        }
+
+        for ffm_type in ffm_types.iter() {
+            if let Err(e) = signal_ffm(ffm_type, &mut self.proc_ctx_map, true).await {
+                // stages paused so far are resumed, pipeline is not left half paused
+                self.rollback_paused_stages().await;
+
+                return Err(e);
+            }
+        }
+
+        self.paused = Some(target);
+
+        log::info!("[FFMpegInner::pause_stages] paused, target={}", target);
+
+        Ok(())
+    }
+
+    async fn resume_stages(&mut self) -> Result<(), RunnerFFMError> {
+        if self.paused.is_none() {
+            return Err(RunnerFFMError::InnerOperErr(format!("not paused")));
+        }
+
+        let mut last_err = None;
+
+        // producers are resumed first
+        for ffm_type in self.start_order.clone().iter() {
+            if !self.proc_ctx_map.get(ffm_type).unwrap().paused {
+                continue;
+            }
+
+            if let Err(e) = signal_ffm(ffm_type, &mut self.proc_ctx_map, false).await {
+                log::error!(
+                    "[FFMpegInner::resume_stages] failed to resume, ffm_type={}, e={}",
+                    ffm_type,
+                    e.to_string()
+                );
+                last_err = Some(e);
+            }
+        }
+
+        log::info!(
+            "[FFMpegInner::resume_stages] resumed, target={}",
+            self.paused.unwrap()
+        );
+
+        self.paused = None;
+
+        match last_err {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    async fn rollback_paused_stages(&mut self) {
+        for ffm_type in self.start_order.clone().iter() {
+            if !self.proc_ctx_map.get(ffm_type).unwrap().paused {
+                continue;
+            }
+
+            if let Err(e) = signal_ffm(ffm_type, &mut self.proc_ctx_map, false).await {
+                log::error!(
+                    "[FFMpegInner::rollback_paused_stages] failed to resume, ffm_type={}, e={}",
+                    ffm_type,
+                    e.to_string()
+                );
+            }
+        }
+    }
+
+    fn respond_pause_msg(&self, responder: PauseMsgOCResponder, rst: Result<(), RunnerFFMError>) {
+        if let Err(_) = responder.send(rst) {
+            log::error!(
+                "[RunnerFFMpegInner::proc_meesage] failed to send response of pause/resume"
+            );
+        }
+    }
+
+    // respawn non-critical stages which are exited while the session is alive
+    async fn respawn_exited_stages(&mut self) {
+        let spawn_retry_max_time_ms = EchoTimeDuration::from_millis(10000);
+
+        for ffm_type in self.start_order.clone().iter() {
+            let ctx = self.proc_ctx_map.get_mut(ffm_type).unwrap();
+            let policy = &ctx.create_ctx.policy;
+
+            if policy.critical || !policy.auto_respawn {
+                continue;
+            }
+
+            // running, or never spawned (dependency failed)
+            if ctx.proc.is_none() || ctx.term_exit_rst.is_none() {
+                continue;
+            }
+
+            if ctx.respawn_cnt >= STAGE_RESPAWN_MAX_CNT {
+                continue;
+            }
+
+            if let Some(spawn_instant) = ctx.spawn_instant {
+                if spawn_instant.elapsed() < ECHO_TIME_DURATION_MS!(STAGE_RESPAWN_INTERVAL_MS) {
+                    continue;
+                }
+            }
+
+            ctx.respawn_cnt += 1;
+
+            metrics::FFM_RESPAWN_TOTAL
+                .with_label_values(&[ffm_type.to_string().as_str()])
+                .inc();
+
+            log::info!(
+                "[FFMpegInner::respawn_exited_stages] respawning stage, ffm_type={}, respawn_cnt={}",
+                ffm_type,
+                ctx.respawn_cnt
+            );
+
+            if let Err(e) = spawn_ffm(ctx, spawn_retry_max_time_ms).await {
+                log::error!(
+                    "[FFMpegInner::respawn_exited_stages] failed to respawn stage, ffm_type={}, e={}",
+                    ffm_type,
+                    e.to_string()
+                );
+            }
+        }
+    }
+
+    //
+    // proc_message
+    //
+
+    async fn proc_message(&mut self) -> Result<Option<RunnerFFMpegInnerExitRst>, RunnerFFMError> {
         //tokio::time::sleep(ECHO_TIME_DURATION_MS!(10)).await;
         ECHO_ASYNC_SLEEP_MS!(10);
 
@@ -485,49 +936,7 @@ impl RunnerFFMpegInner {
                             "FFMpegInnerMsg::Terminate"
                         );
 
-                        if self.receiver_type.is_some() {
-                            // terminate receiver
-                            let _term_rst = terminate_ffm(
-                                self.receiver_type.as_ref().unwrap(),
-                                &mut self.proc_ctx_map,
-                                poll_term_timeout_ms,
-                            )
-                            .await;
-
-                            match _term_rst {
-                                Err(RunnerFFMError::FFMpegTermTimeout) => {}
-                                Err(e) => {
-                                    log::error!(
-                                        "[FFMpegInner::run_inner, proc_message,\
-                                     FFMpegInnerMsg::Terminate] failed to terminate, e={}",
-                                        e.to_string()
-                                    );
-                                }
-                                _ => {}
-                            }
-                        }
-
-                        if self.recorder_type.is_some() {
-                            // terminate recorder
-                            let _term_rst = terminate_ffm(
-                                self.recorder_type.as_ref().unwrap(),
-                                &mut self.proc_ctx_map,
-                                poll_term_timeout_ms,
-                            )
-                            .await;
-
-                            match _term_rst {
-                                Err(RunnerFFMError::FFMpegTermTimeout) => {}
-                                Err(e) => {
-                                    log::error!(
-                                        "[FFMpegInner::run_inner, proc_message,\
-                                     FFMpegInnerMsg::Terminate] failed to terminate, e={}",
-                                        e.to_string()
-                                    );
-                                }
-                                _ => {}
-                            }
-                        }
+                        self.terminate_stages(false).await;
 
                         //
                         // check termination status and respond
@@ -614,7 +1023,20 @@ impl RunnerFFMpegInner {
                             Err(e) => Err(e),
                         }
                     } // end of message (RunnerFFMpegInnerMsg::Terminate)
-                    _ => Ok(None),
+
+                    RunnerFFMpegInnerMsg::Pause(responder, target) => {
+                        let rst = self.pause_stages(target).await;
+                        self.respond_pause_msg(responder, rst);
+
+                        Ok(None)
+                    }
+
+                    RunnerFFMpegInnerMsg::Resume(responder) => {
+                        let rst = self.resume_stages().await;
+                        self.respond_pause_msg(responder, rst);
+
+                        Ok(None)
+                    }
                 };
             }
             // error on channel, may channel is closed ?
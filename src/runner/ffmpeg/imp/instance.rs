@@ -1,23 +1,28 @@
+use crate::comm::{EchoArc, EchoAsyncRwLock};
 use crate::comm_media::MediaType;
 use crate::config::Config;
+use crate::runner::ffmpeg::loudnorm::{LoudnormPrm, LOUDNORM_ACODEC};
+use crate::runner::ffmpeg::playlist::INGEST_DIR_NAME;
 use crate::runner::ffmpeg::receiver::rtmp as FFMpegRTMPReceiver;
 use crate::runner::ffmpeg::recorder::playlist_m3u8 as FFMpegM3u8Recorder;
+use crate::runner::ffmpeg::slate;
 use crate::runner::{
-    ffmpeg::{FFMpegCmd, RunnerFFMCreateCtx, RunnerFFMError, RunnerFFMpeg},
+    ffmpeg::{FFMpegCmd, RunnerFFMCreateCtx, RunnerFFMCreateCtxAry, RunnerFFMError, RunnerFFMpeg},
     message::RecvWorkerMsgSend,
 };
-use crate::service::api::reqres::publish::ReqPropReceiverPrm;
 use crate::{EchoPathBufToString, PropMedia};
 use std::path::PathBuf;
 
 pub fn new(
     config: Config,
     recv_worker_msg_send: RecvWorkerMsgSend,
-    runner_prms: ReqPropReceiverPrm,
+    runner_args: String,
     media_prms: PropMedia,
     app_name: String,
     sess_key: String,
     publish_port: u16,
+    loudnorm: Option<LoudnormPrm>,
+    backup_publish_port: Option<u16>,
 ) -> Result<RunnerFFMpeg, RunnerFFMError> {
     let hls_root_dir = config.echo_hls_root_dir.to_str();
 
@@ -36,7 +41,6 @@ pub fn new(
     //    = fs::canonicalize(&hls_out_path).unwrap().to_str().unwrap().to_string();
 
     let ffmpeg_path = config.echo_ffmpeg_path.clone();
-    let listen_ip = format!("0.0.0.0");
 
     let hls_out_root_path = config
         .echo_hls_root_dir
@@ -52,12 +56,6 @@ pub fn new(
         .into_string()
         .unwrap();
 
-    let ffmpeg_hls_time = match media_prms.media_type {
-        MediaType::Audio => config.echo_ffmpeg_hls_time_aud,
-        MediaType::Video => config.echo_ffmpeg_hls_time_vid,
-        _ => config.echo_ffmpeg_hls_time_aud,
-    };
-
     //
     // receiver
     //
@@ -67,27 +65,21 @@ pub fn new(
 
     {
         let hls_out_path = format!("{}/{}", hls_out_root_path, app_name);
+        // served playlist is written by RecvWorker, ffmpeg::playlist
+        let hls_ingest_path = format!("{}/{}", hls_out_path, INGEST_DIR_NAME);
         let ffmpeg_log_file_path =
             format!("{}/{}_receiver.log", ffmpeg_log_root_path, app_name.clone());
 
-        let receiver_cmd_rst = FFMpegRTMPReceiver::new_ffm_cmd(
-            media_prms.clone(),
+        let receiver_cmd_rst = new_receiver_cmd(
+            &config,
+            &media_prms,
             app_name.clone(),
             sess_key.clone(),
-            listen_ip.clone(),
+            runner_args.clone(),
             publish_port,
-            hls_out_path.clone(),
-            ffmpeg_path.clone(),
-            runner_prms.args.clone(),
+            hls_ingest_path.clone(),
             ffmpeg_log_file_path.clone(),
-            config.echo_ffmpeg_verbose.clone(),
-            config.echo_ffmpeg_overwrite.clone(),
-            0,
-            config.echo_ffmpeg_vcodec.clone(),
-            config.echo_ffmpeg_acodec.clone(),
-            config.echo_ffmpeg_hls_init_time.clone(),
-            ffmpeg_hls_time.clone(),
-            config.echo_ffmpeg_hls_list_size.clone(),
+            loudnorm.as_ref(),
         );
 
         receiver_cmd = match receiver_cmd_rst {
@@ -105,11 +97,25 @@ pub fn new(
         let mut _receiver_ctx = FFMpegRTMPReceiver::new_ffm_create_ctx(receiver_cmd.unwrap());
 
         _receiver_ctx.set_prop("hls_out_path", &hls_out_path);
+        _receiver_ctx.set_prop("hls_ingest_path", &hls_ingest_path);
+        _receiver_ctx.set_prop(
+            "playlist_file_path",
+            &format!("{}/playlist.m3u8", hls_out_path),
+        );
         _receiver_ctx.set_prop(
             "hls_prerole_path",
             &EchoPathBufToString!(&config.echo_hls_prerole_dir.clone()),
         );
         _receiver_ctx.set_prop("log_file_path", &ffmpeg_log_file_path);
+        _receiver_ctx.set_prop("hls_slate", if slate::is_enabled() { "1" } else { "0" });
+        _receiver_ctx.set_prop(
+            "hls_failover",
+            if backup_publish_port.is_some() {
+                "1"
+            } else {
+                "0"
+            },
+        );
 
         receiver_ctx = Some(_receiver_ctx);
     }
@@ -123,12 +129,26 @@ pub fn new(
 
     {
         if config.echo_rec_enabled {
-            let rec_ffmpeg_given = match media_prms.media_type {
+            let mut rec_ffmpeg_given = match media_prms.media_type {
                 MediaType::Audio => config.echo_rec_ffmpeg_opt_aud.clone(),
                 MediaType::Video => config.echo_rec_ffmpeg_opt_vid.clone(),
                 _ => config.echo_rec_ffmpeg_opt_aud.clone(),
             };
 
+            // recorder input(hls) is not normalized, recording is normalized by recorder
+            if let Some(x) = loudnorm.as_ref() {
+                if let Some(filter) = x.recording_filter() {
+                    if rec_ffmpeg_given.is_empty() {
+                        rec_ffmpeg_given = match media_prms.media_type {
+                            MediaType::Audio => String::from("-vn -acodec copy"),
+                            _ => String::from("-vcodec copy -acodec copy"),
+                        };
+                    }
+
+                    rec_ffmpeg_given = x.apply_to_args(&rec_ffmpeg_given, &filter);
+                }
+            }
+
             let src_playlist_file_path =
                 format!("{}/{}/playlist.m3u8", hls_out_root_path, app_name);
 
@@ -186,14 +206,26 @@ pub fn new(
 
     let (runner_msg_send, runner_msg_recv) = tokio::sync::mpsc::unbounded_channel();
 
+    let mut create_ctxs: RunnerFFMCreateCtxAry = Vec::new();
+
+    create_ctxs.extend(receiver_ctx);
+    create_ctxs.extend(recorder_ctx);
+
+    for ctx in create_ctxs.iter_mut() {
+        ctx.set_owner_info(app_name.clone(), sess_key.clone());
+    }
+
     let inst = RunnerFFMpeg {
         recv_worker_msg_send,
 
         runner_msg_send,
         runner_msg_recv,
 
-        receiver_ctx,
-        recorder_ctx,
+        create_ctxs,
+
+        stage_stats: EchoArc::new(EchoAsyncRwLock::new(Vec::new())),
+
+        paused: None,
 
         inner_handle: None,
         inner_msg_send: None,
@@ -201,3 +233,55 @@ pub fn new(
 
     Ok(inst)
 }
+
+// rtmp receiver of session, primary receiver of driver and backup receiver(ffmpeg::failover)
+pub fn new_receiver_cmd(
+    config: &Config,
+    media_prms: &PropMedia,
+    app_name: String,
+    sess_key: String,
+    runner_args: String,
+    publish_port: u16,
+    hls_out_path: String,
+    ffmpeg_log_file_path: String,
+    loudnorm: Option<&LoudnormPrm>,
+) -> Result<FFMpegCmd, RunnerFFMError> {
+    let ffmpeg_hls_time = match media_prms.media_type {
+        MediaType::Audio => config.echo_ffmpeg_hls_time_aud.clone(),
+        MediaType::Video => config.echo_ffmpeg_hls_time_vid.clone(),
+        _ => config.echo_ffmpeg_hls_time_aud.clone(),
+    };
+
+    // normalized audio is encoded, 'copy' of config can not be filtered
+    let hls_audio_filter = loudnorm.and_then(|x| x.hls_filter());
+
+    let (ffmpeg_acodec, ffmpeg_audio_bitrate) = match hls_audio_filter {
+        Some(_) => (
+            LOUDNORM_ACODEC.to_string(),
+            loudnorm.map(|x| x.bitrate.clone()),
+        ),
+        None => (config.echo_ffmpeg_acodec.clone(), None),
+    };
+
+    FFMpegRTMPReceiver::new_ffm_cmd(
+        media_prms.clone(),
+        app_name,
+        sess_key,
+        format!("0.0.0.0"),
+        publish_port,
+        hls_out_path,
+        config.echo_ffmpeg_path.clone(),
+        runner_args,
+        ffmpeg_log_file_path,
+        config.echo_ffmpeg_verbose.clone(),
+        config.echo_ffmpeg_overwrite.clone(),
+        0,
+        config.echo_ffmpeg_vcodec.clone(),
+        ffmpeg_acodec,
+        hls_audio_filter,
+        ffmpeg_audio_bitrate,
+        config.echo_ffmpeg_hls_init_time.clone(),
+        ffmpeg_hls_time,
+        config.echo_ffmpeg_hls_list_size.clone(),
+    )
+}
@@ -1,9 +1,12 @@
 pub mod begin;
+pub mod driver;
 pub mod end;
 pub mod etc;
 pub mod inner;
 pub mod instance;
+pub mod pause;
 pub mod prerole;
 pub mod reset;
 pub mod spawn;
+pub mod stats;
 pub mod tick;
@@ -0,0 +1,77 @@
+use crate::runner::ffmpeg::message::*;
+use crate::runner::ffmpeg::*;
+use crate::runner::*;
+use crate::ECHO_TIME_DURATION_MS;
+
+impl RunnerFFMpeg {
+    pub(in crate::runner) async fn pause(&mut self, target: PauseTarget) -> DriverRst {
+        log::debug!("[RunnerFFMpeg::pause] target={}", target);
+
+        let (responder, rst) = tokio::sync::oneshot::channel();
+
+        self.send_pause_msg(RunnerFFMpegInnerMsg::Pause(responder, target), rst)
+            .await?;
+
+        self.paused = Some(target);
+
+        Ok(DriverRstOk::Ok)
+    }
+
+    pub(in crate::runner) async fn resume(&mut self) -> DriverRst {
+        log::debug!("[RunnerFFMpeg::resume]");
+
+        let (responder, rst) = tokio::sync::oneshot::channel();
+
+        self.send_pause_msg(RunnerFFMpegInnerMsg::Resume(responder), rst)
+            .await?;
+
+        self.paused = None;
+
+        Ok(DriverRstOk::Ok)
+    }
+
+    async fn send_pause_msg(
+        &mut self,
+        msg: RunnerFFMpegInnerMsg,
+        rst: PauseMsgOCRst,
+    ) -> Result<(), RunnerError> {
+        let msg_name = msg.to_string();
+
+        let inner_msg_send = match self.inner_msg_send.as_ref() {
+            Some(s) if self.inner_handle.is_some() => s,
+            _ => {
+                return Err(RunnerError::RunnerDriverErr(format!(
+                    "ffmpeg inner is not running, msg={}",
+                    msg_name
+                )))
+            }
+        };
+
+        inner_msg_send.send(msg).map_err(|e| {
+            RunnerError::RunnerDriverErr(format!(
+                "failed to send msg to ffmpeg inner, msg={}, e={}",
+                msg_name,
+                e.to_string()
+            ))
+        })?;
+
+        // signaling processes takes a few milliseconds
+        let rst = tokio::time::timeout(ECHO_TIME_DURATION_MS!(TERMINATE_TIMEOUT_MS), rst)
+            .await
+            .map_err(|_| {
+                RunnerError::RunnerDriverErr(format!(
+                    "timeout on awaiting reply from ffmpeg inner, msg={}",
+                    msg_name
+                ))
+            })?
+            .map_err(|e| {
+                RunnerError::RunnerDriverErr(format!(
+                    "failed to receive reply from ffmpeg inner, msg={}, e={}",
+                    msg_name,
+                    e.to_string()
+                ))
+            })?;
+
+        rst.map_err(|e| RunnerError::RunnerDriverErr(e.to_string()))
+    }
+}
@@ -0,0 +1,94 @@
+use crate::runner::ffmpeg::*;
+use crate::runner::*;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+// bytes to read from the end of ffmpeg log, enough for several progress lines
+const INGEST_LOG_TAIL_BYTES: u64 = 4096;
+
+impl RunnerFFMpeg {
+    pub(in crate::runner) async fn stats(&self) -> DriverStats {
+        let mut stats = DriverStats::default();
+
+        stats.procs = self.stage_stats.read().await.clone();
+        stats.ingest = self.read_ingest_stats().await;
+        stats.paused = self.paused;
+
+        stats
+    }
+
+    // latest progress line of receiver ffmpeg log
+    // > frame=  750 fps= 30 q=-1.0 size=N/A time=00:00:25.00 bitrate=N/A speed=1.01x
+    async fn read_ingest_stats(&self) -> Option<DriverIngestStats> {
+        let receiver_ctx = self
+            .create_ctxs
+            .iter()
+            .find(|x| matches!(x.get_ffm_type(), RunnerFFMType::RUNNER_FFM_RECEVIER(_)))?;
+
+        let log_file_path = receiver_ctx.get_prop("log_file_path");
+
+        if log_file_path.is_empty() {
+            return None;
+        }
+
+        let mut log_file = tokio::fs::File::open(log_file_path).await.ok()?;
+        let metadata = log_file.metadata().await.ok()?;
+
+        let tail_pos = metadata.len().saturating_sub(INGEST_LOG_TAIL_BYTES);
+        log_file
+            .seek(std::io::SeekFrom::Start(tail_pos))
+            .await
+            .ok()?;
+
+        let mut buf = Vec::new();
+        log_file.read_to_end(&mut buf).await.ok()?;
+
+        let tail = String::from_utf8_lossy(&buf);
+
+        // ffmpeg overwrites progress line with '\r'
+        let progress_line = tail
+            .split(|c| c == '\r' || c == '\n')
+            .filter(|x| x.contains("time=") && x.contains("speed="))
+            .last()?;
+
+        let mut ingest = parse_ingest_progress_line(progress_line);
+
+        ingest.updated_epoch = metadata
+            .modified()
+            .ok()
+            .and_then(|x| x.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|x| x.as_secs() as i64)
+            .unwrap_or(0);
+
+        Some(ingest)
+    }
+}
+
+fn parse_ingest_progress_line(line: &str) -> DriverIngestStats {
+    let mut ingest = DriverIngestStats::default();
+
+    // "fps= 30" -> "fps=30"
+    let mut normalized = line.trim().to_string();
+    while normalized.contains("= ") {
+        normalized = normalized.replace("= ", "=");
+    }
+
+    for token in normalized.split_whitespace() {
+        let (key, val) = match token.split_once('=') {
+            Some(kv) => kv,
+            None => continue,
+        };
+
+        match key {
+            "frame" => ingest.frame = val.parse().unwrap_or(0),
+            "fps" => ingest.fps = val.parse().unwrap_or(0.0),
+            "bitrate" => {
+                ingest.bitrate_kbps = val.trim_end_matches("kbits/s").parse().unwrap_or(0.0)
+            }
+            "time" => ingest.out_time = val.to_string(),
+            "speed" => ingest.speed = val.trim_end_matches('x').parse().unwrap_or(0.0),
+            _ => {}
+        }
+    }
+
+    ingest
+}
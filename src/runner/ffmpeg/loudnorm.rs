@@ -0,0 +1,253 @@
+// runner::ffmpeg::loudnorm
+// > EBU R128 loudness normalization of session audio, PublishOverrides::loudnorm
+// > '-acodec copy' is replaced by aac encode with loudnorm and limiter
+// > recorder reads hls output, recordings are normalized by recorder only when hls is not
+use super::error::RunnerFFMError;
+use super::media_monitor::parse_ebur128_summary;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+// encoder of normalized audio
+pub const LOUDNORM_ACODEC: &str = "aac";
+
+// ebur128 of whole recording, decoded faster than realtime
+pub const REC_LOUDNESS_TIMEOUT_SEC: u64 = 600;
+
+// limiter attack/release(ms), gentle
+const LIMITER_ATTACK_MS: u32 = 5;
+const LIMITER_RELEASE_MS: u32 = 100;
+
+// loudnorm upsamples to 192kHz internally
+const LOUDNORM_OUT_SAMPLE_RATE: u32 = 48000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoudnormPrm {
+    // integrated loudness target(LUFS), EBU R128: -23
+    pub target_lufs: f64,
+    // true peak(dBTP), limiter ceiling
+    pub true_peak_db: f64,
+    // loudness range target(LU)
+    pub lra: f64,
+    // bitrate of aac encode
+    pub bitrate: String,
+
+    // normalized independently
+    pub hls: bool,
+    pub recording: bool,
+}
+
+impl Default for LoudnormPrm {
+    fn default() -> Self {
+        LoudnormPrm {
+            target_lufs: -23.0,
+            true_peak_db: -1.0,
+            lra: 11.0,
+            bitrate: String::from("128k"),
+            hls: true,
+            recording: true,
+        }
+    }
+}
+
+impl LoudnormPrm {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(-70.0..=-5.0).contains(&self.target_lufs) {
+            return Err(format!(
+                "target_lufs must be in -70..-5, target_lufs={}",
+                self.target_lufs
+            ));
+        }
+
+        if !(-9.0..=0.0).contains(&self.true_peak_db) {
+            return Err(format!(
+                "true_peak_db must be in -9..0, true_peak_db={}",
+                self.true_peak_db
+            ));
+        }
+
+        if !(1.0..=50.0).contains(&self.lra) {
+            return Err(format!("lra must be in 1..50, lra={}", self.lra));
+        }
+
+        Ok(())
+    }
+
+    // single pass(live) loudnorm, limiter below true peak, resampled back
+    pub fn filter_chain(&self) -> String {
+        // dBTP to linear
+        let limit = 10f64.powf(self.true_peak_db / 20.0);
+
+        format!(
+            "loudnorm=I={}:TP={}:LRA={},alimiter=limit={:.3}:attack={}:release={}:level=0,aresample={}",
+            self.target_lufs,
+            self.true_peak_db,
+            self.lra,
+            limit,
+            LIMITER_ATTACK_MS,
+            LIMITER_RELEASE_MS,
+            LOUDNORM_OUT_SAMPLE_RATE
+        )
+    }
+
+    // filter of receiver(hls output)
+    pub fn hls_filter(&self) -> Option<String> {
+        self.hls.then(|| self.filter_chain())
+    }
+
+    // filter of recorder, none if recorder input(hls) is normalized already
+    pub fn recording_filter(&self) -> Option<String> {
+        (self.recording && !self.hls).then(|| self.filter_chain())
+    }
+
+    // loudness of recordings is measured and tagged on finalization
+    pub fn tags_recording(&self) -> bool {
+        self.hls || self.recording
+    }
+
+    // audio codec args of given recorder args are replaced by encode with filter
+    // > "-v quiet -vn -acodec copy" -> "-v quiet -vn -af {filter} -acodec aac -b:a 128k"
+    pub fn apply_to_args(&self, args: &str, filter: &str) -> String {
+        let mut tokens = args.split_whitespace();
+        let mut applied = Vec::new();
+
+        while let Some(x) = tokens.next() {
+            match x {
+                "-acodec" | "-c:a" | "-codec:a" | "-af" | "-filter:a" | "-b:a" => {
+                    tokens.next();
+                }
+                _ => applied.push(x.to_string()),
+            }
+        }
+
+        applied.extend([
+            format!("-af {}", filter),
+            format!("-acodec {}", LOUDNORM_ACODEC),
+            format!("-b:a {}", self.bitrate),
+        ]);
+
+        applied.join(" ")
+    }
+}
+
+//
+// recording loudness
+//
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingLoudness {
+    pub file: String,
+    pub integrated_lufs: Option<f64>,
+    pub peak_dbfs: Option<f64>,
+    pub target_lufs: f64,
+}
+
+// measures integrated loudness of recording, written into container metadata
+// > remuxed into hidden temporary file of same directory, and renamed over recording
+pub async fn tag_recording_loudness(
+    ffmpeg_path: &str,
+    file_path: &Path,
+    prm: &LoudnormPrm,
+) -> Result<RecordingLoudness, RunnerFFMError> {
+    let measured = run_ffmpeg(
+        ffmpeg_path,
+        &[
+            "-i",
+            &file_path.to_string_lossy(),
+            "-vn",
+            "-af",
+            "ebur128=peak=sample:framelog=verbose",
+            "-f",
+            "null",
+            "-",
+        ],
+    )
+    .await?;
+
+    let (integrated_lufs, peak_dbfs) = parse_ebur128_summary(&measured);
+
+    let file_name = file_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    // not counted as recording file(stem does not end with status)
+    let tmp_path = file_path.with_file_name(format!(".{}", file_name));
+
+    let integrated = integrated_lufs
+        .map(|x| format!("{:.1}", x))
+        .unwrap_or_else(|| String::from("-inf"));
+
+    let tagged = run_ffmpeg(
+        ffmpeg_path,
+        &[
+            "-y",
+            "-i",
+            &file_path.to_string_lossy(),
+            "-map",
+            "0",
+            "-c",
+            "copy",
+            "-metadata",
+            &format!("loudness_integrated_lufs={}", integrated),
+            "-metadata",
+            &format!("loudness_target_lufs={}", prm.target_lufs),
+            &tmp_path.to_string_lossy(),
+        ],
+    )
+    .await;
+
+    let renamed = match tagged {
+        Ok(_) => tokio::fs::rename(&tmp_path, file_path)
+            .await
+            .map_err(|e| RunnerFFMError::FileOperErr(e.to_string())),
+        Err(e) => Err(e),
+    };
+
+    if let Err(e) = renamed {
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+        return Err(e);
+    }
+
+    Ok(RecordingLoudness {
+        file: file_name,
+        integrated_lufs,
+        peak_dbfs,
+        target_lufs: prm.target_lufs,
+    })
+}
+
+// stderr of ffmpeg
+async fn run_ffmpeg(ffmpeg_path: &str, args: &[&str]) -> Result<String, RunnerFFMError> {
+    let output = tokio::process::Command::new(ffmpeg_path)
+        .args(["-hide_banner", "-nostats"])
+        .args(args)
+        .kill_on_drop(true)
+        .output();
+
+    let output = tokio::time::timeout(Duration::from_secs(REC_LOUDNESS_TIMEOUT_SEC), output)
+        .await
+        .map_err(|_| {
+            RunnerFFMError::OperErr(format!("ffmpeg of recording loudness is timeoutted"))
+        })?
+        .map_err(|e| {
+            RunnerFFMError::OperErr(format!(
+                "failed to run ffmpeg, ffmpeg_path={}, e={}",
+                ffmpeg_path, e
+            ))
+        })?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if !output.status.success() {
+        return Err(RunnerFFMError::OperErr(format!(
+            "ffmpeg of recording loudness is exited with error, status={}, stderr={}",
+            output.status,
+            stderr.lines().last().unwrap_or_default()
+        )));
+    }
+
+    Ok(stderr)
+}
@@ -0,0 +1,642 @@
+// runner::ffmpeg::media_monitor
+// > content of published stream(audio level, black/frozen video), measured by ffmpeg on hls segments listed since last check
+// > new segments are measured together, one short ffmpeg process per session within interval
+use super::error::RunnerFFMError;
+use super::playlist::get_segments;
+use super::slate::SLATE_SEGMENT_PREFIX;
+use crate::comm::EchoUtc;
+use crate::comm_media::MediaType;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+
+// peak(dBFS) of segments below threshold is silence, default: SILENCE_THRESHOLD_DB_DEFAULT
+pub const SILENCE_THRESHOLD_DB_ENV: &str = "ECHO_SILENCE_THRESHOLD_DB";
+pub const SILENCE_THRESHOLD_DB_DEFAULT: f64 = -50.0;
+
+// silence_started is emitted after silence lasts, default: SILENCE_MIN_SEC_DEFAULT
+pub const SILENCE_MIN_SEC_ENV: &str = "ECHO_SILENCE_MIN_SEC";
+pub const SILENCE_MIN_SEC_DEFAULT: i64 = 10;
+
+// session is ended after silence lasts(minutes), default 0: never
+pub const SILENCE_END_SESSION_MIN_ENV: &str = "ECHO_SILENCE_END_SESSION_MIN";
+
+// video_black, video_frozen are emitted after picture lasts, default: VIDEO_CONTENT_MIN_SEC_DEFAULT
+pub const VIDEO_BLACK_MIN_SEC_ENV: &str = "ECHO_VIDEO_BLACK_MIN_SEC";
+pub const VIDEO_FROZEN_MIN_SEC_ENV: &str = "ECHO_VIDEO_FROZEN_MIN_SEC";
+pub const VIDEO_CONTENT_MIN_SEC_DEFAULT: i64 = 10;
+
+// measured segments are black(frozen) if black(frozen) part covers
+pub const VIDEO_CONTENT_COVER_RATIO: f64 = 0.9;
+
+// recent video events kept in VideoContent
+pub const VIDEO_EVENTS_KEEP_MAX: usize = 10;
+
+pub const MONITOR_FFMPEG_TIMEOUT_MS: u64 = 10000;
+
+// timestamps start from 0 in measured segments
+const VIDEO_CONTENT_FILTER: &str =
+    "setpts=PTS-STARTPTS,blackdetect=d=1:pic_th=0.98:pix_th=0.10,freezedetect=n=-60dB:d=2";
+
+//
+// monitor
+//
+
+pub enum MediaMonitor {
+    Audio(AudioLevelMonitor),
+    Video(VideoContentMonitor),
+}
+
+impl MediaMonitor {
+    pub fn new(media_type: MediaType, ffmpeg_path: String, playlist_path: String) -> Self {
+        match media_type {
+            MediaType::Audio => Self::Audio(AudioLevelMonitor::new(ffmpeg_path, playlist_path)),
+            MediaType::Video => Self::Video(VideoContentMonitor::new(ffmpeg_path, playlist_path)),
+        }
+    }
+
+    // call periodically
+    pub async fn check(&mut self) -> Result<Vec<MediaMonitorEvent>, RunnerFFMError> {
+        match self {
+            Self::Audio(x) => x.check().await,
+            Self::Video(x) => x.check().await,
+        }
+    }
+}
+
+// lasting content condition of stream(silence, black, frozen)
+#[derive(Debug, Clone, Default)]
+struct LastingCondition {
+    // some: active since(epoch)
+    since_epoch: Option<i64>,
+    // lasted min seconds, started is notified
+    notified: bool,
+}
+
+enum ConditionChange {
+    Started { since_epoch: i64 },
+    Ended { since_epoch: i64, duration_sec: i64 },
+}
+
+impl LastingCondition {
+    // active: measured segments(from now - measured_sec) are in condition
+    fn update(
+        &mut self,
+        active: bool,
+        now: i64,
+        measured_sec: f64,
+        min_sec: i64,
+    ) -> Option<ConditionChange> {
+        if !active {
+            let since_epoch = self.since_epoch.take()?;
+            let notified = std::mem::replace(&mut self.notified, false);
+
+            // ended at the beginning of measured segments
+            return notified.then(|| ConditionChange::Ended {
+                since_epoch,
+                duration_sec: now - measured_sec as i64 - since_epoch,
+            });
+        }
+
+        let since_epoch = *self.since_epoch.get_or_insert(now - measured_sec as i64);
+
+        if self.notified || now - since_epoch < min_sec {
+            return None;
+        }
+
+        self.notified = true;
+
+        Some(ConditionChange::Started { since_epoch })
+    }
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key)
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(default)
+}
+
+//
+// audio level
+//
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AudioLevel {
+    // ebur128 integrated loudness of last measured segments
+    pub loudness_lufs: Option<f64>,
+    // sample peak, none: -inf(digital silence)
+    pub peak_dbfs: Option<f64>,
+    pub measured_epoch: i64,
+    // duration of last measured segments
+    pub measured_sec: f64,
+    pub silent: bool,
+    // some: silent since(epoch)
+    pub silence_since_epoch: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MediaMonitorEvent {
+    SilenceStarted {
+        since_epoch: i64,
+        peak_dbfs: Option<f64>,
+        threshold_db: f64,
+    },
+    SilenceEnded {
+        since_epoch: i64,
+        duration_sec: i64,
+        loudness_lufs: Option<f64>,
+    },
+    // silence lasted SILENCE_END_SESSION_MIN_ENV, session is ended
+    SilenceTimeout {
+        since_epoch: i64,
+        duration_sec: i64,
+    },
+    // active: started, or ended(duration_sec)
+    VideoBlack {
+        active: bool,
+        since_epoch: i64,
+        duration_sec: Option<i64>,
+    },
+    VideoFrozen {
+        active: bool,
+        since_epoch: i64,
+        duration_sec: Option<i64>,
+    },
+}
+
+impl MediaMonitorEvent {
+    // name of session event
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SilenceStarted { .. } => "silence_started",
+            Self::SilenceEnded { .. } => "silence_ended",
+            Self::SilenceTimeout { .. } => "silence_timeout",
+            Self::VideoBlack { .. } => "video_black",
+            Self::VideoFrozen { .. } => "video_frozen",
+        }
+    }
+}
+
+pub struct AudioLevelMonitor {
+    ffmpeg_path: String,
+    segments: NewSegments,
+
+    threshold_db: f64,
+    silence_min_sec: i64,
+    // 0: session is not ended by silence
+    silence_end_session_sec: i64,
+
+    level: AudioLevel,
+    silence: LastingCondition,
+    timeout_notified: bool,
+}
+
+impl AudioLevelMonitor {
+    pub fn new(ffmpeg_path: String, playlist_path: String) -> Self {
+        AudioLevelMonitor {
+            ffmpeg_path,
+            segments: NewSegments::new(playlist_path),
+            threshold_db: env_or(SILENCE_THRESHOLD_DB_ENV, SILENCE_THRESHOLD_DB_DEFAULT),
+            silence_min_sec: env_or(SILENCE_MIN_SEC_ENV, SILENCE_MIN_SEC_DEFAULT),
+            silence_end_session_sec: env_or(SILENCE_END_SESSION_MIN_ENV, 0i64) * 60,
+            level: AudioLevel::default(),
+            silence: LastingCondition::default(),
+            timeout_notified: false,
+        }
+    }
+
+    pub fn level(&self) -> AudioLevel {
+        self.level.clone()
+    }
+
+    // call periodically, events of silence
+    pub async fn check(&mut self) -> Result<Vec<MediaMonitorEvent>, RunnerFFMError> {
+        let (paths, measured_sec) = match self.segments.take().await {
+            Some(x) => x,
+            // no new segment(paused, publisher disconnected), silence is not measured
+            None => return Ok(Vec::new()),
+        };
+
+        let (loudness_lufs, peak_dbfs) = measure_loudness(&self.ffmpeg_path, &paths).await?;
+
+        let now = EchoUtc::now().timestamp();
+
+        self.level.loudness_lufs = loudness_lufs;
+        self.level.peak_dbfs = peak_dbfs;
+        self.level.measured_epoch = now;
+        self.level.measured_sec = measured_sec;
+        self.level.silent = peak_dbfs.map(|x| x < self.threshold_db).unwrap_or(true);
+
+        Ok(self.update_silence(now, measured_sec))
+    }
+
+    fn update_silence(&mut self, now: i64, measured_sec: f64) -> Vec<MediaMonitorEvent> {
+        let mut events = Vec::new();
+
+        match self
+            .silence
+            .update(self.level.silent, now, measured_sec, self.silence_min_sec)
+        {
+            Some(ConditionChange::Started { since_epoch }) => {
+                events.push(MediaMonitorEvent::SilenceStarted {
+                    since_epoch,
+                    peak_dbfs: self.level.peak_dbfs,
+                    threshold_db: self.threshold_db,
+                })
+            }
+            Some(ConditionChange::Ended {
+                since_epoch,
+                duration_sec,
+            }) => events.push(MediaMonitorEvent::SilenceEnded {
+                since_epoch,
+                duration_sec,
+                loudness_lufs: self.level.loudness_lufs,
+            }),
+            None => {}
+        }
+
+        self.level.silence_since_epoch = self.silence.since_epoch;
+
+        let since_epoch = match self.silence.since_epoch {
+            Some(x) => x,
+            None => {
+                self.timeout_notified = false;
+                return events;
+            }
+        };
+
+        let duration_sec = now - since_epoch;
+
+        if self.silence_end_session_sec > 0
+            && !self.timeout_notified
+            && duration_sec >= self.silence_end_session_sec
+        {
+            self.timeout_notified = true;
+
+            events.push(MediaMonitorEvent::SilenceTimeout {
+                since_epoch,
+                duration_sec,
+            });
+        }
+
+        events
+    }
+}
+
+//
+// video content
+//
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VideoContent {
+    pub measured_epoch: i64,
+    // duration of last measured segments, and black/frozen part of it
+    pub measured_sec: f64,
+    pub black_sec: f64,
+    pub frozen_sec: f64,
+    // some: black(frozen) since(epoch)
+    pub black_since_epoch: Option<i64>,
+    pub frozen_since_epoch: Option<i64>,
+    // recent video_black, video_frozen
+    pub events: Vec<MediaMonitorEvent>,
+}
+
+pub struct VideoContentMonitor {
+    ffmpeg_path: String,
+    segments: NewSegments,
+
+    black_min_sec: i64,
+    frozen_min_sec: i64,
+
+    content: VideoContent,
+    black: LastingCondition,
+    frozen: LastingCondition,
+}
+
+impl VideoContentMonitor {
+    pub fn new(ffmpeg_path: String, playlist_path: String) -> Self {
+        VideoContentMonitor {
+            ffmpeg_path,
+            segments: NewSegments::new(playlist_path),
+            black_min_sec: env_or(VIDEO_BLACK_MIN_SEC_ENV, VIDEO_CONTENT_MIN_SEC_DEFAULT),
+            frozen_min_sec: env_or(VIDEO_FROZEN_MIN_SEC_ENV, VIDEO_CONTENT_MIN_SEC_DEFAULT),
+            content: VideoContent::default(),
+            black: LastingCondition::default(),
+            frozen: LastingCondition::default(),
+        }
+    }
+
+    pub fn content(&self) -> VideoContent {
+        self.content.clone()
+    }
+
+    // call periodically, events of black, frozen picture
+    pub async fn check(&mut self) -> Result<Vec<MediaMonitorEvent>, RunnerFFMError> {
+        let (paths, measured_sec) = match self.segments.take().await {
+            Some(x) => x,
+            None => return Ok(Vec::new()),
+        };
+
+        let (black_sec, frozen_sec) =
+            measure_video_content(&self.ffmpeg_path, &paths, measured_sec).await?;
+
+        let now = EchoUtc::now().timestamp();
+        let cover_sec = measured_sec * VIDEO_CONTENT_COVER_RATIO;
+
+        self.content.measured_epoch = now;
+        self.content.measured_sec = measured_sec;
+        self.content.black_sec = black_sec;
+        self.content.frozen_sec = frozen_sec;
+
+        let mut events = Vec::new();
+
+        // black picture is frozen too, reported as black only
+        let is_black = measured_sec > 0.0 && black_sec >= cover_sec;
+        let is_frozen = measured_sec > 0.0 && frozen_sec >= cover_sec && !is_black;
+
+        if let Some(x) = self
+            .black
+            .update(is_black, now, measured_sec, self.black_min_sec)
+        {
+            events.push(match x {
+                ConditionChange::Started { since_epoch } => MediaMonitorEvent::VideoBlack {
+                    active: true,
+                    since_epoch,
+                    duration_sec: None,
+                },
+                ConditionChange::Ended {
+                    since_epoch,
+                    duration_sec,
+                } => MediaMonitorEvent::VideoBlack {
+                    active: false,
+                    since_epoch,
+                    duration_sec: Some(duration_sec),
+                },
+            });
+        }
+
+        if let Some(x) = self
+            .frozen
+            .update(is_frozen, now, measured_sec, self.frozen_min_sec)
+        {
+            events.push(match x {
+                ConditionChange::Started { since_epoch } => MediaMonitorEvent::VideoFrozen {
+                    active: true,
+                    since_epoch,
+                    duration_sec: None,
+                },
+                ConditionChange::Ended {
+                    since_epoch,
+                    duration_sec,
+                } => MediaMonitorEvent::VideoFrozen {
+                    active: false,
+                    since_epoch,
+                    duration_sec: Some(duration_sec),
+                },
+            });
+        }
+
+        self.content.black_since_epoch = self.black.since_epoch;
+        self.content.frozen_since_epoch = self.frozen.since_epoch;
+
+        self.content.events.extend(events.iter().cloned());
+
+        let len = self.content.events.len();
+        if len > VIDEO_EVENTS_KEEP_MAX {
+            self.content.events.drain(..len - VIDEO_EVENTS_KEEP_MAX);
+        }
+
+        Ok(events)
+    }
+}
+
+//
+// new segments
+//
+
+struct NewSegments {
+    playlist_path: String,
+
+    // listed segments already measured
+    measured: Vec<String>,
+}
+
+impl NewSegments {
+    fn new(playlist_path: String) -> Self {
+        NewSegments {
+            playlist_path,
+            measured: Vec::new(),
+        }
+    }
+
+    // (paths, sum of #EXTINF) of segments listed since last take
+    // > none: no new segment, playlist is not written yet or being written
+    async fn take(&mut self) -> Option<(Vec<String>, f64)> {
+        let playlist = tokio::fs::read_to_string(&self.playlist_path).await.ok()?;
+
+        if !playlist.ends_with('\n') {
+            return None;
+        }
+
+        let playlist_dir = Path::new(&self.playlist_path).parent()?;
+
+        let new_segments: Vec<(String, f64)> = get_segment_durations(&playlist)
+            .into_iter()
+            .filter(|(x, _)| !self.measured.contains(x))
+            // slate while publisher is away, not published content
+            .filter(|(x, _)| !x.starts_with(SLATE_SEGMENT_PREFIX))
+            // quarantined(#EXT-X-GAP) or deleted by hls_list_size
+            .filter(|(x, _)| playlist_dir.join(x).exists())
+            .map(|(x, d)| (playlist_dir.join(x).to_string_lossy().to_string(), d))
+            .collect();
+
+        self.measured = get_segments(&playlist)
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+
+        if new_segments.is_empty() {
+            return None;
+        }
+
+        let measured_sec = new_segments.iter().map(|x| x.1).sum();
+
+        Some((
+            new_segments.into_iter().map(|x| x.0).collect(),
+            measured_sec,
+        ))
+    }
+}
+
+// (uri, #EXTINF) in playlist order
+pub fn get_segment_durations(playlist: &str) -> Vec<(String, f64)> {
+    let mut durations = Vec::new();
+    let mut duration = 0.0;
+
+    for line in playlist.lines().map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        if let Some(x) = line.strip_prefix("#EXTINF:") {
+            duration = x
+                .split(',')
+                .next()
+                .and_then(|x| x.trim().parse().ok())
+                .unwrap_or(0.0);
+        } else if !line.starts_with('#') {
+            durations.push((line.to_string(), duration));
+            duration = 0.0;
+        }
+    }
+
+    durations
+}
+
+//
+// ffmpeg
+//
+
+// (integrated loudness, sample peak) by ebur128 summary
+// > per frame log of ebur128 is printed at verbose level, summary only
+pub async fn measure_loudness(
+    ffmpeg_path: &str,
+    segment_paths: &[String],
+) -> Result<(Option<f64>, Option<f64>), RunnerFFMError> {
+    let stderr = run_ffmpeg_filter(
+        ffmpeg_path,
+        segment_paths,
+        &["-vn", "-af", "ebur128=peak=sample:framelog=verbose"],
+    )
+    .await?;
+
+    let (loudness, peak) = parse_ebur128_summary(&stderr);
+
+    if loudness.is_none() && peak.is_none() {
+        return Err(RunnerFFMError::OperErr(format!(
+            "no ebur128 summary, segments={}",
+            segment_paths.len()
+        )));
+    }
+
+    Ok((loudness, peak))
+}
+
+// (integrated loudness, sample peak) of ebur128 summary in ffmpeg log
+// [Parsed_ebur128_0 @ 0x..] Summary:
+//   Integrated loudness:
+//     I:         -19.6 LUFS
+//   ..
+//   Sample peak:
+//     Peak:       -0.4 dBFS
+pub fn parse_ebur128_summary(stderr: &str) -> (Option<f64>, Option<f64>) {
+    let mut summary = stderr
+        .lines()
+        .skip_while(|x| !x.trim_end().ends_with("Summary:"))
+        .map(|x| x.trim());
+
+    let loudness = summary
+        .by_ref()
+        .find_map(|x| x.strip_prefix("I:"))
+        .and_then(parse_level);
+
+    let peak = summary
+        .find_map(|x| x.strip_prefix("Peak:"))
+        .and_then(parse_level);
+
+    (loudness, peak)
+}
+
+// (black, frozen) seconds of segments by blackdetect, freezedetect
+pub async fn measure_video_content(
+    ffmpeg_path: &str,
+    segment_paths: &[String],
+    measured_sec: f64,
+) -> Result<(f64, f64), RunnerFFMError> {
+    let stderr = run_ffmpeg_filter(
+        ffmpeg_path,
+        segment_paths,
+        &["-an", "-vf", VIDEO_CONTENT_FILTER],
+    )
+    .await?;
+
+    let mut black_sec = 0.0;
+    let mut frozen_sec = 0.0;
+    // freeze lasting to the end of segments is not ended
+    let mut freeze_start: Option<f64> = None;
+
+    // [blackdetect @ 0x..] black_start:0 black_end:4.004 black_duration:4.004
+    // [freezedetect @ 0x..] lavfi.freezedetect.freeze_start: 1.001
+    // [freezedetect @ 0x..] lavfi.freezedetect.freeze_duration: 3.003
+    // [freezedetect @ 0x..] lavfi.freezedetect.freeze_end: 4.004
+    for line in stderr.lines() {
+        if let Some(x) = get_log_value(line, "black_duration:") {
+            black_sec += x;
+        } else if let Some(x) = get_log_value(line, "freeze_start:") {
+            freeze_start = Some(x);
+        } else if let Some(x) = get_log_value(line, "freeze_duration:") {
+            frozen_sec += x;
+            freeze_start = None;
+        }
+    }
+
+    if let Some(x) = freeze_start {
+        frozen_sec += (measured_sec - x).max(0.0);
+    }
+
+    Ok((black_sec, frozen_sec))
+}
+
+// number after key in log line
+fn get_log_value(line: &str, key: &str) -> Option<f64> {
+    line.split_once(key)?
+        .1
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+// "-19.6 LUFS" -> -19.6, "-inf dBFS" -> none
+fn parse_level(x: &str) -> Option<f64> {
+    x.split_whitespace()
+        .next()?
+        .parse::<f64>()
+        .ok()
+        .filter(|x| x.is_finite())
+}
+
+// stderr of 'ffmpeg -i concat:{segments} {filter_args} -f null -'
+async fn run_ffmpeg_filter(
+    ffmpeg_path: &str,
+    segment_paths: &[String],
+    filter_args: &[&str],
+) -> Result<String, RunnerFFMError> {
+    let output = tokio::process::Command::new(ffmpeg_path)
+        .args(["-hide_banner", "-nostats", "-i"])
+        .arg(format!("concat:{}", segment_paths.join("|")))
+        .args(filter_args)
+        .args(["-f", "null", "-"])
+        .kill_on_drop(true)
+        .output();
+
+    let output = tokio::time::timeout(Duration::from_millis(MONITOR_FFMPEG_TIMEOUT_MS), output)
+        .await
+        .map_err(|_| RunnerFFMError::OperErr(format!("ffmpeg of media monitor is timeoutted")))?
+        .map_err(|e| {
+            RunnerFFMError::OperErr(format!(
+                "failed to run ffmpeg, ffmpeg_path={}, e={}",
+                ffmpeg_path, e
+            ))
+        })?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if !output.status.success() {
+        return Err(RunnerFFMError::OperErr(format!(
+            "ffmpeg of media monitor is exited with error, status={}, stderr={}",
+            output.status,
+            stderr.lines().last().unwrap_or_default()
+        )));
+    }
+
+    Ok(stderr)
+}
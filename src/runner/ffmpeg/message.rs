@@ -1,6 +1,7 @@
 use super::RunnerFFMError;
 use crate::comm::{EchoOCResponder, EchoOCRst};
 use crate::runner::ffmpeg::RunnerFFMpegInnerExitRst;
+use crate::runner::PauseTarget;
 use anyhow::Result;
 
 //
@@ -31,6 +32,10 @@ pub type RunnerFFMpegMsgRecv = tokio::sync::mpsc::UnboundedReceiver<RunnerFFMpeg
 #[derive(strum_macros::Display, Debug)]
 pub enum RunnerFFMpegInnerMsg {
     Terminate(TerminateMsgOCResponder),
+
+    // SIGSTOP/SIGCONT to stages
+    Pause(PauseMsgOCResponder, PauseTarget),
+    Resume(PauseMsgOCResponder),
 }
 
 pub type RunnerFFMpegInnerMsgSend = tokio::sync::mpsc::UnboundedSender<RunnerFFMpegInnerMsg>;
@@ -45,3 +50,8 @@ pub type TerminateMsgOCRst = EchoOCRst<TerminateMsgRstType>;
 pub fn create_terminate_msg_oc_sender() -> (TerminateMsgOCResponder, TerminateMsgOCRst) {
     tokio::sync::oneshot::channel::<TerminateMsgRstType>()
 }
+
+// FFMpegInnerMsg::Pause, FFMpegInnerMsg::Resume
+pub type PauseMsgRstType = Result<(), RunnerFFMError>;
+pub type PauseMsgOCResponder = EchoOCResponder<PauseMsgRstType>;
+pub type PauseMsgOCRst = EchoOCRst<PauseMsgRstType>;
@@ -1,20 +1,30 @@
 // runner::ffmpeg::mod.rs
+pub mod adts_inspect;
+pub mod capability;
 pub mod cmd;
 pub mod error;
+pub mod failover;
 pub mod imp;
+pub mod loudnorm;
+pub mod media_monitor;
 pub mod message;
+pub mod playlist;
 pub mod receiver;
 pub mod recorder;
+pub mod slate;
+pub mod stream_probe;
 use self::cmd::FFMpegCmd;
 use self::message::{
     RunnerFFMpegInnerMsgRecv, RunnerFFMpegInnerMsgSend, RunnerFFMpegMsgRecv, RunnerFFMpegMsgSend,
 };
 use super::message::RecvWorkerMsgSend;
-use super::{RunnerProcChild, RunnerProcCmd};
-use crate::comm::{EchoArc, EchoAsyncRwLock};
+use super::{
+    DriverCreatePrm, DriverProcStats, PauseTarget, ReceiverDriverBox, RunnerError, RunnerProcChild,
+    RunnerProcCmd,
+};
+use crate::comm::{EchoArc, EchoAsyncRwLock, EchoTimeInstant};
 use crate::comm_ps::PollExitStRst;
 use crate::config::Config;
-use crate::service::api::reqres::publish::ReqPropReceiverPrm;
 use crate::EchoJoinHandle;
 use crate::PropMedia;
 use serde::{Deserialize, Serialize};
@@ -30,6 +40,9 @@ type RunnerFFMProcShared = EchoArc<EchoAsyncRwLock<RunnerFFMProc>>;
 type RunnerFFMProcCmd = RunnerProcCmd;
 type RunnerFFMProcCmdShared = EchoArc<EchoAsyncRwLock<RunnerProcCmd>>;
 
+// process stats of stages, written by RunnerFFMpegInner, read by RunnerFFMpeg::stats
+pub type RunnerFFMStageStatsShared = EchoArc<EchoAsyncRwLock<Vec<DriverProcStats>>>;
+
 //
 // RunnerFFMEvtRst
 //
@@ -48,10 +61,83 @@ pub enum RunnerFFMType {
 
     // PLAYLIST_M3U8
     RUNNER_FFM_RECORDER(&'static str),
+
+    // JPEG
+    RUNNER_FFM_THUMBNAILER(&'static str),
+
+    // RTMP_PUSH
+    RUNNER_FFM_RELAY(&'static str),
+
+    // ABR_LADDER
+    RUNNER_FFM_TRANSCODER(&'static str),
 }
 
 pub type RunnerFFMTypeAry = Vec<RunnerFFMType>;
 
+//
+// RunnerFFMStagePolicy
+// > how a stage(ffmpeg process) is ordered and supervised in RunnerFFMpegInner
+//
+
+#[derive(Debug, Clone)]
+pub struct RunnerFFMStagePolicy {
+    // lower is spawned first
+    start_order: u32,
+
+    // lower is terminated first
+    stop_order: u32,
+
+    // stages which must be spawned before this stage
+    // > if one of them failed to spawn, this stage is not spawned
+    depends_on: RunnerFFMTypeAry,
+
+    // no need to terminate explicitly
+    // ex) ffmpeg playlist-m3u8 recorder
+    //     if ffmpeg process which generate playlist.m3u8 periodic is exit,
+    //        ffmpeg playlist-m3u8 recorder will exit automataically without terminate explicitly
+    no_need_termination: bool,
+
+    // failure(spawn error or exit) of this stage takes the session down
+    critical: bool,
+
+    // respawn this stage alone if it exits while the session is alive
+    // > critical stage is respawned with whole session by RecvWorker
+    auto_respawn: bool,
+}
+
+impl RunnerFFMStagePolicy {
+    pub fn new(start_order: u32, stop_order: u32) -> Self {
+        RunnerFFMStagePolicy {
+            start_order,
+            stop_order,
+            depends_on: RunnerFFMTypeAry::new(),
+            no_need_termination: false,
+            critical: false,
+            auto_respawn: false,
+        }
+    }
+
+    pub fn depends_on(mut self, ffm_type: RunnerFFMType) -> Self {
+        self.depends_on.push(ffm_type);
+        self
+    }
+
+    pub fn no_need_termination(mut self, v: bool) -> Self {
+        self.no_need_termination = v;
+        self
+    }
+
+    pub fn critical(mut self, v: bool) -> Self {
+        self.critical = v;
+        self
+    }
+
+    pub fn auto_respawn(mut self, v: bool) -> Self {
+        self.auto_respawn = v;
+        self
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunnerFFMOwnerInfo {
     app_name: String,
@@ -69,13 +155,7 @@ pub struct RunnerFFMCreateCtx {
     on_spawn: PFnOnSpawnFFMpeg,
     post_spawn: PFnPostSpawnFFMpeg,
 
-    // no need to terminate explicitly
-    // ex) ffmpeg playlist-m3u8 recorder
-    //     if ffmpeg process which generate playlist.m3u8 periodic is exit,
-    //        ffmpeg playlist-m3u8 recorder will exit automataically without terminate explicitly
-    no_need_termination: bool,
-
-    auto_respawn: bool,
+    policy: RunnerFFMStagePolicy,
 }
 // proc: Option<RunnerFFMProcShared>,
 // proc_cmd: Option<RunnerFFMProcCmdShared>,
@@ -88,8 +168,7 @@ impl RunnerFFMCreateCtx {
         ffmpeg_cmd: FFMpegCmd,
         on_spawn: PFnOnSpawnFFMpeg,
         post_spawn: PFnPostSpawnFFMpeg,
-        no_need_termination: bool,
-        auto_respawn: bool,
+        policy: RunnerFFMStagePolicy,
     ) -> Self {
         RunnerFFMCreateCtx {
             ffm_type,
@@ -104,11 +183,18 @@ impl RunnerFFMCreateCtx {
             on_spawn,
             post_spawn,
 
-            no_need_termination,
-            auto_respawn,
+            policy,
         }
     }
 
+    pub fn get_ffm_type(&self) -> RunnerFFMType {
+        self.ffm_type
+    }
+
+    pub fn get_policy(&self) -> &RunnerFFMStagePolicy {
+        &self.policy
+    }
+
     pub fn set_owner_info(&mut self, app_name: String, sess_key: String) {
         self.owner_info = RunnerFFMOwnerInfo { app_name, sess_key };
     }
@@ -148,8 +234,13 @@ pub struct RunnerFFMpeg {
     runner_msg_send: RunnerFFMpegMsgSend,
     runner_msg_recv: RunnerFFMpegMsgRecv,
 
-    receiver_ctx: Option<RunnerFFMCreateCtx>,
-    recorder_ctx: Option<RunnerFFMCreateCtx>,
+    // stages of session pipeline, receiver/recorder/..
+    create_ctxs: RunnerFFMCreateCtxAry,
+
+    stage_stats: RunnerFFMStageStatsShared,
+
+    // RunnerFFMpegInnerMsg::Pause is accepted
+    paused: Option<PauseTarget>,
 
     inner_handle: Option<EchoJoinHandle<RunnerFFMpegInnerExitRst, RunnerFFMError>>,
     inner_msg_send: Option<RunnerFFMpegInnerMsgSend>,
@@ -158,21 +249,48 @@ pub struct RunnerFFMpeg {
 pub fn create_runner_ffmpeg(
     config: Config,
     recv_worker_msg_send: RecvWorkerMsgSend,
-    runner_prms: ReqPropReceiverPrm,
+    runner_args: String,
     media_prms: PropMedia,
     app_name: String,
     sess_key: String,
     publish_port: u16,
+    loudnorm: Option<loudnorm::LoudnormPrm>,
+    backup_publish_port: Option<u16>,
 ) -> Result<RunnerFFMpeg, RunnerFFMError> {
     imp::instance::new(
         config,
         recv_worker_msg_send,
-        runner_prms,
+        runner_args,
         media_prms,
         app_name,
         sess_key,
         publish_port,
+        loudnorm,
+        backup_publish_port,
+    )
+}
+
+// PFnNewReceiverDriver for MediaReceiver::FFMPEG
+pub fn new_receiver_driver(prm: DriverCreatePrm) -> Result<ReceiverDriverBox, RunnerError> {
+    let runner = create_runner_ffmpeg(
+        prm.config,
+        prm.worker_msg_send,
+        prm.receiver_args,
+        prm.media,
+        prm.app_name,
+        prm.sess_key,
+        prm.publish_port,
+        prm.loudnorm,
+        prm.backup_publish_port,
     )
+    .map_err(|e| {
+        RunnerError::RunnerDriverErr(format!(
+            "failed to create ffmpeg receiver driver, e={}",
+            e.to_string()
+        ))
+    })?;
+
+    Ok(Box::new(runner))
 }
 
 // pub async fn spawn_ffmpeg_process(create_ctx: &mut RunnerFFMCreateCtx)
@@ -191,12 +309,14 @@ pub struct RunnerFFMpegInnerProcCtx {
     // readonly
     create_ctx: RunnerFFMCreateCtx,
 
-    no_need_termination: bool,
-    auto_respawn: bool,
-
     is_spawned: bool,
+    respawn_cnt: u32,
+    spawn_instant: Option<EchoTimeInstant>,
     spawn_err: Option<RunnerFFMError>,
 
+    // SIGSTOP is sent
+    paused: bool,
+
     proc: Option<RunnerFFMProc>,
     proc_cmd: Option<RunnerFFMProcCmd>,
 
@@ -209,11 +329,14 @@ type RunnerFFMpegInnerProcCtxMap = HashMap<RunnerFFMType, RunnerFFMpegInnerProcC
 pub struct RunnerFFMpegInner {
     responder: RunnerFFMpegMsgSend,
 
-    receiver_type: Option<RunnerFFMType>,
-    recorder_type: Option<RunnerFFMType>,
+    // resolved from RunnerFFMStagePolicy
+    start_order: RunnerFFMTypeAry,
+    stop_order: RunnerFFMTypeAry,
 
     proc_ctx_map: RunnerFFMpegInnerProcCtxMap,
 
+    stage_stats: RunnerFFMStageStatsShared,
+
     // ffmpeg_proc: RunnerProcChild,
     // command: RunnerProcCmd,
     output: LinkedList<String>,
@@ -225,15 +348,19 @@ pub struct RunnerFFMpegInner {
     pub inner_msg_recv: RunnerFFMpegInnerMsgRecv,
 
     pub force_terminating: bool,
+
+    // critical stage is exited, remaining stages are being terminated
+    pub session_down: bool,
+
+    paused: Option<PauseTarget>,
 }
 
 pub fn create_runner_ffmpeg_inner(
     responder: RunnerFFMpegMsgSend,
-    receiver_type: Option<RunnerFFMType>,
-    recorder_type: Option<RunnerFFMType>,
     create_ctxs: RunnerFFMCreateCtxAry,
+    stage_stats: RunnerFFMStageStatsShared,
 ) -> Result<RunnerFFMpegInner, RunnerFFMError> {
-    imp::inner::create_runner_ffmpeg_inner(responder, receiver_type, recorder_type, create_ctxs)
+    imp::inner::create_runner_ffmpeg_inner(responder, create_ctxs, stage_stats)
 }
 
 #[derive(Debug, Clone)]
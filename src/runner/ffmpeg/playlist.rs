@@ -0,0 +1,426 @@
+// runner::ffmpeg::playlist
+// > live playlist served to players('{hls_out}/playlist.m3u8'), written only by HlsLivePlaylist
+// > receiver(ffmpeg hls muxer) writes playlist and segments of its own into '{hls_out}/ingest',
+//   new segments of it are appended to live playlist
+// > slate, backup segments are appended to live playlist as well, gap of pause is tagged with discontinuity
+// > media sequence and discontinuity sequence are counted as segments slide out (rfc 8216, 6.2.2)
+use super::error::RunnerFFMError;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+pub const PLAYLIST_FILE_NAME: &str = "playlist.m3u8";
+
+// '{hls_out}/ingest/playlist.m3u8', written by receiver
+pub const INGEST_DIR_NAME: &str = "ingest";
+
+const TAG_EXTM3U: &str = "#EXTM3U";
+const TAG_VERSION: &str = "#EXT-X-VERSION:";
+const TAG_TARGET_DURATION: &str = "#EXT-X-TARGETDURATION:";
+const TAG_MEDIA_SEQUENCE: &str = "#EXT-X-MEDIA-SEQUENCE:";
+const TAG_DISCONTINUITY_SEQUENCE: &str = "#EXT-X-DISCONTINUITY-SEQUENCE:";
+const TAG_INDEPENDENT_SEGMENTS: &str = "#EXT-X-INDEPENDENT-SEGMENTS";
+const TAG_PLAYLIST_TYPE: &str = "#EXT-X-PLAYLIST-TYPE";
+const TAG_EXTINF: &str = "#EXTINF:";
+const TAG_DISCONTINUITY: &str = "#EXT-X-DISCONTINUITY";
+const TAG_GAP: &str = "#EXT-X-GAP";
+const TAG_ENDLIST: &str = "#EXT-X-ENDLIST";
+
+const PLAYLIST_VERSION_DEFAULT: u64 = 3;
+
+//
+// segment
+//
+
+#[derive(Debug, Clone)]
+pub struct PlaylistSegment {
+    // relative to live playlist, ex) 'ingest/20240101-1704067200.ts', 'slate_3.ts'
+    pub uri: String,
+    pub duration: f64,
+    // timestamps restart at this segment
+    pub discontinuity: bool,
+    // player skips it, quarantined by ffmpeg::adts_inspect before it is appended
+    pub gap: bool,
+    // other tags of segment as written by receiver, ex) #EXT-X-PROGRAM-DATE-TIME
+    tags: Vec<String>,
+    // copied into hls out dir(slate, backup), removed once it slides out
+    owned: bool,
+}
+
+impl PlaylistSegment {
+    // copied by slate or failover
+    pub fn owned(uri: String, duration: f64, discontinuity: bool) -> Self {
+        PlaylistSegment {
+            uri,
+            duration,
+            discontinuity,
+            gap: false,
+            tags: Vec::new(),
+            owned: true,
+        }
+    }
+}
+
+//
+// live playlist
+//
+
+pub struct HlsLivePlaylist {
+    path: PathBuf,
+    origin_path: PathBuf,
+
+    // entries of live playlist, -hls_list_size of receiver
+    list_size: usize,
+
+    version: u64,
+    independent_segments: bool,
+    // not decreased, players expect it constant
+    target_duration: u64,
+
+    media_seq: u64,
+    discont_seq: u64,
+    segments: VecDeque<PlaylistSegment>,
+
+    // next appended segment is tagged, ex) gap of pause
+    discontinuity: bool,
+
+    // last segment of receiver playlist taken, none: nothing taken yet
+    origin_last: Option<String>,
+    // segments listed on receiver playlist when it was read last
+    origin_listed: Vec<String>,
+
+    ended: bool,
+}
+
+impl HlsLivePlaylist {
+    // hls_out_path: '{hls_root}/{app_name}'
+    pub fn new(hls_out_path: &str, list_size: i32) -> Self {
+        let dir = Path::new(hls_out_path);
+
+        let mut inst = HlsLivePlaylist {
+            path: dir.join(PLAYLIST_FILE_NAME),
+            origin_path: dir.join(INGEST_DIR_NAME).join(PLAYLIST_FILE_NAME),
+            list_size: list_size.max(1) as usize,
+            version: PLAYLIST_VERSION_DEFAULT,
+            independent_segments: false,
+            target_duration: 1,
+            media_seq: 0,
+            discont_seq: 0,
+            segments: VecDeque::new(),
+            discontinuity: false,
+            origin_last: None,
+            origin_listed: Vec::new(),
+            ended: false,
+        };
+
+        inst.continue_sequences();
+
+        // left by receiver of previous session, not taken
+        inst.origin_last = std::fs::read_to_string(&inst.origin_path)
+            .ok()
+            .and_then(|x| get_segments(&x).last().map(|s| s.to_string()));
+
+        inst
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new("."))
+    }
+
+    // has segment, and not ended
+    pub fn is_live(&self) -> bool {
+        !self.segments.is_empty() && !self.ended
+    }
+
+    // call at the beginning of gap(ex: pause), next appended segment is tagged
+    pub fn mark_discontinuity(&mut self) {
+        self.discontinuity = true;
+    }
+
+    // playlist left by previous writer(ex: session recovered), sequences go on from it
+    // > its segments are not listed again, they are counted as slid out
+    fn continue_sequences(&mut self) {
+        let playlist = match std::fs::read_to_string(&self.path) {
+            Ok(x) => x,
+            Err(_) => return,
+        };
+
+        let (header, segments) = parse_playlist(&playlist);
+
+        self.media_seq =
+            get_header_value(&header, TAG_MEDIA_SEQUENCE).unwrap_or(0) + segments.len() as u64;
+        self.discont_seq = get_header_value(&header, TAG_DISCONTINUITY_SEQUENCE).unwrap_or(0)
+            + segments.iter().filter(|x| x.discontinuity).count() as u64;
+        self.target_duration = get_header_value(&header, TAG_TARGET_DURATION).unwrap_or(1);
+
+        // timestamps of previous segments are not continued
+        self.discontinuity = !segments.is_empty();
+    }
+
+    // new segments of receiver playlist since last take, not listed yet
+    // > respawned receiver writes new playlist, timestamps restart at its first segment
+    pub fn take_origin_segments(&mut self) -> Vec<PlaylistSegment> {
+        let origin = match std::fs::read_to_string(&self.origin_path) {
+            Ok(x) => x,
+            // not written yet
+            Err(_) => return Vec::new(),
+        };
+
+        // being written, try next time
+        if !origin.ends_with('\n') {
+            return Vec::new();
+        }
+
+        let (header, segments) = parse_playlist(&origin);
+
+        if let Some(x) = get_header_value(&header, TAG_VERSION) {
+            self.version = self.version.max(x);
+        }
+
+        if header.iter().any(|x| x == TAG_INDEPENDENT_SEGMENTS) {
+            self.independent_segments = true;
+        }
+
+        self.origin_listed = segments
+            .iter()
+            .map(|x| format!("{}/{}", INGEST_DIR_NAME, x.uri))
+            .collect();
+
+        let (start, respawned) = match self.origin_last.as_ref() {
+            Some(last) => match segments.iter().position(|x| x.uri == *last) {
+                Some(i) => (i + 1, false),
+                None => (0, true),
+            },
+            None => (0, false),
+        };
+
+        let mut taken: Vec<PlaylistSegment> = segments
+            .into_iter()
+            .skip(start)
+            .filter(|x| x.duration > 0.0)
+            .collect();
+
+        if let Some(x) = taken.last() {
+            self.origin_last = Some(x.uri.clone());
+        }
+
+        for x in taken.iter_mut() {
+            x.uri = format!("{}/{}", INGEST_DIR_NAME, x.uri);
+        }
+
+        if respawned {
+            if let Some(x) = taken.first_mut() {
+                x.discontinuity = true;
+            }
+        }
+
+        taken
+    }
+
+    // segments are appended, oldest ones slide out over list_size
+    pub fn append(&mut self, segments: Vec<PlaylistSegment>) -> Result<(), RunnerFFMError> {
+        if segments.is_empty() {
+            return Ok(());
+        }
+
+        for mut x in segments {
+            if std::mem::take(&mut self.discontinuity) {
+                x.discontinuity = true;
+            }
+
+            // receiver is back after slate or backup, timestamps of it differ
+            if self
+                .segments
+                .back()
+                .map_or(false, |last| last.owned && !x.owned)
+            {
+                x.discontinuity = true;
+            }
+
+            self.target_duration = self.target_duration.max(x.duration.ceil() as u64);
+            self.segments.push_back(x);
+        }
+
+        let mut removed = Vec::new();
+
+        while self.segments.len() > self.list_size {
+            let x = self.segments.pop_front().unwrap();
+
+            self.media_seq += 1;
+
+            if x.discontinuity {
+                self.discont_seq += 1;
+            }
+
+            removed.push(x);
+        }
+
+        // live again, ex) slate after receiver exited
+        self.ended = false;
+
+        self.write()?;
+
+        // slid out, copied ones and ones left by exited receiver
+        // > listed ones are deleted by receiver(delete_segments)
+        for x in removed
+            .iter()
+            .filter(|x| x.owned || !self.origin_listed.contains(&x.uri))
+        {
+            let _ = std::fs::remove_file(self.dir().join(&x.uri));
+        }
+
+        Ok(())
+    }
+
+    // '#EXT-X-ENDLIST', players stop waiting for next segment
+    // > false: no segment is listed, or already ended
+    pub fn end(&mut self) -> Result<bool, RunnerFFMError> {
+        if !self.is_live() {
+            return Ok(false);
+        }
+
+        self.ended = true;
+
+        self.write().map(|_| true)
+    }
+
+    fn write(&self) -> Result<(), RunnerFFMError> {
+        let mut lines = vec![
+            TAG_EXTM3U.to_string(),
+            format!("{}{}", TAG_VERSION, self.version),
+            format!("{}{}", TAG_TARGET_DURATION, self.target_duration),
+            format!("{}{}", TAG_MEDIA_SEQUENCE, self.media_seq),
+        ];
+
+        if self.discont_seq > 0 {
+            lines.push(format!(
+                "{}{}",
+                TAG_DISCONTINUITY_SEQUENCE, self.discont_seq
+            ));
+        }
+
+        if self.independent_segments {
+            lines.push(TAG_INDEPENDENT_SEGMENTS.to_string());
+        }
+
+        for x in self.segments.iter() {
+            if x.discontinuity {
+                lines.push(TAG_DISCONTINUITY.to_string());
+            }
+
+            lines.extend(x.tags.iter().cloned());
+
+            if x.gap {
+                lines.push(TAG_GAP.to_string());
+            }
+
+            lines.push(format!("{}{:.6},", TAG_EXTINF, x.duration));
+            lines.push(x.uri.clone());
+        }
+
+        if self.ended {
+            lines.push(TAG_ENDLIST.to_string());
+        }
+
+        write_playlist(&self.path, &lines.join("\n"))
+    }
+}
+
+//
+// m3u8
+//
+
+// (playlist tags, segments) in playlist order, uri as listed
+fn parse_playlist(playlist: &str) -> (Vec<String>, Vec<PlaylistSegment>) {
+    let mut header: Vec<String> = Vec::new();
+    let mut segments: Vec<PlaylistSegment> = Vec::new();
+
+    // tags of next segment
+    let mut pending = PlaylistSegment {
+        uri: String::new(),
+        duration: 0.0,
+        discontinuity: false,
+        gap: false,
+        tags: Vec::new(),
+        owned: false,
+    };
+
+    for line in playlist.lines().map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        if is_playlist_tag(line) {
+            header.push(line.to_string());
+        } else if line == TAG_ENDLIST {
+            continue;
+        } else if line == TAG_DISCONTINUITY {
+            pending.discontinuity = true;
+        } else if line == TAG_GAP {
+            pending.gap = true;
+        } else if let Some(x) = line.strip_prefix(TAG_EXTINF) {
+            pending.duration = x
+                .split(',')
+                .next()
+                .and_then(|d| d.trim().parse().ok())
+                .unwrap_or(0.0);
+        } else if line.starts_with('#') {
+            pending.tags.push(line.to_string());
+        } else {
+            pending.uri = line.to_string();
+            segments.push(pending.clone());
+
+            pending.duration = 0.0;
+            pending.discontinuity = false;
+            pending.gap = false;
+            pending.tags.clear();
+        }
+    }
+
+    (header, segments)
+}
+
+// tags of whole playlist, others belong to the next segment
+fn is_playlist_tag(line: &str) -> bool {
+    [
+        TAG_EXTM3U,
+        TAG_VERSION,
+        TAG_TARGET_DURATION,
+        TAG_MEDIA_SEQUENCE,
+        TAG_DISCONTINUITY_SEQUENCE,
+        TAG_INDEPENDENT_SEGMENTS,
+        TAG_PLAYLIST_TYPE,
+    ]
+    .iter()
+    .any(|x| line.starts_with(x))
+}
+
+fn get_header_value(header: &[String], tag: &str) -> Option<u64> {
+    header
+        .iter()
+        .find_map(|x| x.strip_prefix(tag))
+        .and_then(|x| x.trim().parse().ok())
+}
+
+// write temporary file and rename, player never reads half written playlist
+fn write_playlist(playlist_path: &Path, content: &str) -> Result<(), RunnerFFMError> {
+    let tmp_path = playlist_path.with_extension("m3u8.tmp");
+
+    std::fs::write(&tmp_path, format!("{}\n", content))
+        .and_then(|_| std::fs::rename(&tmp_path, playlist_path))
+        .map_err(|e| {
+            RunnerFFMError::FileOperErr(format!(
+                "failed to write playlist, path={:?}, e={}",
+                playlist_path,
+                e.to_string()
+            ))
+        })
+}
+
+// segment uris in playlist order
+pub fn get_segments(playlist: &str) -> Vec<&str> {
+    playlist
+        .lines()
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .collect()
+}
@@ -3,10 +3,13 @@
 //
 use crate::comm_fs;
 use crate::comm_media::PropMedia;
+use crate::runner::ffmpeg::failover::BACKUP_SEGMENT_PREFIX;
 use crate::runner::ffmpeg::imp::prerole;
+use crate::runner::ffmpeg::playlist::get_segments;
+use crate::runner::ffmpeg::slate::SLATE_SEGMENT_PREFIX;
 use crate::runner::ffmpeg::{
     create_ffmpeg_log_file, FFMpegCmd, RstOnSpawnFFMpeg, RstPostSpawnFFMpeg, RunnerFFMCreateCtx,
-    RunnerFFMError, RunnerFFMType,
+    RunnerFFMError, RunnerFFMStagePolicy, RunnerFFMType,
 };
 use crate::runner::RunnerProcCmd;
 use std::result::Result;
@@ -30,6 +33,8 @@ pub fn new_ffm_cmd(
     ffmpeg_rw_timeout: i64,
     ffmpeg_vcodec: String,
     ffmpeg_acodec: String,
+    ffmpeg_audio_filter: Option<String>,
+    ffmpeg_audio_bitrate: Option<String>,
 
     ffmpeg_hls_init_time: String,
     ffmpeg_hls_time: String,
@@ -50,6 +55,8 @@ pub fn new_ffm_cmd(
         ffmpeg_rw_timeout,
         ffmpeg_vcodec,
         ffmpeg_acodec,
+        ffmpeg_audio_filter,
+        ffmpeg_audio_bitrate,
         ffmpeg_hls_init_time,
         ffmpeg_hls_time,
         ffmpeg_hls_list_size,
@@ -73,8 +80,8 @@ pub fn new_ffm_create_ctx(cmd: FFMpegCmd) -> RunnerFFMCreateCtx {
         cmd,
         on_spawn_ffmpeg,
         post_spawn_ffmpeg,
-        false,
-        true,
+        // receiver is respawned with whole session by RecvWorker
+        RunnerFFMStagePolicy::new(0, 0).critical(true),
     )
 }
 
@@ -91,6 +98,7 @@ pub fn new_ffm_create_ctx(cmd: FFMpegCmd) -> RunnerFFMCreateCtx {
 
 fn on_spawn_ffmpeg(ctx: &RunnerFFMCreateCtx) -> Result<RstOnSpawnFFMpeg, RunnerFFMError> {
     assert!(ctx.chk_prop_key("hls_out_path"));
+    assert!(ctx.chk_prop_key("hls_ingest_path"));
     assert!(ctx.chk_prop_key("hls_prerole_path"));
     assert!(ctx.chk_prop_key("log_file_path"));
 
@@ -101,12 +109,13 @@ fn on_spawn_ffmpeg(ctx: &RunnerFFMCreateCtx) -> Result<RstOnSpawnFFMpeg, RunnerF
 
     let mut spawner = RunnerProcCmd::new(ctx.ffmpeg_cmd.get_program().clone());
 
-    let hls_out_path = ctx.get_prop("hls_out_path");
+    // playlist and segments of receiver, listed on served playlist by RecvWorker
+    let hls_ingest_path = ctx.get_prop("hls_ingest_path");
 
-    if let Err(e) = comm_fs::create_dir_sync(&hls_out_path) {
+    if let Err(e) = comm_fs::create_dir_sync(&hls_ingest_path) {
         let _em = format!(
-            "[ffmpeg::receiver::rtmp] failed to create hls out root path={}, e={}",
-            hls_out_path,
+            "[ffmpeg::receiver::rtmp] failed to create hls ingest path={}, e={}",
+            hls_ingest_path,
             e.to_string()
         );
         return Err(RunnerFFMError::FFMpegSpawnErr(_em));
@@ -137,7 +146,7 @@ fn on_spawn_ffmpeg(ctx: &RunnerFFMCreateCtx) -> Result<RstOnSpawnFFMpeg, RunnerF
     // spawn ffmpeg rtmp receiver process
     //
 
-    spawner.current_dir(hls_out_path.clone());
+    spawner.current_dir(hls_ingest_path.clone());
     spawner.args(ctx.ffmpeg_cmd.get_ffmpeg_args().clone());
     //self.cmd.print_ffmpeg_args();
 
@@ -199,8 +208,33 @@ fn on_spawn_ffmpeg(ctx: &RunnerFFMCreateCtx) -> Result<RstOnSpawnFFMpeg, RunnerF
 }
 
 pub fn post_spawn_ffmpeg(ctx: &RunnerFFMCreateCtx) -> Result<RstPostSpawnFFMpeg, RunnerFFMError> {
+    // respawned while slate is on air, receiver appends to the live playlist
+    if ctx.get_prop("hls_slate") == "1"
+        && is_last_segment_of(ctx.get_prop("playlist_file_path"), SLATE_SEGMENT_PREFIX)
+    {
+        log::debug!(
+            "[ffmpeg::receiver::rtmp] prerole is skipped, slate is on air, playlist={}",
+            ctx.get_prop("playlist_file_path")
+        );
+
+        return Ok(());
+    }
+
+    // respawned while backup publisher is on air, same as slate
+    if ctx.get_prop("hls_failover") == "1"
+        && is_last_segment_of(ctx.get_prop("playlist_file_path"), BACKUP_SEGMENT_PREFIX)
+    {
+        log::debug!(
+            "[ffmpeg::receiver::rtmp] prerole is skipped, backup is on air, playlist={}",
+            ctx.get_prop("playlist_file_path")
+        );
+
+        return Ok(());
+    }
+
+    // prerole is listed on live playlist as first segments of receiver, ffmpeg::playlist
     let prerole_src_path = ctx.get_prop("hls_prerole_path");
-    let prerole_out_path = format!("{}", ctx.get_prop("hls_out_path"));
+    let prerole_out_path = format!("{}", ctx.get_prop("hls_ingest_path"));
 
     // copy prerole file
     let rst = prerole::generate_prerole_file_sync(
@@ -210,3 +244,11 @@ pub fn post_spawn_ffmpeg(ctx: &RunnerFFMCreateCtx) -> Result<RstPostSpawnFFMpeg,
 
     Ok(())
 }
+
+// last segment of playlist is written by other than receiver(slate, backup)
+fn is_last_segment_of(playlist_file_path: &str, prefix: &str) -> bool {
+    std::fs::read_to_string(playlist_file_path)
+        .ok()
+        .and_then(|x| get_segments(&x).last().map(|s| s.starts_with(prefix)))
+        .unwrap_or(false)
+}
@@ -4,7 +4,7 @@
 use crate::comm_media::MediaType;
 use crate::runner::ffmpeg::{
     create_ffmpeg_log_file, FFMpegCmd, RstOnSpawnFFMpeg, RstPostSpawnFFMpeg, RunnerFFMCreateCtx,
-    RunnerFFMError, RunnerFFMType,
+    RunnerFFMError, RunnerFFMStagePolicy, RunnerFFMType,
 };
 use crate::runner::RunnerProcCmd;
 use crate::{comm_fs, EchoPathBufToString, EchoStringToPathBuf};
@@ -55,8 +55,10 @@ pub fn new_ffm_create_ctx(cmd: FFMpegCmd) -> RunnerFFMCreateCtx {
         post_spawn_ffmpeg,
         // ex) if ffmpeg process which generate playlist.m3u8 periodic is exit,
         // ffmpeg playlist-m3u8 recorder will exit automataically without terminate explicitly
-        true,
-        true,
+        RunnerFFMStagePolicy::new(10, 10)
+            .depends_on(RunnerFFMType::RUNNER_FFM_RECEVIER("RTMP"))
+            .no_need_termination(true)
+            .auto_respawn(true),
     )
 }
 
@@ -0,0 +1,231 @@
+// runner::ffmpeg::slate
+// > while receiver waits for publisher(-listen 1), pre-encoded slate segments are appended to live playlist
+// > slate ends once publisher is connected again, segments of receiver held meanwhile are listed with discontinuity
+// > slate segments must be encoded as live segments are(codec, sample rate, resolution), only timestamps are discontinuous
+use super::error::RunnerFFMError;
+use super::media_monitor::get_segment_durations;
+use super::playlist::{HlsLivePlaylist, PlaylistSegment};
+use crate::comm::{EchoTimeDuration, EchoTimeInstant};
+use crate::comm_media::MediaType;
+use std::path::{Path, PathBuf};
+
+// slate is enabled if set, '{dir}/audio/playlist.m3u8', '{dir}/video/playlist.m3u8'
+pub const HLS_SLATE_DIR_ENV: &str = "ECHO_HLS_SLATE_DIR";
+
+// publisher may reconnect within, session is ended after, default: HLS_SLATE_GRACE_SEC_DEFAULT
+pub const HLS_SLATE_GRACE_SEC_ENV: &str = "ECHO_HLS_SLATE_GRACE_SEC";
+pub const HLS_SLATE_GRACE_SEC_DEFAULT: u64 = 60;
+
+// copied slate segments in hls out dir, '{prefix}{serial}.{ext}'
+pub const SLATE_SEGMENT_PREFIX: &str = "slate_";
+
+const SLATE_PLAYLIST_FILE_NAME: &str = "playlist.m3u8";
+
+pub fn is_enabled() -> bool {
+    std::env::var(HLS_SLATE_DIR_ENV).map_or(false, |x| !x.is_empty())
+}
+
+//
+// slate source
+//
+
+// pre-encoded slate, looped while on air
+struct SlateSource {
+    dir: PathBuf,
+    // (file name, #EXTINF)
+    segments: Vec<(String, f64)>,
+}
+
+impl SlateSource {
+    fn load(slate_dir: &str, media_type: MediaType) -> Result<Self, RunnerFFMError> {
+        let dir = Path::new(slate_dir).join(match media_type {
+            MediaType::Audio => "audio",
+            MediaType::Video => "video",
+        });
+
+        let playlist_path = dir.join(SLATE_PLAYLIST_FILE_NAME);
+
+        let playlist = std::fs::read_to_string(&playlist_path).map_err(|e| {
+            RunnerFFMError::InvalidConfigParameter(format!(
+                "failed to read slate playlist, path={:?}, e={}",
+                playlist_path, e
+            ))
+        })?;
+
+        let segments: Vec<(String, f64)> = get_segment_durations(&playlist)
+            .into_iter()
+            .filter(|(x, d)| *d > 0.0 && dir.join(x).is_file())
+            .collect();
+
+        if segments.is_empty() {
+            return Err(RunnerFFMError::InvalidConfigParameter(format!(
+                "no slate segment, path={:?}",
+                playlist_path
+            )));
+        }
+
+        Ok(SlateSource { dir, segments })
+    }
+}
+
+//
+// slate
+//
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlateTick {
+    // not on air
+    Idle,
+    OnAir,
+    // publisher did not reconnect within grace period
+    GraceExpired,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SlateSummary {
+    pub on_air_sec: u64,
+    pub segments: u32,
+}
+
+pub struct HlsSlate {
+    source: SlateSource,
+
+    grace: EchoTimeDuration,
+
+    // some: on air since
+    started_at: Option<EchoTimeInstant>,
+    next_append_at: EchoTimeInstant,
+    next_index: usize,
+    appended: u32,
+
+    // serial of copied slate file, not reused within session
+    serial: u64,
+}
+
+impl HlsSlate {
+    // none: slate is disabled, or slate source is not loadable
+    pub fn from_env(media_type: MediaType) -> Option<Self> {
+        let slate_dir = std::env::var(HLS_SLATE_DIR_ENV)
+            .ok()
+            .filter(|x| !x.is_empty())?;
+
+        let source = match SlateSource::load(&slate_dir, media_type) {
+            Ok(x) => x,
+            Err(e) => {
+                log::error!(
+                    "[HlsSlate::from_env] slate is disabled, e={}",
+                    e.to_string()
+                );
+                return None;
+            }
+        };
+
+        let grace_sec = std::env::var(HLS_SLATE_GRACE_SEC_ENV)
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(HLS_SLATE_GRACE_SEC_DEFAULT);
+
+        Some(HlsSlate {
+            source,
+            grace: EchoTimeDuration::from_secs(grace_sec),
+            started_at: None,
+            next_append_at: EchoTimeInstant::now(),
+            next_index: 0,
+            appended: 0,
+            serial: 0,
+        })
+    }
+
+    pub fn is_on_air(&self) -> bool {
+        self.started_at.is_some()
+    }
+
+    pub fn grace_sec(&self) -> u64 {
+        self.grace.as_secs()
+    }
+
+    // call when receiver exited, first slate segment is appended at once
+    // > false: nothing is live yet(no segment), slate is not started
+    pub fn start(&mut self, playlist: &mut HlsLivePlaylist) -> Result<bool, RunnerFFMError> {
+        if self.is_on_air() {
+            return Ok(true);
+        }
+
+        if !playlist.is_live() {
+            return Ok(false);
+        }
+
+        let now = EchoTimeInstant::now();
+
+        self.started_at = Some(now);
+        self.next_append_at = now;
+        self.next_index = 0;
+        self.appended = 0;
+
+        self.tick(playlist).map(|_| true)
+    }
+
+    // listed slate segments are removed by live playlist as they slide out
+    pub fn stop(&mut self) -> Option<SlateSummary> {
+        let started_at = self.started_at.take()?;
+
+        Some(SlateSummary {
+            on_air_sec: started_at.elapsed().as_secs(),
+            segments: self.appended,
+        })
+    }
+
+    // call periodically, next slate segment is appended when previous one is played out
+    pub fn tick(&mut self, playlist: &mut HlsLivePlaylist) -> Result<SlateTick, RunnerFFMError> {
+        let started_at = match self.started_at {
+            Some(x) => x,
+            None => return Ok(SlateTick::Idle),
+        };
+
+        if started_at.elapsed() >= self.grace {
+            return Ok(SlateTick::GraceExpired);
+        }
+
+        if EchoTimeInstant::now() < self.next_append_at {
+            return Ok(SlateTick::OnAir);
+        }
+
+        self.append(playlist)?;
+
+        Ok(SlateTick::OnAir)
+    }
+
+    fn append(&mut self, playlist: &mut HlsLivePlaylist) -> Result<(), RunnerFFMError> {
+        let (src, duration) = self.source.segments[self.next_index].clone();
+
+        let ext = Path::new(&src)
+            .extension()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or(String::from("ts"));
+
+        let file_name = format!("{}{}.{}", SLATE_SEGMENT_PREFIX, self.serial, ext);
+
+        let dest_path = playlist.dir().join(&file_name);
+
+        std::fs::copy(self.source.dir.join(&src), &dest_path).map_err(|e| {
+            RunnerFFMError::FileOperErr(format!(
+                "failed to copy slate segment, src={}, e={}",
+                src, e
+            ))
+        })?;
+
+        // timestamps restart at the first slate segment, and every loop of slate
+        playlist.append(vec![PlaylistSegment::owned(
+            file_name,
+            duration,
+            self.next_index == 0,
+        )])?;
+
+        self.next_append_at += EchoTimeDuration::from_secs_f64(duration);
+        self.next_index = (self.next_index + 1) % self.source.segments.len();
+        self.appended += 1;
+        self.serial += 1;
+
+        Ok(())
+    }
+}
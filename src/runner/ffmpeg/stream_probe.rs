@@ -0,0 +1,404 @@
+// runner::ffmpeg::stream_probe
+// > one-shot probe of published stream once first hls segment is written, checked against declared PropMedia
+// > input dump of receiver log is preferred(stream as sent), ffprobe of segment if not dumped(ex: -v quiet)
+use super::error::RunnerFFMError;
+use super::failover::BACKUP_SEGMENT_PREFIX;
+use super::slate::SLATE_SEGMENT_PREFIX;
+use crate::comm_media::{MediaFormat, MediaType, PropMedia};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+// default: 'ffprobe' next to echo_ffmpeg_path
+pub const FFPROBE_PATH_ENV: &str = "ECHO_FFPROBE_PATH";
+
+// "event"(default): mismatch is a session event, "terminate": session is ended
+pub const STREAM_MISMATCH_POLICY_ENV: &str = "ECHO_STREAM_MISMATCH_POLICY";
+
+pub const FFPROBE_TIMEOUT_MS: u64 = 5000;
+
+// input dump is printed at head of receiver log
+const INPUT_DUMP_HEAD_BYTES: usize = 16384;
+
+//
+// probe result
+//
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VideoStreamInfo {
+    pub codec: String,
+    pub profile: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub fps: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AudioStreamInfo {
+    pub codec: String,
+    pub profile: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StreamProbe {
+    // "input": input dump of receiver log, "segment": ffprobe of segment
+    pub source: String,
+    // segment of which written is waited for
+    pub segment: String,
+    pub format_name: String,
+    pub video: Option<VideoStreamInfo>,
+    pub audio: Option<AudioStreamInfo>,
+}
+
+// 'ffprobe -print_format json -show_streams -show_format'
+#[derive(Deserialize)]
+struct FFProbeOut {
+    #[serde(default)]
+    streams: Vec<FFProbeStream>,
+    format: Option<FFProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct FFProbeStream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    profile: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    // "30/1"
+    avg_frame_rate: Option<String>,
+    // "44100"
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct FFProbeFormat {
+    format_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamMismatchPolicy {
+    Event,
+    Terminate,
+}
+
+impl StreamMismatchPolicy {
+    pub fn from_env() -> Self {
+        match std::env::var(STREAM_MISMATCH_POLICY_ENV).as_deref() {
+            Ok("terminate") => Self::Terminate,
+            _ => Self::Event,
+        }
+    }
+}
+
+// by FFPROBE_PATH_ENV, or ffmpeg_path of which file name is replaced
+pub fn get_ffprobe_path(ffmpeg_path: &str) -> String {
+    if let Ok(x) = std::env::var(FFPROBE_PATH_ENV) {
+        if !x.is_empty() {
+            return x;
+        }
+    }
+
+    match Path::new(ffmpeg_path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => {
+            dir.join("ffprobe").to_string_lossy().to_string()
+        }
+        _ => String::from("ffprobe"),
+    }
+}
+
+// first segment of playlist written since epoch
+// > slate and backup segments are not written by this publisher, skipped
+// > none: playlist does not exist, or no segment yet
+pub async fn first_segment_since(playlist_path: &str, since_epoch: i64) -> Option<PathBuf> {
+    let playlist = tokio::fs::read_to_string(playlist_path).await.ok()?;
+    let playlist_dir = Path::new(playlist_path).parent()?;
+
+    for segment in playlist
+        .lines()
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+    {
+        let segment_path = playlist_dir.join(segment);
+
+        let file_name = segment_path
+            .file_name()
+            .map_or(String::new(), |x| x.to_string_lossy().to_string());
+
+        if file_name.starts_with(SLATE_SEGMENT_PREFIX)
+            || file_name.starts_with(BACKUP_SEGMENT_PREFIX)
+        {
+            continue;
+        }
+
+        let mtime_epoch = match tokio::fs::metadata(&segment_path)
+            .await
+            .and_then(|x| x.modified())
+        {
+            Ok(x) => x
+                .duration_since(UNIX_EPOCH)
+                .map(|x| x.as_secs() as i64)
+                .unwrap_or(0),
+            // deleted by hls_list_size
+            Err(_) => continue,
+        };
+
+        if mtime_epoch >= since_epoch {
+            return Some(segment_path);
+        }
+    }
+
+    None
+}
+
+// input dump of receiver log, or ffprobe of segment
+pub async fn probe_stream(
+    ffprobe_path: &str,
+    receiver_log_path: &str,
+    segment_path: &Path,
+) -> Result<StreamProbe, RunnerFFMError> {
+    if let Some(mut probe) = parse_input_dump(receiver_log_path).await {
+        probe.segment = segment_path.to_string_lossy().to_string();
+        return Ok(probe);
+    }
+
+    probe_segment(ffprobe_path, segment_path).await
+}
+
+pub async fn probe_segment(
+    ffprobe_path: &str,
+    segment_path: &Path,
+) -> Result<StreamProbe, RunnerFFMError> {
+    let output = tokio::process::Command::new(ffprobe_path)
+        .args([
+            "-v",
+            "error",
+            "-print_format",
+            "json",
+            "-show_streams",
+            "-show_format",
+        ])
+        .arg(segment_path)
+        .kill_on_drop(true)
+        .output();
+
+    let output = tokio::time::timeout(Duration::from_millis(FFPROBE_TIMEOUT_MS), output)
+        .await
+        .map_err(|_| {
+            RunnerFFMError::OperErr(format!("ffprobe is timeoutted, path={:?}", segment_path))
+        })?
+        .map_err(|e| {
+            RunnerFFMError::OperErr(format!(
+                "failed to run ffprobe, ffprobe_path={}, e={}",
+                ffprobe_path, e
+            ))
+        })?;
+
+    if !output.status.success() {
+        return Err(RunnerFFMError::OperErr(format!(
+            "ffprobe is exited with error, path={:?}, status={}, stderr={}",
+            segment_path,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let out: FFProbeOut = serde_json::from_slice(&output.stdout)
+        .map_err(|e| RunnerFFMError::OperErr(format!("invalid ffprobe output, e={}", e)))?;
+
+    let mut probe = StreamProbe {
+        source: String::from("segment"),
+        segment: segment_path.to_string_lossy().to_string(),
+        format_name: out.format.and_then(|x| x.format_name).unwrap_or_default(),
+        video: None,
+        audio: None,
+    };
+
+    // first stream of each type
+    for x in out.streams {
+        match x.codec_type.as_deref() {
+            Some("video") if probe.video.is_none() => {
+                probe.video = Some(VideoStreamInfo {
+                    codec: x.codec_name.unwrap_or_default(),
+                    profile: x.profile,
+                    width: x.width.unwrap_or(0),
+                    height: x.height.unwrap_or(0),
+                    fps: x.avg_frame_rate.as_deref().and_then(parse_frame_rate),
+                })
+            }
+            Some("audio") if probe.audio.is_none() => {
+                probe.audio = Some(AudioStreamInfo {
+                    codec: x.codec_name.unwrap_or_default(),
+                    profile: x.profile,
+                    sample_rate: x.sample_rate.and_then(|x| x.parse().ok()),
+                    channels: x.channels,
+                })
+            }
+            _ => {}
+        }
+    }
+
+    Ok(probe)
+}
+
+//
+// input dump
+//
+
+// "Input #0, flv, from 'rtmp://0.0.0.0:30000/live01/..':"
+//   "Stream #0:0: Video: h264 (High), yuv420p(progressive), 1280x720 [SAR 1:1 DAR 16:9], 30 fps, 30 tbr, 1k tbn"
+//   "Stream #0:1: Audio: aac (LC), 44100 Hz, stereo, fltp, 128 kb/s"
+// > none: not dumped(ex: -v quiet), or not received yet
+pub async fn parse_input_dump(receiver_log_path: &str) -> Option<StreamProbe> {
+    let buf = tokio::fs::read(receiver_log_path).await.ok()?;
+    let head = String::from_utf8_lossy(&buf[..buf.len().min(INPUT_DUMP_HEAD_BYTES)]).to_string();
+
+    let mut lines = head
+        .split(|c| c == '\r' || c == '\n')
+        .skip_while(|x| !x.starts_with("Input #0"));
+
+    let format_name = lines
+        .next()?
+        .split(", ")
+        .nth(1)
+        .unwrap_or_default()
+        .to_string();
+
+    let mut probe = StreamProbe {
+        source: String::from("input"),
+        format_name,
+        ..Default::default()
+    };
+
+    // until "Output #0" or "Stream mapping:"
+    for line in lines.take_while(|x| x.starts_with(' ')) {
+        if let Some((_, desc)) = line.split_once(": Video: ") {
+            if probe.video.is_none() {
+                probe.video = Some(parse_video_desc(desc));
+            }
+        } else if let Some((_, desc)) = line.split_once(": Audio: ") {
+            if probe.audio.is_none() {
+                probe.audio = Some(parse_audio_desc(desc));
+            }
+        }
+    }
+
+    if probe.video.is_none() && probe.audio.is_none() {
+        return None;
+    }
+
+    Some(probe)
+}
+
+// "h264 (High)" -> ("h264", Some("High"))
+fn parse_codec_desc(desc: &str) -> (String, Option<String>) {
+    let mut it = desc.splitn(2, ' ');
+    let codec = it.next().unwrap_or_default().to_string();
+
+    let profile = it
+        .next()
+        .and_then(|x| x.strip_prefix('('))
+        .and_then(|x| x.split(')').next())
+        .map(|x| x.to_string());
+
+    (codec, profile)
+}
+
+fn parse_video_desc(desc: &str) -> VideoStreamInfo {
+    let mut parts = desc.split(", ");
+    let (codec, profile) = parse_codec_desc(parts.next().unwrap_or_default());
+
+    let mut info = VideoStreamInfo {
+        codec,
+        profile,
+        ..Default::default()
+    };
+
+    for part in parts {
+        let first = part.split_whitespace().next().unwrap_or_default();
+
+        if let Some((w, h)) = first.split_once('x') {
+            if let (Ok(w), Ok(h)) = (w.parse(), h.parse()) {
+                info.width = w;
+                info.height = h;
+            }
+        } else if let Some(fps) = part.strip_suffix(" fps") {
+            info.fps = fps.trim().parse().ok();
+        }
+    }
+
+    info
+}
+
+fn parse_audio_desc(desc: &str) -> AudioStreamInfo {
+    let mut parts = desc.split(", ");
+    let (codec, profile) = parse_codec_desc(parts.next().unwrap_or_default());
+
+    let mut info = AudioStreamInfo {
+        codec,
+        profile,
+        ..Default::default()
+    };
+
+    for part in parts {
+        if let Some(rate) = part.strip_suffix(" Hz") {
+            info.sample_rate = rate.trim().parse().ok();
+        } else if info.channels.is_none() {
+            info.channels = match part {
+                "mono" => Some(1),
+                "stereo" => Some(2),
+                "5.1" | "5.1(side)" => Some(6),
+                "7.1" => Some(8),
+                x => x.strip_suffix(" channels").and_then(|x| x.parse().ok()),
+            };
+        }
+    }
+
+    info
+}
+
+// "30000/1001" -> 29.97, "0/0": none
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let (num, den): (f64, f64) = (num.parse().ok()?, den.parse().ok()?);
+
+    if den == 0.0 {
+        return None;
+    }
+
+    Some(num / den)
+}
+
+impl StreamProbe {
+    // differences from declared media of publish request, empty: as declared
+    pub fn check_declared(&self, media: &PropMedia) -> Vec<String> {
+        let mut mismatches = Vec::new();
+
+        match media.media_type {
+            MediaType::Audio if self.video.is_some() => mismatches.push(format!(
+                "audio is declared, but video arrived, codec={}",
+                self.video.as_ref().unwrap().codec
+            )),
+            MediaType::Video if self.video.is_none() => {
+                mismatches.push(format!("video is declared, but no video stream arrived"))
+            }
+            _ => {}
+        }
+
+        match (media.format, self.audio.as_ref()) {
+            (MediaFormat::AAC, Some(audio)) if audio.codec != "aac" => mismatches.push(format!(
+                "aac is declared, but audio codec is {}",
+                audio.codec
+            )),
+            (_, None) if media.media_type == MediaType::Audio => {
+                mismatches.push(format!("audio is declared, but no audio stream arrived"))
+            }
+            _ => {}
+        }
+
+        mismatches
+    }
+}
@@ -20,6 +20,7 @@ use {
     // std::sync::Mutex/RwLock : no await
     std::{
         collections::{ HashMap, VecDeque },
+        path::PathBuf,
         sync::{Arc},
         time::{Duration, Instant},
     },
@@ -36,30 +37,84 @@ use {
 use crate::ECHO_ASYNC_SLEEP_MS;
 
 use super::RecvWorkerType;
+use super::driver::DriverRegistry;
+use super::admission::{
+    self,
+    AdmissionPolicy,
+    AdmissionRejection,
+    AdmissionSession,
+    CapacityReport,
+    DiskUsage,
+};
+use super::store::{self, SessionRecord};
+use super::cluster::{self, ClusterRegistry, NodeState, SessionClaim};
+use super::webhook::{self, WebhookEvent};
+use super::publish_auth::PublishOverrides;
+use super::event_bus;
+use super::ffmpeg::capability;
+use super::ffmpeg::loudnorm::{self, LoudnormPrm, LOUDNORM_ACODEC};
+use super::ffmpeg::recorder::RecFileStatus;
+use crate::comm_media::{MediaReceiver, MediaType};
+use crate::EchoPathBufToString;
 
 use super::{
     RecvWorkerManager,
+    RecvWorkerManagerHandover,
+    RecvWorkerManagerHandoverSlot,
     RecvWorkerHandle,
     RecvWorkerManagerStatus,    
+    RecvWorkerStateSnapshot,
+    SessionInfo,
+    SessionList,
+    SessionQueryFilter,
+    ShutdownMode,
+    ShutdownPrm,
+    MANAGER_IDLE_TICK_MS,
+    WORKER_EXIT_TIMEOUT_SEC,
+    POLL_PERIOD_TERMINATE_MS,
+    QUERY_WORKER_STATE_TIMEOUT_MS,
+    SHUTDOWN_DRAIN_DEADLINE_SEC,
+    SHUTDOWN_TERMINATE_DEADLINE_SEC,
+    SHUTDOWN_FINALIZE_DEADLINE_SEC,
+    SKIPPED_SESSION_CHECK_INTERVAL_SEC,
+    SESSION_LIST_DEFAULT_LIMIT,
+    SESSION_LIST_MAX_LIMIT,
 };
 
 impl RecvWorkerManager {
 
-    fn peek_message(&mut self) 
+    // Empty: no message within MANAGER_IDLE_TICK_MS
+    async fn recv_message(&mut self) 
         -> Result<RecvWorkerManagerMsg, mpsc::error::TryRecvError> {
-        self.msg_received.try_recv()
+
+        let idle_tick = Duration::from_millis(MANAGER_IDLE_TICK_MS);
+
+        match tokio::time::timeout(idle_tick, self.msg_received.recv()).await {
+            Ok(Some(msg)) => Ok(msg),
+            Ok(None) => Err(mpsc::error::TryRecvError::Disconnected),
+            Err(_) => Err(mpsc::error::TryRecvError::Empty),
+        }
     }
 
     async fn handle_msg_spawn_recv_worker(&mut self,
         responder:SpawnRecvWorkerMsgOCResponder,
-        req_publish:ReqPublishV3) -> Result<(), RunnerError> {
+        mut req_publish:ReqPublishV3,
+        overrides: PublishOverrides) -> Result<(), RunnerError> {
 
         log::debug!("[RecvWorkerManager] creating receiver worker, {:?}", req_publish);
 
         if self.status != RecvWorkerManagerStatus::Running {
-            return Err(RunnerError::InvalidManagerStatus(
+            // draining on shutdown, answer rejection without exiting message loop of manager
+            let _ = responder.send(Err(RunnerError::InvalidManagerStatus(
                 json!({ "error" : format!("RecvWorker Manager not ready.., status={:?}", 
-                    self.status) })));
+                    self.status) }))));
+
+            return Ok(());
+        }
+
+        // transcoding profile decided by on_publish, counted on admission control as is
+        if let Some(profile) = overrides.profile.clone() {
+            req_publish.receiver_prm.args = profile;
         }
 
         //
@@ -87,13 +142,15 @@ impl RecvWorkerManager {
                     prev_publish_info = Some((
                         prev_worker_handle.publish_ip.clone(),
                         prev_worker_handle.publish_port,
+                        prev_worker_handle.backup_publish_port,
                         prev_worker_handle.uuid.clone()));
             }
 
             if let Some(resp) = prev_publish_info {                
-                responder
-                    .send(Ok(resp))
-                    .unwrap();
+                if let Err(_) = responder.send(Ok(resp)) {
+                    log::warn!("[RecvWorkerManager] can't answer re-publish, \
+                        requester is gone, app_name={}", chk_app_name);
+                }
 
                 // send terminate signal to ffmpeg
 
@@ -106,138 +163,1270 @@ impl RecvWorkerManager {
                 let _em = format!("previos worker founded, but worker handle\
                  info is invalid, app_name={}", chk_app_name);
 
-                return Err(RunnerError::RecvWorkerHandleErr(_em));
+                let _ = responder.send(Err(RunnerError::RecvWorkerHandleErr(_em)));
+
+                return Ok(());
             }
         }
 
+        //
+        // ffmpeg capability, missing codec or feature is refused before port is leased
+        //
+
+        if let Err(e) = check_capability(&req_publish, &overrides) {
+            log::warn!("[RecvWorkerManager] publish is refused by capability check, \
+                app_name={}, e={}", chk_app_name, e);
+
+            let _ = responder.send(Err(RunnerError::UnsupportedCapability(e)));
+
+            return Ok(());
+        }
+
+        // backup receiver is run by worker as ffmpeg
+        if overrides.backup && req_publish.receiver != MediaReceiver::FFMPEG {
+            let e = format!("backup publisher is not supported by receiver, receiver={:?}", 
+                req_publish.receiver);
+
+            log::warn!("[RecvWorkerManager] publish is refused by backup override, \
+                app_name={}, e={}", chk_app_name, e);
+
+            let _ = responder.send(Err(RunnerError::UnsupportedCapability(e)));
+
+            return Ok(());
+        }
+
+        //
+        // admission control, re-publish of running session is not counted
+        //
+
+        // backup publisher takes second port of same session
+        let required_ports = if overrides.backup { 2 } else { 1 };
+
+        if let Err(rejection) = self.check_admission(&req_publish, required_ports).await {
+            log::warn!("[RecvWorkerManager] publish is rejected by admission control, \
+                app_name={}, e={}", chk_app_name, rejection);
+
+            let _ = responder.send(Err(RunnerError::AdmissionRejected(rejection)));
+
+            return Ok(());
+        }
+
         ////////////////////////////////////////////////////////////////////////////
 
         let publish_ip
             = self.get_publish_ip().to_string();
 
-        let publish_port 
-            = self.pick_publish_port()
-                .await
-                .map_err(|e| {
-                    let _em = format!("can't pick publish port,\
-                     app_name={}\
-                     f=handle_msg_spawn_recv_worker,
-                     e={}", 
-                     chk_app_name,
-                     e.to_string());
+        let publish_port = match self.pick_publish_port().await {
+            Ok(port) => port,
+            Err(e) => {
+                let _em = format!("can't pick publish port, \
+                    app_name={}, f=handle_msg_spawn_recv_worker, e={}", 
+                    chk_app_name, e.to_string());
+
+                log::error!("[RecvWorkerManager] {}", _em);
+
+                // manager keeps running, only this publish fails
+                let _ = responder.send(Err(RunnerError::RunnerOperErr(_em)));
+
+                return Ok(());
+            }
+        };
+
+        // second port of same session, for backup publisher
+        let backup_publish_port = match overrides.backup {
+            true => match self.pick_publish_port().await {
+                Ok(port) => Some(port),
+                Err(e) => {
+                    self.avail_publish_ports.write().await.push_back(publish_port);
+
+                    let _em = format!("can't pick backup publish port, \
+                        app_name={}, f=handle_msg_spawn_recv_worker, e={}", 
+                        chk_app_name, e.to_string());
 
-                    RunnerError::RunnerOperErr(_em)
-                })?;
+                    log::error!("[RecvWorkerManager] {}", _em);
 
+                    let _ = responder.send(Err(RunnerError::RunnerOperErr(_em)));
+
+                    return Ok(());
+                }
+            },
+            false => None,
+        };
+
+        let app_name = req_publish.app_name.clone();
         let worker_uuid = EchoUUID_new().to_string();
+        let expire_duration = overrides.max_duration_sec
+            .map(Duration::from_secs)
+            .unwrap_or(self.config.echo_publish_max_duration);
 
-        match self.spawn_worker(
-            self.config.clone(),
+        let response = match self.start_worker(
             worker_uuid.clone(),
             req_publish,
-            publish_ip.as_str(), 
-            publish_port).await {
+            publish_ip.as_str(),
+            publish_port,
+            backup_publish_port,
+            expire_duration,
+            overrides).await {
 
-            Ok((handle, mut worker)) => {
-                let rst = worker.begin().await;
-                
-                let response = match rst {
-                    Ok(r) => {
-                        let worker_joinhandle 
-                            = tokio::spawn(worker.run());
+            Ok(_) => Ok((publish_ip, publish_port, backup_publish_port, worker_uuid)),
+            Err(e) => Err(e),
+        };
+
+        if let Err(_) = responder.send(response) {
+            log::warn!("[RecvWorkerManager] can't answer spawn of receiver worker, \
+                requester is gone, app_name={}", app_name);
+        }
+
+        Ok(())    
+    }
+
+
+    // spawn, begin and run worker, then persist session
+    // > publish ports are given back to pool on failure
+    async fn start_worker(&mut self,
+        worker_uuid: String,
+        req_publish: ReqPublishV3,
+        publish_ip: &str,
+        publish_port: u16,
+        backup_publish_port: Option<u16>,
+        expire_duration: EchoTimeDuration,
+        overrides: PublishOverrides) -> Result<Arc<EchoAsyncRwLock<RecvWorkerHandle>>, RunnerError> {
+
+        let app_name = req_publish.app_name.clone();
+
+        if let Err(e) = self.claim_session(&app_name).await {
+            self.give_back_publish_ports(publish_port, backup_publish_port).await;
+            return Err(e);
+        }
+
+        // global config is replaced for this session
+        let mut config = self.config.clone();
+
+        if let Some(rec_enabled) = overrides.rec_enabled {
+            config.echo_rec_enabled = rec_enabled;
+        }
+
+        let (handle, mut worker) = match self.spawn_worker(
+            config,
+            worker_uuid,
+            req_publish,
+            publish_ip,
+            publish_port,
+            backup_publish_port,
+            expire_duration,
+            overrides).await {
+
+            Ok(x) => x,
+            Err(e) => {
+                self.give_back_publish_ports(publish_port, backup_publish_port).await;
+                self.release_session(&app_name).await;
+                return Err(e);
+            }
+        };
+
+        if let Err(e) = worker.begin().await {
+            // not a session, do not count it on admission control
+            self.worker_handles.remove(&app_name);
+            self.give_back_publish_ports(publish_port, backup_publish_port).await;
+            self.release_session(&app_name).await;
+
+            return Err(RunnerError::FailedToCreateRecvWorker(
+                format!("{}", e.to_string())));
+        }
+
+        let worker_joinhandle 
+            = tokio::spawn(worker.run());
+
+        let rec = {
+            // rwlock.write
+            let mut _handle = handle.write().await;
+
+            _handle.worker_joinhandle = Some(worker_joinhandle);
+            _handle.to_session_record()
+        };
+
+        self.persist_session(rec);
+
+        Ok(handle)
+    }
+
+
+    // find worker handle by selector
+    // > TerminateRecvWorkerPrmType::WorkerUUID, 'key' is worker_uuid, otherwise app_name
+    async fn find_worker_handle(&self,
+        prm_type: TerminateRecvWorkerPrmType,
+        key: &str,
+        sess_key: &str) 
+        -> Result<Arc<EchoAsyncRwLock<RecvWorkerHandle>>, RunnerError> {
+
+        match prm_type {
+            TerminateRecvWorkerPrmType::AppName => {
+                self.worker_handles
+                    .get(key)
+                    .cloned()
+                    .ok_or(RunnerError::RecvWorkerNotFound(
+                        format!("app_name={}", key)))
+            },
+
+            TerminateRecvWorkerPrmType::AppNameSessKey => {
+                let handle 
+                    = self.worker_handles
+                        .get(key)
+                        .ok_or(RunnerError::RecvWorkerNotFound(
+                            format!("app_name={}, sess_key={}", key, sess_key)))?;
+
+                // app_name may be reused by newer session, do not touch it
+                if handle.read().await.sess_key != sess_key {
+                    return Err(RunnerError::RecvWorkerNotFound(
+                        format!("sess_key is mismatched, app_name={}, sess_key={}", 
+                            key, sess_key)));
+                }
+
+                Ok(handle.clone())
+            },
+
+            TerminateRecvWorkerPrmType::WorkerUUID => {
+                for (_, handle) in self.worker_handles.iter() {
+                    if handle.read().await.worker_uuid == key {
+                        return Ok(handle.clone());
+                    }
+                }
+
+                Err(RunnerError::RecvWorkerNotFound(
+                    format!("worker_uuid={}", key)))
+            },
+        }
+    }
+
+
+    async fn handle_msg_terminate_recv_worker(&mut self, 
+        responder: TerminateRecvWorkerMsgOCResponder,
+        prm_type:TerminateRecvWorkerPrmType,
+        app_name:String,
+        sess_key:String) 
+        -> Result<(), RunnerError> {
+        
+        let handle 
+            = match self.find_worker_handle(prm_type, &app_name, &sess_key).await {
+                Ok(h) => h,
+                Err(e) => {
+                    let _ = responder.send(Err(e));
+                    return Ok(());
+                }
+            };
+
+        let (worker_uuid, worker_app_name, worker_sess_key, worker_msg_send) = {
+            // rwlock.read
+            let _handle = handle.read().await;
+
+            (_handle.worker_uuid.clone(),
+             _handle.app_name.clone(),
+             _handle.sess_key.clone(),
+             _handle.worker_msg_send.clone())
+        };
+
+        // awaiting exit of worker in separated task, do not block message loop of manager
+        // > publish port is restored on NotifyRecvWorkerIsExiting
+        tokio::spawn(async move {
+            let resp = finish_recv_worker_and_wait(worker_msg_send)
+                .await
+                .map(|_| json!({
+                    "stopped": [ {
+                        "worker_uuid" : worker_uuid,
+                        "app_name"    : worker_app_name,
+                        "sess_key"    : worker_sess_key,
+                    } ],
+                }));
+
+            if let Err(_) = responder.send(resp) {
+                log::error!("[RecvWorkerManager] failed to send response, \
+                    msg=RecvWorkerManagerMsg::TerminateRecvWorker");
+            }
+        });
+
+        Ok(())
+    }
+
+
+    // forward an operation(expiry, pause, ..) to the worker selected by prm_type,
+    // answer of worker is relayed to responder
+    async fn forward_worker_oper<F>(&mut self,
+        responder: EchoOCResponder<Result<PrmJson, RunnerError>>,
+        prm_type: TerminateRecvWorkerPrmType,
+        app_name: String,
+        sess_key: String,
+        msg_name: &'static str,
+        new_msg: F) -> Result<(), RunnerError> 
+        where F: FnOnce(EchoOCResponder<Result<PrmJson, RunnerError>>) -> RecvWorkerMsg {
+
+        let handle 
+            = match self.find_worker_handle(prm_type, &app_name, &sess_key).await {
+                Ok(h) => h,
+                Err(e) => {
+                    let _ = responder.send(Err(e));
+                    return Ok(());
+                }
+            };
+
+        let worker_msg_send 
+            = handle.read().await.worker_msg_send.clone();
+
+        let (oper_responder,
+             oper_recv)
+            = tokio::sync::oneshot::channel();
+
+        if let Err(e) = worker_msg_send.send(new_msg(oper_responder)) {
+            let _ = responder.send(Err(RunnerError::RunnerMsgChanErr(
+                "send_fail", 
+                msg_name,
+                "forward_worker_oper",
+                e.to_string())));
+
+            return Ok(());
+        }
+
+        // awaiting answer in separated task, do not block message loop of manager
+        tokio::spawn(async move {
+            let resp = match oper_recv.await {
+                Ok(r) => r,
+                Err(e) => Err(RunnerError::RunnerMsgChanErr(
+                    "recv_rst_fail", 
+                    msg_name,
+                    "forward_worker_oper",
+                    e.to_string())),
+            };
+
+            if let Err(_) = responder.send(resp) {
+                log::error!("[RecvWorkerManager] failed to send response, msg={}", msg_name);
+            }
+        });
+
+        Ok(())
+    }
+
+
+    async fn handle_msg_terminate_recv_workers(&mut self,
+        responder: TerminateRecvWorkersMsgOCResponder,
+        filter: SessionQueryFilter) -> Result<(), RunnerError> {
+
+        // empty filter matches all sessions, terminating all must be explicit
+        if !filter.has_selector() && !filter.all {
+            log::warn!("[RecvWorkerManager] bulk termination is refused without selector, \
+                filter={:?}", filter);
+
+            let _ = responder.send(Err(RunnerError::RecvWorkManagerOperErr(
+                "no selector is given, set state, media_type, app_name_prefix, \
+                min_uptime_sec or all=true".to_string())));
+
+            return Ok(());
+        }
+
+        let app_name_prefix 
+            = filter.app_name_prefix.clone().unwrap_or_default();
 
-                        handle.write().await.worker_joinhandle = Some(worker_joinhandle);
-                        Ok((publish_ip, publish_port, worker_uuid))
-                    },
+        let worker_msg_sends 
+            = self.collect_worker_msg_sends(|app_name| 
+                app_name.starts_with(app_name_prefix.as_str())).await;
 
+        let publish_ip = self.get_publish_ip().to_string();
+
+        // awaiting answers of workers in separated task, do not block message loop of manager
+        tokio::spawn(async move {
+            let senders: HashMap<String, RecvWorkerMsgSend> 
+                = worker_msg_sends.iter().cloned().collect();
+
+            let snapshots = query_recv_worker_states(worker_msg_sends).await;
+
+            let mut stopped = Vec::new();
+            let mut failed = Vec::new();
+
+            for info in filter_sessions(snapshots, &publish_ip, &filter) {
+                let worker = json!({
+                    "worker_uuid" : info.state.worker_uuid,
+                    "app_name"    : info.state.app_name,
+                    "sess_key"    : info.state.sess_key,
+                });
+
+                let worker_msg_send 
+                    = senders.get(&info.state.worker_uuid).unwrap().clone();
+
+                match finish_recv_worker_and_wait(worker_msg_send).await {
+                    Ok(_) => stopped.push(worker),
                     Err(e) => {
-                        Err(RunnerError::FailedToCreateRecvWorker(
-                            format!("{}", e.to_string())))
+                        log::error!("[RecvWorkerManager] failed to terminate worker, \
+                            worker_uuid={}, e={}", info.state.worker_uuid, e.to_string());
+
+                        failed.push(json!({
+                            "worker" : worker,
+                            "error"  : e.to_string(),
+                        }));
+                    }
+                }
+            }
+
+            let resp = json!({
+                "stopped" : stopped,
+                "failed"  : failed,
+            });
+
+            if let Err(_) = responder.send(Ok(resp)) {
+                log::error!("[RecvWorkerManager] failed to send response, \
+                    msg=RecvWorkerManagerMsg::TerminateRecvWorkers");
+            }
+        });
+
+        Ok(())
+    }
+
+
+    async fn handle_msg_query_recv_worker_states(&mut self,
+        responder: QueryRecvWorkerStatesMsgOCResponder) -> Result<(), RunnerError> {
+
+        let worker_msg_sends 
+            = self.collect_worker_msg_sends(|_| true).await;
+
+        // awaiting answers in separated task, do not block message loop of manager
+        tokio::spawn(async move {
+            let snapshots = query_recv_worker_states(worker_msg_sends).await;
+
+            if let Err(_) = responder.send(Ok(snapshots)) {
+                log::error!("[RecvWorkerManager] failed to send response, \
+                    msg=RecvWorkerManagerMsg::QueryRecvWorkerStates");
+            }
+        });
+
+        Ok(())
+    }
+
+
+    async fn handle_msg_query_sessions(&mut self,
+        responder: QuerySessionsMsgOCResponder,
+        filter: SessionQueryFilter) -> Result<(), RunnerError> {
+
+        let app_name_prefix 
+            = filter.app_name_prefix.clone().unwrap_or_default();
+
+        let worker_msg_sends 
+            = self.collect_worker_msg_sends(|app_name| 
+                app_name.starts_with(app_name_prefix.as_str())).await;
+
+        let publish_ip = self.get_publish_ip().to_string();
+
+        tokio::spawn(async move {
+            let snapshots = query_recv_worker_states(worker_msg_sends).await;
+
+            let session_list = build_session_list(snapshots, &publish_ip, &filter);
+
+            if let Err(_) = responder.send(Ok(session_list)) {
+                log::error!("[RecvWorkerManager] failed to send response, \
+                    msg=RecvWorkerManagerMsg::QuerySessions");
+            }
+        });
+
+        Ok(())
+    }
+
+
+    async fn handle_msg_query_session(&mut self,
+        responder: QuerySessionMsgOCResponder,
+        app_name: String) -> Result<(), RunnerError> {
+
+        let worker_msg_sends 
+            = self.collect_worker_msg_sends(|x| x == app_name.as_str()).await;
+
+        if worker_msg_sends.is_empty() {
+            let _ = responder.send(Err(RunnerError::RecvWorkerNotFound(
+                format!("app_name={}", app_name))));
+
+            return Ok(());
+        }
+
+        let publish_ip = self.get_publish_ip().to_string();
+
+        tokio::spawn(async move {
+            let snapshot 
+                = query_recv_worker_states(worker_msg_sends).await.pop();
+
+            let resp = match snapshot {
+                Some(s) => Ok(new_session_info(s, &publish_ip)),
+                // worker is exiting, or timeout
+                None => Err(RunnerError::RecvWorkerNotFound(
+                    format!("worker did not answer, app_name={}", app_name))),
+            };
+
+            if let Err(_) = responder.send(resp) {
+                log::error!("[RecvWorkerManager] failed to send response, \
+                    msg=RecvWorkerManagerMsg::QuerySession");
+            }
+        });
+
+        Ok(())
+    }
+
+
+    // exited session is not counted anymore, and publish port is reusable
+    // > handle of app_name may be owned by newer worker
+    // > overrides of removed session, none: handle is not owned by worker
+    async fn remove_worker_handle(&mut self, worker_uuid: &str, app_name: &str) -> Option<PublishOverrides> {
+        let (publish_port, backup_publish_port, overrides) = match self.worker_handles.get(app_name) {
+            Some(handle) => {
+                // rwlock.read
+                let _handle = handle.read().await;
+
+                if _handle.worker_uuid != worker_uuid {
+                    return None;
+                }
+
+                (_handle.publish_port, _handle.backup_publish_port, _handle.overrides.clone())
+            },
+            None => return None,
+        };
+
+        self.worker_handles.remove(app_name);
+        self.give_back_publish_ports(publish_port, backup_publish_port).await;
+
+        self.unpersist_session(app_name);
+        self.release_session(app_name).await;
+
+        log::debug!("[RecvWorkerManager] removed worker handle, worker_uuid={}, app_name={}, \
+            publish_port={}", worker_uuid, app_name, publish_port);
+
+        Some(overrides)
+    }
+
+    // ports listened by process of skipped session, not leased until it is resolved
+    async fn take_publish_ports(&self, rec: &SessionRecord) {
+        let mut ports = self.avail_publish_ports.write().await;
+
+        ports.retain(|x| *x != rec.publish_port && Some(*x) != rec.backup_publish_port);
+    }
+
+    async fn give_back_publish_ports(&self, publish_port: u16, backup_publish_port: Option<u16>) {
+        let mut ports = self.avail_publish_ports.write().await;
+
+        ports.push_back(publish_port);
+        ports.extend(backup_publish_port);
+    }
+
+
+    //
+    // recordings
+    //
+
+    fn get_rec_out_path(&self, app_name: &str) -> String {
+        format!("{}/{}", EchoPathBufToString!(self.config.echo_rec_root_dir.clone()), app_name)
+    }
+
+    // recordings of ended session, _NEW -> _FIN, renamed file names are returned
+    // > normalized session: loudness is measured and tagged before each file is finalized,
+    //   finalized in background(whole recording is decoded), nothing is returned,
+    //   names are reported by WebhookEvent::RecordingFinished once renamed
+    async fn finalize_recordings(&mut self, worker_uuid: &str, app_name: &str,
+        loudnorm: Option<LoudnormPrm>) -> Vec<String> {
+
+        let rec_out_path = self.get_rec_out_path(app_name);
+
+        if let Some(prm) = loudnorm.filter(|x| x.tags_recording()) {
+            let new_files = store::list_record_files(&rec_out_path, RecFileStatus::NEW).await;
+
+            if new_files.is_empty() {
+                return Vec::new();
+            }
+
+            let task = tokio::spawn(finalize_normalized_recordings(
+                self.config.echo_ffmpeg_path.clone(),
+                rec_out_path,
+                new_files,
+                prm,
+                app_name.to_string(),
+                worker_uuid.to_string()));
+
+            self.finalize_tasks.retain(|(_, x)| !x.is_finished());
+            self.finalize_tasks.push((app_name.to_string(), task));
+
+            return Vec::new();
+        }
+
+        let finalized = store::finalize_record_files(&rec_out_path).await;
+
+        if !finalized.is_empty() {
+            log::info!("[RecvWorkerManager] recordings are finalized, app_name={}, files={:?}",
+                app_name, finalized);
+
+            webhook::notify(WebhookEvent::RecordingFinished, app_name, worker_uuid, json!({
+                "rec_out_path" : rec_out_path,
+                "files" : finalized,
+            }));
+        }
+
+        finalized
+    }
+
+    // archiver reports recording is stored permanently, _FIN -> _ARC
+    async fn handle_msg_notify_recording_archived(&mut self,
+        responder: NotifyRecordingArchivedMsgOCResponder,
+        app_name: String,
+        file_name: String) -> Result<(), RunnerError> {
+
+        let rec_out_path = self.get_rec_out_path(&app_name);
+
+        let rst = store::archive_record_file(&rec_out_path, &file_name).await;
+
+        if let Ok(arc_file_name) = rst.as_ref() {
+            log::info!("[RecvWorkerManager] recording is archived, app_name={}, file={}",
+                app_name, arc_file_name);
+
+            webhook::notify(WebhookEvent::RecordingArchived, &app_name, "", json!({
+                "rec_out_path" : rec_out_path,
+                "file" : arc_file_name,
+            }));
+        }
+
+        if let Err(_) = responder.send(rst.map(|x| json!({ "file" : x }))) {
+            log::error!("[RecvWorkerManager] failed to send response, \
+                msg=RecvWorkerManagerMsg::NotifyRecordingArchived");
+        }
+
+        Ok(())
+    }
+
+
+    //
+    // session store
+    // > failure of store is logged only, session keeps running without durability
+    //
+
+    // queued, written off the manager loop
+    fn persist_session(&self, rec: SessionRecord) {
+        if let Some(store) = self.session_store.as_ref() {
+            store.put(rec);
+        }
+    }
+
+    fn unpersist_session(&self, app_name: &str) {
+        if let Some(store) = self.session_store.as_ref() {
+            store.remove(app_name);
+        }
+    }
+
+    async fn handle_msg_notify_expiry_changed(&mut self,
+        worker_uuid: String,
+        app_name: String,
+        expire_epoch: i64) {
+
+        let rec = match self.worker_handles.get(&app_name) {
+            Some(handle) => {
+                // rwlock.write
+                let mut _handle = handle.write().await;
+
+                if _handle.worker_uuid != worker_uuid {
+                    return;
+                }
+
+                _handle.expire_epoch = expire_epoch;
+                _handle.to_session_record()
+            },
+            None => return,
+        };
+
+        self.persist_session(rec);
+    }
+
+
+    //
+    // cluster
+    //
+
+    // ownership of app_name, fails while redis is unreachable
+    // > same app_name must not be published on two nodes
+    async fn claim_session(&self, app_name: &str) -> Result<(), RunnerError> {
+        let cluster = match self.cluster.as_ref() {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+
+        match cluster.claim_session(app_name).await? {
+            SessionClaim::Claimed => Ok(()),
+            SessionClaim::OwnedBy(node_id) => {
+                log::warn!("[RecvWorkerManager::claim_session] session is owned by other node, \
+                    app_name={}, node_id={}", app_name, node_id);
+
+                Err(RunnerError::SessionOwnedByOtherNode(format!(
+                    "app_name={}, node_id={}", app_name, node_id)))
+            }
+        }
+    }
+
+    // claim expires by itself without heartbeat, failure is logged only
+    async fn release_session(&self, app_name: &str) {
+        let cluster = match self.cluster.as_ref() {
+            Some(x) => x,
+            None => return,
+        };
+
+        if let Err(e) = cluster.release_session(app_name).await {
+            log::error!("[RecvWorkerManager::release_session] failed to release session, \
+                app_name={}, e={}", app_name, e.to_string());
+        }
+    }
+
+    async fn handle_msg_query_node_state(&mut self,
+        responder: QueryNodeStateMsgOCResponder) -> Result<(), RunnerError> {
+
+        let state = NodeState {
+            capacity: self.build_capacity_report().await,
+        };
+
+        if let Err(_) = responder.send(Ok(state)) {
+            log::error!("[RecvWorkerManager] failed to send response, \
+                msg=RecvWorkerManagerMsg::QueryNodeState");
+        }
+
+        Ok(())
+    }
+
+    async fn handle_msg_query_cluster(&mut self,
+        responder: QueryClusterMsgOCResponder) -> Result<(), RunnerError> {
+
+        let cluster = match self.cluster.clone() {
+            Some(x) => x,
+            None => {
+                let _ = responder.send(Err(RunnerError::ClusterErr(
+                    format!("cluster mode is disabled, {} is not set", cluster::CLUSTER_REDIS_ENV))));
+
+                return Ok(());
+            }
+        };
+
+        // redis is queried off the manager loop
+        tokio::spawn(async move {
+            let rst = cluster.list_nodes().await.map(|nodes| json!({
+                "node_id" : cluster.node_id(),
+                "nodes" : nodes,
+            }));
+
+            if let Err(_) = responder.send(rst) {
+                log::error!("[RecvWorkerManager] failed to send response, \
+                    msg=RecvWorkerManagerMsg::QueryCluster");
+            }
+        });
+
+        Ok(())
+    }
+
+
+    // sessions of previous run(crash, kill) are respawned on same port while unexpired,
+    // others are finalized(recordings, playlist) and unpublished to vsvr
+    async fn reconcile_sessions(&mut self) {
+        let store = match self.session_store.as_ref() {
+            Some(x) => x.store().clone(),
+            None => return,
+        };
+
+        let records = match store.load().await {
+            Ok(x) => x,
+            Err(e) => {
+                log::error!("[RecvWorkerManager::reconcile_sessions] failed to load sessions, \
+                    store={}, e={}", store.name(), e.to_string());
+                return;
+            }
+        };
+
+        if records.is_empty() {
+            return;
+        }
+
+        log::info!("[RecvWorkerManager::reconcile_sessions] reconciling sessions, store={}, count={}",
+            store.name(), records.len());
+
+        let mut recovered = Vec::new();
+        let mut abandoned = Vec::new();
+        let mut skipped = Vec::new();
+
+        for rec in records {
+            let cur_epoch = EchoUtc::now().timestamp();
+
+            if rec.expire_epoch <= cur_epoch {
+                abandoned.push(rec.app_name.clone());
+                self.abandon_session(rec, "expired").await;
+                continue;
+            }
+
+            // listened by other process, may be receiver of previous manager(supervisor restart)
+            // > record is kept and rechecked, ports are not leased to new session meanwhile
+            if is_session_port_listening(&rec) {
+                log::warn!("[RecvWorkerManager::reconcile_sessions] publish port is in use, \
+                    session is not recovered yet, app_name={}, publish_port={}",
+                    rec.app_name, rec.publish_port);
+
+                self.take_publish_ports(&rec).await;
+
+                skipped.push(rec.app_name.clone());
+                self.skipped_sessions.push(rec);
+                continue;
+            }
+
+            let remain = Duration::from_secs((rec.expire_epoch - cur_epoch) as u64);
+
+            match self.recover_session(&rec, remain).await {
+                Ok(_) => recovered.push(rec.app_name.clone()),
+                Err(e) => {
+                    log::error!("[RecvWorkerManager::reconcile_sessions] failed to recover session, \
+                        app_name={}, e={}", rec.app_name, e.to_string());
+
+                    abandoned.push(rec.app_name.clone());
+                    self.abandon_session(rec, "recovery_failed").await;
+                }
+            }
+        }
+
+        log::info!("[RecvWorkerManager::reconcile_sessions] reconciled sessions, \
+            recovered={:?}, abandoned={:?}, skipped={:?}", recovered, abandoned, skipped);
+    }
+
+    // skipped records of reconciliation, recovered once port is released, abandoned once expired
+    // > record of app_name published again meanwhile is dropped, newer session owns the record
+    async fn check_skipped_sessions(&mut self) {
+        // not recovered while shutting down, records are left for next run
+        if (self.skipped_sessions.is_empty() && self.releasing_ports.is_empty())
+            || self.status != RecvWorkerManagerStatus::Running {
+            return;
+        }
+
+        if self.skipped_sessions_checked_at.elapsed()
+            < Duration::from_secs(SKIPPED_SESSION_CHECK_INTERVAL_SEC) {
+            return;
+        }
+
+        self.skipped_sessions_checked_at = EchoTimeInstant::now();
+
+        for port in std::mem::take(&mut self.releasing_ports) {
+            self.release_port_when_closed(port).await;
+        }
+
+        for rec in std::mem::take(&mut self.skipped_sessions) {
+            let cur_epoch = EchoUtc::now().timestamp();
+
+            if self.worker_handles.contains_key(&rec.app_name) {
+                log::info!("[RecvWorkerManager::check_skipped_sessions] session is published again, \
+                    record is dropped, app_name={}", rec.app_name);
+
+                self.release_session_ports_when_closed(&rec).await;
+                continue;
+            }
+
+            if rec.expire_epoch <= cur_epoch {
+                self.release_session_ports_when_closed(&rec).await;
+                self.abandon_session(rec, "expired").await;
+                continue;
+            }
+
+            if is_session_port_listening(&rec) {
+                self.skipped_sessions.push(rec);
+                continue;
+            }
+
+            // taken again by recover_session
+            self.give_back_publish_ports(rec.publish_port, rec.backup_publish_port).await;
+
+            let remain = Duration::from_secs((rec.expire_epoch - cur_epoch) as u64);
+
+            match self.recover_session(&rec, remain).await {
+                Ok(_) => log::info!("[RecvWorkerManager::check_skipped_sessions] session is recovered, \
+                    app_name={}", rec.app_name),
+                Err(e) => {
+                    log::error!("[RecvWorkerManager::check_skipped_sessions] failed to recover session, \
+                        app_name={}, e={}", rec.app_name, e.to_string());
+
+                    self.abandon_session(rec, "recovery_failed").await;
+                }
+            }
+        }
+    }
+
+    // ports of dropped record, not given back while other process listens on them
+    async fn release_session_ports_when_closed(&mut self, rec: &SessionRecord) {
+        for port in std::iter::once(rec.publish_port).chain(rec.backup_publish_port) {
+            self.release_port_when_closed(port).await;
+        }
+    }
+
+    async fn release_port_when_closed(&mut self, port: EchoPublishPort) {
+        if is_port_listening(port) {
+            log::debug!("[RecvWorkerManager::check_skipped_sessions] port is still listened, \
+                kept out of pool, port={}", port);
+
+            self.releasing_ports.push(port);
+            return;
+        }
+
+        self.avail_publish_ports.write().await.push_back(port);
+    }
+
+    // receiver is respawned on same port with same worker_uuid, publisher reconnects to it
+    async fn recover_session(&mut self, rec: &SessionRecord, remain: EchoTimeDuration)
+        -> Result<(), RunnerError> {
+
+        // ffmpeg may be replaced since the session is started
+        check_capability(&rec.req_publish, &rec.overrides)
+            .map_err(RunnerError::UnsupportedCapability)?;
+
+        // ports must be in range of config, and not taken
+        {
+            let mut ports = self.avail_publish_ports.write().await;
+
+            for port in std::iter::once(rec.publish_port).chain(rec.backup_publish_port) {
+                match ports.iter().position(|x| *x == port) {
+                    Some(idx) => { ports.remove(idx); },
+                    None => {
+                        // leased one of this session is given back
+                        if port != rec.publish_port {
+                            ports.push_back(rec.publish_port);
+                        }
+
+                        return Err(RunnerError::RunnerOperErr(format!(
+                            "publish port is not available, publish_port={}", port)));
                     }
-                };
+                }
+            }
+        }
+
+        let publish_ip = self.get_publish_ip().to_string();
+
+        let handle = self.start_worker(
+            rec.worker_uuid.clone(),
+            rec.req_publish.clone(),
+            publish_ip.as_str(),
+            rec.publish_port,
+            rec.backup_publish_port,
+            remain,
+            rec.overrides.clone()).await?;
+
+        // start of session is kept
+        let rec = {
+            // rwlock.write
+            let mut _handle = handle.write().await;
+
+            _handle.start_epoch = rec.start_epoch;
+            _handle.to_session_record()
+        };
+
+        self.persist_session(rec.clone());
+
+        // claimed again by heartbeat, terminated if other node took it meanwhile
+        if let Some(cluster) = self.cluster.as_ref() {
+            cluster.track_session(&rec.app_name);
+        }
+
+        event_bus::session_event(
+            "info",
+            "session_recovered",
+            &rec.app_name,
+            &rec.worker_uuid,
+            json!({
+                "publish_port" : rec.publish_port,
+                "start_epoch" : rec.start_epoch,
+                "expire_epoch" : rec.expire_epoch,
+            }),
+        );
+
+        Ok(())
+    }
+
+    async fn abandon_session(&mut self, rec: SessionRecord, reason: &str) {
+        let finalized = self.finalize_recordings(&rec.worker_uuid, &rec.app_name,
+            rec.overrides.loudnorm.clone()).await;
+
+        let playlist_path = format!("{}/{}/playlist.m3u8",
+            EchoPathBufToString!(self.config.echo_hls_root_dir.clone()), rec.app_name);
+
+        let playlist_ended = store::end_hls_playlist(&playlist_path).await;
+
+        if self.config.vsvr_api_use_pub_unpub {
+            if let Err(e) = util::update_vsvr_publish_state(
+                self.vsvr_serv_msg_send.clone(),
+                rec.app_name.clone(),
+                rec.sess_key.clone(),
+                crate::service::vsvr::message::VSvrLiveState::UnPublish)
+            .await {
+                log::error!("f=RecvWorkerManager::abandon_session, e={}", e.to_string());
+            }
+        }
+
+        self.unpersist_session(&rec.app_name);
+        self.release_session(&rec.app_name).await;
+
+        event_bus::session_event(
+            "warn",
+            "session_unrecovered",
+            &rec.app_name,
+            &rec.worker_uuid,
+            json!({
+                "reason" : reason,
+                "start_epoch" : rec.start_epoch,
+                "expire_epoch" : rec.expire_epoch,
+                "finalized_files" : finalized,
+                "playlist_ended" : playlist_ended,
+            }),
+        );
+    }
+
+
+    // (app_name, media_type, profile) of running sessions
+    async fn collect_session_attrs(&self) -> Vec<(String, MediaType, String)> {
+        let mut attrs = Vec::new();
+
+        for (app_name, handle) in self.worker_handles.iter() {
+            // rwlock.read
+            let _handle = handle.read().await;
+
+            attrs.push((app_name.clone(), _handle.media_type, _handle.profile.clone()));
+        }
+
+        attrs
+    }
+
+
+    // (load_per_cpu, disks) of host
+    // > df is run on blocking thread, manager loop is not blocked by slow filesystem
+    async fn measure_resources(&self) -> (Option<f64>, Vec<DiskUsage>) {
+        let mut paths: Vec<String> 
+            = vec![self.config.echo_hls_root_dir.to_string_lossy().to_string()];
+
+        if self.config.echo_rec_enabled {
+            paths.push(self.config.echo_rec_root_dir.to_string_lossy().to_string());
+        }
+
+        let measured = tokio::task::spawn_blocking(move || {
+            let disks: Vec<DiskUsage> = paths
+                .into_iter()
+                .map(|path: String| {
+                    let free_mb = admission::measure_free_disk_mb(&path);
+                    DiskUsage { path, free_mb }
+                })
+                .collect();
+
+            (admission::measure_load_per_cpu(), disks)
+        }).await;
+
+        match measured {
+            Ok(x) => x,
+            Err(e) => {
+                log::error!("[RecvWorkerManager::measure_resources] failed to measure, e={}", e);
+                (None, Vec::new())
+            }
+        }
+    }
+
+
+    async fn check_admission(&self, req_publish: &ReqPublishV3, required_ports: usize) 
+        -> Result<(), AdmissionRejection> {
+
+        let attrs = self.collect_session_attrs().await;
+
+        admission::check_session_limits(
+            &self.admission_policy,
+            &to_admission_sessions(&attrs),
+            &AdmissionSession {
+                app_name: req_publish.app_name.as_str(),
+                media_type: req_publish.media.media_type,
+                profile: req_publish.receiver_prm.args.as_str(),
+            })?;
+
+        let (load_per_cpu, disks) = self.measure_resources().await;
+
+        admission::check_resource_limits(&self.admission_policy, load_per_cpu, &disks)?;
+
+        if self.avail_publish_ports.read().await.len() < required_ports {
+            return Err(AdmissionRejection::NoPublishPort);
+        }
+
+        Ok(())
+    }
+
+
+    async fn handle_msg_query_capacity(&mut self,
+        responder: QueryCapacityMsgOCResponder) -> Result<(), RunnerError> {
+
+        let report = self.build_capacity_report().await;
+
+        if let Err(_) = responder.send(Ok(report)) {
+            log::error!("[RecvWorkerManager] failed to send response, \
+                msg=RecvWorkerManagerMsg::QueryCapacity");
+        }
+
+        Ok(())
+    }
+
+    async fn build_capacity_report(&self) -> CapacityReport {
+        let policy = &self.admission_policy;
+
+        let attrs = self.collect_session_attrs().await;
+        let sessions = to_admission_sessions(&attrs);
+
+        let (load_per_cpu, disks) = self.measure_resources().await;
+
+        let avail_publish_ports = self.avail_publish_ports.read().await.len();
+
+        let mut leased_publish_ports = 0;
+
+        for (_, handle) in self.worker_handles.iter() {
+            // rwlock.read
+            let _handle = handle.read().await;
+
+            leased_publish_ports += 1 + _handle.backup_publish_port.iter().count();
+        }
+
+        let remain_sessions = match policy.max_sessions {
+            Some(limit) => avail_publish_ports.min(limit.saturating_sub(sessions.len())),
+            None => avail_publish_ports,
+        };
+
+        // rejection for a publish, not considering per media type/profile/prefix limits
+        let rejection = admission::check_resource_limits(policy, load_per_cpu, &disks)
+            .err()
+            .or_else(|| match policy.max_sessions {
+                Some(limit) if sessions.len() >= limit => 
+                    Some(AdmissionRejection::MaxSessions { limit }),
+                _ => None,
+            })
+            .or_else(|| match avail_publish_ports {
+                0 => Some(AdmissionRejection::NoPublishPort),
+                _ => None,
+            });
+
+        let media_type_limits: HashMap<String, usize> = policy
+            .max_sessions_per_media_type
+            .iter()
+            .map(|(k, v)| (k.to_string().to_lowercase(), *v))
+            .collect();
+
+        CapacityReport {
+            accepting: self.status == RecvWorkerManagerStatus::Running && rejection.is_none(),
+            rejection,
+
+            sessions: sessions.len(),
+            max_sessions: policy.max_sessions,
+            remain_sessions,
+            avail_publish_ports,
+            leased_publish_ports,
+
+            per_media_type: admission::collect_usages(&media_type_limits, &sessions, 
+                |key, x| x.media_type.to_string().to_lowercase() == key),
+            per_profile: admission::collect_usages(&policy.max_sessions_per_profile, &sessions, 
+                |key, x| x.profile == key),
+            per_prefix: admission::collect_usages(&policy.max_sessions_per_prefix, &sessions, 
+                |key, x| x.app_name.starts_with(key)),
+
+            load_per_cpu,
+            max_load_per_cpu: policy.max_load_per_cpu,
+
+            disks,
+            min_free_disk_mb: policy.min_free_disk_mb,
+        }
+    }
+
+
+    async fn handle_msg_shutdown_recv_workers(&mut self,
+        responder: ShutdownRecvWorkersMsgOCResponder,
+        shutdown_prm: ShutdownPrm) -> Result<(), RunnerError> {
+
+        log::info!("[RecvWorkerManager] shutdown is requested, mode={}, deadline_sec={}, status={:?}",
+            shutdown_prm.mode, shutdown_prm.deadline_sec, self.status);
+
+        match self.status {
+            RecvWorkerManagerStatus::Draining => {
+                // shutdown task is already running, answered together on completion
+                if shutdown_prm.mode == ShutdownMode::Terminate {
+                    self.shutdown_terminate_notify.notify_one();
+                }
+
+                self.shutdown_responders.push(responder);
+                return Ok(());
+            },
 
-                responder
-                    .send(response)
-                    .unwrap();                
+            RecvWorkerManagerStatus::Destroying => {
+                let _ = responder.send(Err(RunnerError::InvalidManagerStatus(
+                    json!({ "error" : "RecvWorker Manager is already shutdown" }))));
+
+                return Ok(());
             },
 
-            Err(e) =>{
-                responder
-                    .send(Err(e))
-                    .unwrap();
-            }
+            _ => {}
         }
 
-        Ok(())    
-    }
+        // reject new publish from now
+        self.status = RecvWorkerManagerStatus::Draining;
+        self.shutdown_responders.push(responder);
+
+        // join handles are moved to shutdown task
+        let mut workers = Vec::new();
+
+        for (_, handle) in self.worker_handles.iter() {
+            // rwlock.write
+            let mut _handle = handle.write().await;
+
+            let joinhandle = match _handle.worker_joinhandle.take() {
+                Some(h) if !h.is_finished() => h,
+                // failed to begin, or already exited
+                _ => continue,
+            };
+
+            workers.push(ShutdownRecvWorker {
+                worker_uuid: _handle.worker_uuid.clone(),
+                app_name: _handle.app_name.clone(),
+                sess_key: _handle.sess_key.clone(),
+                worker_msg_send: _handle.worker_msg_send.clone(),
+                joinhandle,
+            });
+        }
 
+        let terminate_notify = self.shutdown_terminate_notify.clone();
+        let manager_msg_send = self.msg_send.clone();
 
-    async fn handle_msg_terminate_recv_worker(&mut self, 
-        prm_type:TerminateRecvWorkerPrmType,
-        app_name:String,
-        sess_key:String) 
-        -> Result<PrmJson, RunnerError> {
-        
-         // find worker handle by app_name
-         let handle 
-            = self.worker_handles.get(&app_name);
+        // joining workers in separated task, manager keeps handling NotifyRecvWorkerIsExiting
+        tokio::spawn(async move {
+            let report 
+                = shutdown_recv_workers(workers, shutdown_prm, terminate_notify).await;
 
-        if handle.is_none() {
-            return Err(RunnerError::RunnerOperErr(
-                format!("inner-error-route, f={},e={}",
-                    "handle_msg_terminate_recv_worker", 
-                    "can't find worker handle by given appname")));
-        }
+            if let Err(e) = manager_msg_send
+                .send(RecvWorkerManagerMsg::NotifyShutdownCompleted(report)) {
+                log::error!("[RecvWorkerManager] failed to send msg, \
+                    msg=RecvWorkerManagerMsg::NotifyShutdownCompleted, e={}", e.to_string());
+            }
+        });
 
-        // send finish message to receive worker
-        let mut worker_msg_send: Option::<RecvWorkerMsgSend> = None;
+        Ok(())
+    }
 
-        { // rwlock.write
-            worker_msg_send 
-                = Some(handle.unwrap()
-                    .write()
-                    .await
-                    .worker_msg_send.clone());
-        }
-        
-        // let _handle2 
-        //     = &handle.unwrap().as_ref().write().unwrap();
 
-        let (fin_responder,
-             fin_recv)
-            = tokio::sync::oneshot::channel();
+    // (worker_uuid, worker_msg_send) of workers, selected by app_name
+    async fn collect_worker_msg_sends<F>(&self, select_app_name: F) 
+        -> Vec<(String, RecvWorkerMsgSend)> 
+        where F: Fn(&str) -> bool {
 
-        worker_msg_send
-            .unwrap()
-            .send(RecvWorkerMsg::FinishRecvWorker(fin_responder, json!({})))
-            .map_err(|e|
-                RunnerError::RunnerMsgChanErr(
-                    "send_fail", 
-                    "RecvWorkerMsg::FinishRecvWorker",
-                    "handle_msg_terminate_recv_worker",
-                    e.to_string()))?;
+        let mut worker_msg_sends = Vec::new();
 
-        let fin_rst 
-            = fin_recv
-                .await
-                .map_err(|e|
-                    RunnerError::RunnerMsgChanErr(
-                        "recv_rst_fail", 
-                        "RecvWorkerMsg::FinishRecvWorker",
-                        "handle_msg_terminate_recv_worker",
-                        e.to_string())
-                )?;
+        for (app_name, handle) in self.worker_handles.iter() {
+            if !select_app_name(app_name.as_str()) {
+                continue;
+            }
 
-        // restore publish port
+            // rwlock.read
+            let _handle = handle.read().await;
 
-        Ok(fin_rst.unwrap())
+            worker_msg_sends.push((
+                _handle.worker_uuid.clone(),
+                _handle.worker_msg_send.clone()));
+        }
 
+        worker_msg_sends
     }
 
 
     async fn proc_msg(&mut self) -> Result<(), RunnerError> {
 
-        match self.peek_message() {
+        match self.recv_message().await {
 
             Ok(worker_manager_msg) => match worker_manager_msg {
 
@@ -248,6 +1437,10 @@ impl RecvWorkerManager {
                     sess_key ) => {
                     log::debug!("[RecvWorkerManager] got msg 'NotifyWorkerIsExiting', worker_uuid={:?}", worker_uuid);
 
+                    // recording may be enabled for this session only(on_publish override)
+                    if let Some(overrides) = self.remove_worker_handle(&worker_uuid, &app_name).await {
+                        self.finalize_recordings(&worker_uuid, &app_name, overrides.loudnorm).await;
+                    }
 
                     if self.config.vsvr_api_use_pub_unpub {
 
@@ -255,17 +1448,15 @@ impl RecvWorkerManager {
                         app_name={}, sess_key={}", app_name, sess_key);
         
                         // don't wait response
-                        util::update_vsvr_publish_state(
+                        // > failure of vsvr must not stop manager, other sessions are still draining
+                        if let Err(e) = util::update_vsvr_publish_state(
                             self.vsvr_serv_msg_send.clone(),
                             app_name,
                             sess_key,
                             crate::service::vsvr::message::VSvrLiveState::UnPublish)
-                        .await
-                        .map_err(|e| {
-                            let em=format!("f=RecvWorkerManager::proc_msg, e={}", e.to_string());
-                            log::error!("{}", em);
-                            RunnerError::RunnerOperErr(em)                            
-                        })?;
+                        .await {
+                            log::error!("f=RecvWorkerManager::proc_msg, e={}", e.to_string());
+                        }
 
                     } else {
                         log::debug!("[RecvWorkerManager::proc_msg] skipped changing vsvr \
@@ -275,12 +1466,22 @@ impl RecvWorkerManager {
                     }
                 },
 
+                /////////////////////////////////////////////////
+                RecvWorkerManagerMsg::NotifyRecvWorkerExpiryChanged(
+                    worker_uuid,
+                    app_name,
+                    expire_epoch) => {
+
+                    self.handle_msg_notify_expiry_changed(worker_uuid, app_name, expire_epoch).await
+                },
+
                 /////////////////////////////////////////////////
                 RecvWorkerManagerMsg::SpawnRecvWorker(                    
                     responder, 
-                    req_publish) => {
+                    req_publish,
+                    overrides) => {
 
-                    self.handle_msg_spawn_recv_worker(responder, req_publish).await?
+                    self.handle_msg_spawn_recv_worker(responder, req_publish, overrides).await?
                 },
 
                 /////////////////////////////////////////////////
@@ -288,19 +1489,115 @@ impl RecvWorkerManager {
                     responder,
                     prm_type, app_name, sess_key) => {
 
-                    let resp 
-                        = self.handle_msg_terminate_recv_worker(
-                            prm_type, app_name, sess_key).await;                                               
-                    
-                    responder
-                        .send(resp)
-                        .map_err(|e|{
-                            RunnerError::RunnerMsgChanErr(
-                                "response", 
-                                "RecvWorkerManagerMsg::TerminateRecvWorker",
-                                "RecvWorkerManager::proc_msg",
-                                format!("{:?}",e))
-                        })?;
+                    self.handle_msg_terminate_recv_worker(
+                        responder, prm_type, app_name, sess_key).await?;
+                },
+
+                /////////////////////////////////////////////////
+                RecvWorkerManagerMsg::ChangeRecvWorkerExpiry(
+                    responder,
+                    prm_type, app_name, sess_key, change_prm) => {
+
+                    self.forward_worker_oper(
+                        responder, prm_type, app_name, sess_key,
+                        "RecvWorkerMsg::ChangeRecvWorkerExpiry",
+                        |r| RecvWorkerMsg::ChangeRecvWorkerExpiry(r, change_prm)).await?
+                },
+
+                /////////////////////////////////////////////////
+                RecvWorkerManagerMsg::PauseRecvWorker(
+                    responder,
+                    prm_type, app_name, sess_key, pause_prm) => {
+
+                    self.forward_worker_oper(
+                        responder, prm_type, app_name, sess_key,
+                        "RecvWorkerMsg::PauseRecvWorker",
+                        |r| RecvWorkerMsg::PauseRecvWorker(r, pause_prm)).await?
+                },
+
+                /////////////////////////////////////////////////
+                RecvWorkerManagerMsg::ResumeRecvWorker(
+                    responder,
+                    prm_type, app_name, sess_key) => {
+
+                    self.forward_worker_oper(
+                        responder, prm_type, app_name, sess_key,
+                        "RecvWorkerMsg::ResumeRecvWorker",
+                        |r| RecvWorkerMsg::ResumeRecvWorker(r)).await?
+                },
+
+                /////////////////////////////////////////////////
+                RecvWorkerManagerMsg::TerminateRecvWorkers(responder, filter) => {
+                    self.handle_msg_terminate_recv_workers(responder, filter).await?
+                },
+
+                /////////////////////////////////////////////////
+                RecvWorkerManagerMsg::QueryRecvWorkerStates(responder) => {
+                    self.handle_msg_query_recv_worker_states(responder).await?
+                },
+
+                /////////////////////////////////////////////////
+                RecvWorkerManagerMsg::QuerySessions(responder, filter) => {
+                    self.handle_msg_query_sessions(responder, filter).await?
+                },
+
+                /////////////////////////////////////////////////
+                RecvWorkerManagerMsg::QuerySession(responder, app_name) => {
+                    self.handle_msg_query_session(responder, app_name).await?
+                },
+
+                /////////////////////////////////////////////////
+                RecvWorkerManagerMsg::ShutdownRecvWorkers(responder, shutdown_prm) => {
+                    self.handle_msg_shutdown_recv_workers(responder, shutdown_prm).await?
+                },
+
+                /////////////////////////////////////////////////
+                RecvWorkerManagerMsg::QueryNodeState(responder) => {
+                    self.handle_msg_query_node_state(responder).await?
+                },
+
+                RecvWorkerManagerMsg::QueryCluster(responder) => {
+                    self.handle_msg_query_cluster(responder).await?
+                },
+
+                RecvWorkerManagerMsg::NotifyRecordingArchived(responder, app_name, file_name) => {
+                    self.handle_msg_notify_recording_archived(responder, app_name, file_name).await?
+                },
+
+                RecvWorkerManagerMsg::QueryCapacity(responder) => {
+                    self.handle_msg_query_capacity(responder).await?
+                },
+
+                /////////////////////////////////////////////////
+                RecvWorkerManagerMsg::UpdateVSvrServSender(vsvr_serv_msg_send) => {
+                    log::info!("[RecvWorkerManager] VSvrServ is restarted, switching sender..");
+
+                    self.vsvr_serv_msg_send = vsvr_serv_msg_send;
+                },
+
+                /////////////////////////////////////////////////
+                RecvWorkerManagerMsg::NotifyShutdownCompleted(mut report) => {
+                    // recordings of exited workers are renamed before process exits
+                    let unfinalized = self.await_finalize_tasks().await;
+
+                    if !unfinalized.is_empty() {
+                        report["unfinalized"] = json!(unfinalized);
+                    }
+
+                    log::info!("[RecvWorkerManager] shutdown is completed, report={}", report);
+
+                    self.destroy();
+                    self.status = RecvWorkerManagerStatus::Destroying;
+
+                    for responder in self.shutdown_responders.drain(..) {
+                        if let Err(_) = responder.send(Ok(report.clone())) {
+                            log::error!("[RecvWorkerManager] failed to send response, \
+                                msg=RecvWorkerManagerMsg::ShutdownRecvWorkers");
+                        }
+                    }
+
+                    // all workers are joined, nothing to manage
+                    self.force_exit = true;
                 },
 
                 _ => {}
@@ -332,15 +1629,38 @@ impl RecvWorkerManager {
         vsvr_serv_msg_send: VSvrServMsgSend,
         config: Config,
         publish_ip: EchoIpStr,                
+        handover: RecvWorkerManagerHandoverSlot,
         ) -> Self {
-                
-        let (sender, 
-            receiver) = mpsc::unbounded_channel();
 
-        let avail_publish_ports 
-            = Arc::new(EchoAsyncRwLock::new(
-                (config.echo_publish_min_port..= 
-                    config.echo_publish_max_port).collect()));
+        // left by previous manager, its workers are adopted
+        let prev = match handover.lock() {
+            Ok(mut x) => x.take(),
+            Err(e) => e.into_inner().take(),
+        };
+
+        let adopted = prev.is_some();
+
+        let (sender, receiver, worker_handles, avail_publish_ports, cluster, finalize_tasks,
+            skipped_sessions, releasing_ports) = match prev {
+            // heartbeat of previous manager keeps running with same registry
+            Some(x) => (x.msg_send, x.msg_received, x.worker_handles, x.avail_publish_ports, x.cluster,
+                x.finalize_tasks, x.skipped_sessions, x.releasing_ports),
+            None => {
+                let (sender, 
+                    receiver) = mpsc::unbounded_channel();
+
+                let avail_publish_ports 
+                    = Arc::new(EchoAsyncRwLock::new(
+                        (config.echo_publish_min_port..= 
+                            config.echo_publish_max_port).collect()));
+
+                (sender, receiver, HashMap::new(), avail_publish_ports,
+                    ClusterRegistry::from_env(&publish_ip), Vec::new(), Vec::new())
+            }
+        };
+
+        let session_store = store::new_session_store(&publish_ip)
+            .map(store::SessionStoreWriter::spawn);
 
         let inst = Self {
             force_exit: false,
@@ -356,7 +1676,7 @@ impl RecvWorkerManager {
             vsvr_serv_msg_send,
 
             // key: app_name, value: worker
-            worker_handles: HashMap::new(),
+            worker_handles,
 
             is_destroyed: false,
             
@@ -364,13 +1684,70 @@ impl RecvWorkerManager {
 
             // config.echo_publish_min_port ~ // config.echo_publish_max_port
             avail_publish_ports : avail_publish_ports,
+
+            driver_registry: DriverRegistry::with_defaults(),
+
+            admission_policy: AdmissionPolicy::from_env(),
+
+            session_store,
+            skipped_sessions,
+            skipped_sessions_checked_at: EchoTimeInstant::now(),
+            releasing_ports,
+
+            cluster,
+
+            shutdown_responders: Vec::new(),
+            shutdown_terminate_notify: Arc::new(tokio::sync::Notify::new()),
+
+            finalize_tasks,
+
+            handover,
+            adopted,
         };
 
-        log::info!("Created RecvWorkerManager..");
+        log::info!("Created RecvWorkerManager.., admission_policy={:?}, session_store={:?}, \
+            cluster_node_id={:?}, adopted_sessions={}", inst.admission_policy, 
+            inst.session_store.as_ref().map(|x| x.store().name()),
+            inst.cluster.as_ref().map(|x| x.node_id().to_string()),
+            inst.worker_handles.len());
 
         inst
     }
 
+    // background finalizing of recordings, up to SHUTDOWN_FINALIZE_DEADLINE_SEC
+    // > app_names of which recordings are not finalized within deadline are returned
+    async fn await_finalize_tasks(&mut self) -> Vec<String> {
+        let deadline = tokio::time::Instant::now()
+            + Duration::from_secs(SHUTDOWN_FINALIZE_DEADLINE_SEC);
+
+        let mut unfinalized = Vec::new();
+
+        for (app_name, task) in self.finalize_tasks.drain(..) {
+            if task.is_finished() {
+                continue;
+            }
+
+            log::info!("[RecvWorkerManager::await_finalize_tasks] waiting recordings to be finalized, \
+                app_name={}", app_name);
+
+            match tokio::time::timeout_at(deadline, task).await {
+                Ok(Ok(_)) => {},
+                Ok(Err(e)) => {
+                    log::error!("[RecvWorkerManager::await_finalize_tasks] finalize task is failed, \
+                        app_name={}, e={}", app_name, e);
+                    unfinalized.push(app_name);
+                },
+                Err(_) => {
+                    log::error!("[RecvWorkerManager::await_finalize_tasks] recordings are not finalized \
+                        within deadline, app_name={}", app_name);
+                    unfinalized.push(app_name);
+                },
+            }
+        }
+
+        unfinalized
+    }
+
     pub fn destroy(&mut self) {
         if self.status == RecvWorkerManagerStatus::Destroying {
             return;
@@ -423,29 +1800,8 @@ impl RecvWorkerManager {
                 = self.pop_publish_port().await?;
 
             // check port is already in use            
-            {
-                let rst = std::process::Command::new("bash")
-                        .arg("-c")
-                        .arg(format!("lsof -n -i TCP:{} | grep LISTEN", publish_port))
-                        .execute_output();
-
-                if rst.is_err() {
-                    // can't execute 'lsof', skip port check
-                    return Ok(publish_port);
-                }
-
-                if let Some(r) = rst.unwrap().status.code() {
-
-                    if r != 0 {  // port not in use
-                        return Ok(publish_port); 
-                    }
-                    
-                    // port is already in use, continue 
-
-                } else {
-                    // error in execute 'lsof', skip port check
-                    return Ok(publish_port);
-                }
+            if !is_port_listening(publish_port) {
+                return Ok(publish_port);
             }
 
             try_count += 1;
@@ -470,8 +1826,13 @@ impl RecvWorkerManager {
         req_publish: ReqPublishV3,        
         publish_ip : &str,
         publish_port: u16,
+        backup_publish_port: Option<u16>,
+        expire_duration: EchoTimeDuration,
+        overrides: PublishOverrides,
     )->Result<(Arc<EchoAsyncRwLock<RecvWorkerHandle>>, RecvWorker), RunnerError> {
 
+        let start_epoch = EchoUtc::now().timestamp();
+
         match RecvWorker::new(
             config.clone(),
             worker_uuid.clone(), 
@@ -480,7 +1841,10 @@ impl RecvWorkerManager {
             self.vsvr_serv_msg_send.clone(),
             req_publish.clone(),
             publish_port,
-            config.echo_publish_max_duration
+            expire_duration,
+            overrides.loudnorm.clone(),
+            backup_publish_port,
+            &self.driver_registry,
         ) {
             Ok(worker) => {
                 let worker_handle_uuid = EchoUUID_new();
@@ -495,6 +1859,13 @@ impl RecvWorkerManager {
                             sess_key: req_publish.sess_key.clone(),
                             publish_ip: publish_ip.to_string(),
                             publish_port: publish_port,                        
+                            backup_publish_port,
+                            media_type: req_publish.media.media_type,
+                            profile: req_publish.receiver_prm.args.clone(),
+                            req_publish: req_publish.clone(),
+                            overrides,
+                            start_epoch,
+                            expire_epoch: start_epoch + expire_duration.as_secs() as i64,
                         }));
 
                 self.worker_handles.insert(
@@ -517,9 +1888,26 @@ impl RecvWorkerManager {
     pub async fn run(mut self) {
         log::debug!("[RecvWorkerManager::run] starting..");
 
+        // adopted manager: sessions, ports and channel are taken over as they were,
+        // > background tasks of previous manager keep sending to same channel
+        if !self.adopted {
+            // before accepting publish, sessions of previous run take their ports first
+            self.reconcile_sessions().await;
+
+            if let Some(cluster) = self.cluster.clone() {
+                tokio::spawn(cluster::run_cluster_heartbeat(cluster, self.msg_send.clone()));
+            }
+
+            tokio::spawn(event_bus::run_session_stats(self.msg_send.clone()));
+            tokio::spawn(crate::metrics::run_metrics_collector(
+                self.msg_send.clone(), self.config.echo_rec_root_dir.clone()));
+        }
+
         self.status = RecvWorkerManagerStatus::Running;
 
         loop {
+            crate::health::stamp_manager_watchdog();
+
             match self.proc_msg().await {
                 Err(RunnerError::RunnerMsgChanErr(_e0, _e1, _e2, _e3)) => {
                     log::error!("[RecvWorkerManager::run] receive msg channel err.., force exiting..: e={0},{1},{2},{3}",
@@ -528,32 +1916,484 @@ impl RecvWorkerManager {
 
                     break;
                 },
-                Err(e) => {
+                Err(e) if e.is_fatal() => {
                     log::error!("[RecvWorkerManager::run] error occured., force exiting..: e={:?}", e);
                     self.force_exit = true;
 
                     break;
                 },
+                Err(e) => {
+                    // failure of a message must not stop other sessions
+                    log::error!("[RecvWorkerManager::run] failed to process msg, e={:?}", e);
+                },
                 Ok(_) => {}               
             }
+
+            self.check_skipped_sessions().await;
             
             if (self.force_exit) {
                 log::debug!("[RecvWorkerManager::run] force exiting..");
                 break;
+            }
+        }
+    }
 
-            } else {
-                // prevent blocking thread, do not block other task is beging drive
-                tokio::time::sleep(Duration::from_millis(100)).await;
+}
+
+// send RecvWorkerMsg::FinishRecvWorker, and await worker accepted it
+async fn finish_recv_worker(worker_msg_send: RecvWorkerMsgSend) -> Result<PrmJson, RunnerError> {
+    let (fin_responder,
+         fin_recv)
+        = tokio::sync::oneshot::channel();
+
+    worker_msg_send
+        .send(RecvWorkerMsg::FinishRecvWorker(fin_responder, json!({})))
+        .map_err(|e|
+            RunnerError::RunnerMsgChanErr(
+                "send_fail", 
+                "RecvWorkerMsg::FinishRecvWorker",
+                "finish_recv_worker",
+                e.to_string()))?;
+
+    let fin_rst 
+        = fin_recv
+            .await
+            .map_err(|e|
+                RunnerError::RunnerMsgChanErr(
+                    "recv_rst_fail", 
+                    "RecvWorkerMsg::FinishRecvWorker",
+                    "finish_recv_worker",
+                    e.to_string())
+            )?;
+
+    fin_rst
+}
+
+// finish_recv_worker, and await worker is exited(its message channel is closed)
+// > reported as stopped only after receiver and recordings are ended
+async fn finish_recv_worker_and_wait(worker_msg_send: RecvWorkerMsgSend) 
+    -> Result<PrmJson, RunnerError> {
+
+    let fin_rst = finish_recv_worker(worker_msg_send.clone()).await?;
+
+    tokio::time::timeout(
+        Duration::from_secs(WORKER_EXIT_TIMEOUT_SEC), 
+        worker_msg_send.closed())
+        .await
+        .map_err(|_| RunnerError::RunnerOperErr(format!(
+            "worker is not exited in {} sec, f=finish_recv_worker_and_wait", 
+            WORKER_EXIT_TIMEOUT_SEC)))?;
+
+    Ok(fin_rst)
+}
+
+// 'lsof' of listening tcp port, false: not in use or can't check
+// receiver args by ffmpeg capability, and loudnorm override
+// > none is checked until ffmpeg is probed
+fn check_capability(req_publish: &ReqPublishV3, overrides: &PublishOverrides) -> Result<(), String> {
+    if let Some(caps) = capability::get() {
+        caps.validate_publish(req_publish)?;
+    }
+
+    // loudness normalization, audio is encoded instead of copied
+    if let Some(loudnorm) = overrides.loudnorm.as_ref() {
+        loudnorm.validate().and_then(|_| match capability::get() {
+            Some(caps) if !caps.has_encoder(LOUDNORM_ACODEC) => Err(format!(
+                "encoder of loudness normalization is not available, codec={}", LOUDNORM_ACODEC)),
+            _ => Ok(()),
+        })?;
+    }
+
+    Ok(())
+}
+
+fn is_session_port_listening(rec: &SessionRecord) -> bool {
+    is_port_listening(rec.publish_port) || rec.backup_publish_port.map_or(false, is_port_listening)
+}
+
+fn is_port_listening(port: u16) -> bool {
+    let rst = std::process::Command::new("bash")
+            .arg("-c")
+            .arg(format!("lsof -n -i TCP:{} | grep LISTEN", port))
+            .execute_output();
+
+    match rst {
+        Ok(output) => output.status.code() == Some(0),
+        // can't execute 'lsof', skip port check
+        Err(_) => false,
+    }
+}
+
+// loudness of each recording is tagged, then _NEW -> _FIN
+// > untagged recording(measure failed) is finalized as is, loudness is reported as null
+async fn finalize_normalized_recordings(
+    ffmpeg_path: String,
+    rec_out_path: String,
+    new_files: Vec<String>,
+    prm: LoudnormPrm,
+    app_name: String,
+    worker_uuid: String) {
+
+    let mut finalized = Vec::new();
+    let mut loudness = Vec::new();
+
+    for file_name in new_files {
+        let file_path = PathBuf::from(&rec_out_path).join(&file_name);
+
+        match loudnorm::tag_recording_loudness(&ffmpeg_path, &file_path, &prm).await {
+            Ok(x) => loudness.push(x),
+            Err(e) => log::error!("[RecvWorkerManager::finalize_normalized_recordings] failed to tag \
+                loudness, app_name={}, file={}, e={}", app_name, file_name, e.to_string()),
+        }
+
+        match store::finalize_record_file(&rec_out_path, &file_name).await {
+            Ok(x) => finalized.push(x),
+            Err(e) => log::error!("[RecvWorkerManager::finalize_normalized_recordings] \
+                app_name={}, e={}", app_name, e.to_string()),
+        }
+    }
+
+    if finalized.is_empty() {
+        return;
+    }
+
+    log::info!("[RecvWorkerManager] normalized recordings are finalized, app_name={}, files={:?}",
+        app_name, finalized);
+
+    webhook::notify(WebhookEvent::RecordingFinished, &app_name, &worker_uuid, json!({
+        "rec_out_path" : rec_out_path,
+        "files" : finalized,
+        "loudness" : loudness,
+    }));
+}
+
+impl RecvWorkerHandle {
+    fn to_session_record(&self) -> SessionRecord {
+        SessionRecord {
+            worker_uuid: self.worker_uuid.clone(),
+            app_name: self.app_name.clone(),
+            sess_key: self.sess_key.clone(),
+            publish_port: self.publish_port,
+            backup_publish_port: self.backup_publish_port,
+            start_epoch: self.start_epoch,
+            expire_epoch: self.expire_epoch,
+            req_publish: self.req_publish.clone(),
+            overrides: self.overrides.clone(),
+        }
+    }
+}
+
+fn to_admission_sessions(attrs: &[(String, MediaType, String)]) -> Vec<AdmissionSession> {
+    attrs
+        .iter()
+        .map(|(app_name, media_type, profile)| AdmissionSession {
+            app_name: app_name.as_str(),
+            media_type: *media_type,
+            profile: profile.as_str(),
+        })
+        .collect()
+}
+
+//
+// shutdown
+//
+
+// worker taken from RecvWorkerHandle on shutdown
+struct ShutdownRecvWorker {
+    worker_uuid: String,
+    app_name: String,
+    sess_key: String,
+    worker_msg_send: RecvWorkerMsgSend,
+    joinhandle: EchoJoinHandle<(), RunnerError>,
+}
+
+impl ShutdownRecvWorker {
+    fn to_json(&self) -> PrmJson {
+        json!({
+            "worker_uuid" : self.worker_uuid,
+            "app_name"    : self.app_name,
+            "sess_key"    : self.sess_key,
+        })
+    }
+}
+
+// drain(optional) and terminate workers, answer is the shutdown report
+// > stopped: terminated by shutdown, drained: ended naturally while draining
+// > unconfirmed: worker is not joined within deadline, ffmpeg may be alive
+async fn shutdown_recv_workers(
+    mut workers: Vec<ShutdownRecvWorker>,
+    shutdown_prm: ShutdownPrm,
+    terminate_notify: Arc<tokio::sync::Notify>) -> PrmJson {
+
+    let mut drained = Vec::new();
+    let mut stopped = Vec::new();
+    let mut unconfirmed = Vec::new();
+
+    let mut terminate_deadline_sec = SHUTDOWN_TERMINATE_DEADLINE_SEC;
+
+    if shutdown_prm.mode == ShutdownMode::Drain {
+        let drain_deadline_sec = match shutdown_prm.deadline_sec {
+            0 => SHUTDOWN_DRAIN_DEADLINE_SEC,
+            n => n,
+        };
+
+        let drain_deadline = tokio::time::Instant::now() 
+            + Duration::from_secs(drain_deadline_sec);
+
+        log::info!("[RecvWorkerManager::shutdown_recv_workers] draining {} workers, \
+            drain_deadline_sec={}", workers.len(), drain_deadline_sec);
+
+        loop {
+            let (finished, running): (Vec<_>, Vec<_>) 
+                = workers.into_iter().partition(|w| w.joinhandle.is_finished());
+
+            workers = running;
+
+            for w in finished.into_iter() {
+                let worker = w.to_json();
+
+                match w.joinhandle.await {
+                    Ok(Ok(_)) => drained.push(worker),
+                    _ => unconfirmed.push(worker),
+                }
+            }
+
+            if workers.is_empty() {
+                break;
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep_until(drain_deadline) => {
+                    log::info!("[RecvWorkerManager::shutdown_recv_workers] drain deadline is reached, \
+                        terminating {} workers", workers.len());
+                    break;
+                },
+                _ = terminate_notify.notified() => {
+                    log::info!("[RecvWorkerManager::shutdown_recv_workers] terminate is requested \
+                        while draining, terminating {} workers", workers.len());
+                    break;
+                },
+                _ = tokio::time::sleep(Duration::from_millis(POLL_PERIOD_TERMINATE_MS)) => {}
+            }
+        }
+    } else if shutdown_prm.deadline_sec > 0 {
+        terminate_deadline_sec = shutdown_prm.deadline_sec;
+    }
+
+    // one deadline for all workers, terminating in parallel
+    let deadline = tokio::time::Instant::now() 
+        + Duration::from_secs(terminate_deadline_sec);
+
+    let finishing: Vec<_> = workers
+        .into_iter()
+        .map(|w| tokio::spawn(finish_and_join_recv_worker(w, deadline)))
+        .collect();
+
+    for f in finishing.into_iter() {
+        match f.await {
+            Ok((worker, true)) => stopped.push(worker),
+            Ok((worker, false)) => unconfirmed.push(worker),
+            Err(e) => {
+                log::error!("[RecvWorkerManager::shutdown_recv_workers] \
+                    terminating task is failed, e={}", e.to_string());
             }
+        }
+    }
+
+    json!({
+        "mode"        : shutdown_prm.mode,
+        "stopped"     : stopped,
+        "drained"     : drained,
+        "unconfirmed" : unconfirmed,
+    })
+}
+
+// FinishRecvWorker, then join worker until deadline
+// > worker is joined after driver.end(), all ffmpegs of worker are dead
+async fn finish_and_join_recv_worker(
+    w: ShutdownRecvWorker,
+    deadline: tokio::time::Instant) -> (PrmJson, bool) {
+
+    let worker = w.to_json();
+
+    // worker may be already exiting by itself(expired), joined below anyway
+    match tokio::time::timeout_at(deadline, finish_recv_worker(w.worker_msg_send)).await {
+        Ok(Ok(_)) => {},
+        Ok(Err(e)) => {
+            log::debug!("[RecvWorkerManager::finish_and_join_recv_worker] failed to finish, \
+                worker_uuid={}, e={}", w.worker_uuid, e.to_string());
+        },
+        Err(_) => {
+            log::error!("[RecvWorkerManager::finish_and_join_recv_worker] timeout on finish, \
+                worker_uuid={}", w.worker_uuid);
+        }
+    }
+
+    match tokio::time::timeout_at(deadline, w.joinhandle).await {
+        Ok(Ok(Ok(_))) => (worker, true),
+        Ok(Ok(Err(e))) => {
+            log::error!("[RecvWorkerManager::finish_and_join_recv_worker] worker exited with error, \
+                worker_uuid={}, e={}", w.worker_uuid, e.to_string());
+            (worker, false)
+        },
+        Ok(Err(e)) => {
+            log::error!("[RecvWorkerManager::finish_and_join_recv_worker] worker task is failed, \
+                worker_uuid={}, e={}", w.worker_uuid, e.to_string());
+            (worker, false)
+        },
+        Err(_) => {
+            log::error!("[RecvWorkerManager::finish_and_join_recv_worker] timeout on joining worker, \
+                worker_uuid={}", w.worker_uuid);
+            (worker, false)
+        }
+    }
+}
+
+// send RecvWorkerMsg::QueryRecvWorkerState to all workers, and aggregate answers
+// > (worker_uuid, worker_msg_send)
+async fn query_recv_worker_states(
+    worker_msg_sends: Vec<(String, RecvWorkerMsgSend)>) -> Vec<RecvWorkerStateSnapshot> {
+
+    let mut pending = Vec::new();
+
+    for (worker_uuid, worker_msg_send) in worker_msg_sends.into_iter() {
+        let (query_responder,
+             query_recv)
+            = tokio::sync::oneshot::channel();
 
+        if let Err(e) = worker_msg_send
+            .send(RecvWorkerMsg::QueryRecvWorkerState(query_responder)) {
+            // worker is exiting
+            log::debug!("[RecvWorkerManager::query_recv_worker_states] failed to send query, \
+                worker_uuid={}, e={}", worker_uuid, e.to_string());
+            continue;
         }
+
+        pending.push((worker_uuid, query_recv));
+    }
+
+    // all workers are answering concurrently, bounded by one deadline
+    let deadline = tokio::time::Instant::now() 
+        + Duration::from_millis(QUERY_WORKER_STATE_TIMEOUT_MS);
+
+    let mut snapshots = Vec::new();
+
+    for (worker_uuid, query_recv) in pending.into_iter() {
+        match tokio::time::timeout_at(deadline, query_recv).await {
+            Ok(Ok(Ok(snapshot))) => snapshots.push(snapshot),
+            Ok(Ok(Err(e))) => {
+                log::error!("[RecvWorkerManager::query_recv_worker_states] worker answered error, \
+                    worker_uuid={}, e={}", worker_uuid, e.to_string());
+            },
+            Ok(Err(e)) => {
+                log::debug!("[RecvWorkerManager::query_recv_worker_states] worker is gone, \
+                    worker_uuid={}, e={}", worker_uuid, e.to_string());
+            },
+            Err(_) => {
+                log::error!("[RecvWorkerManager::query_recv_worker_states] timeout, \
+                    worker_uuid={}, timeout_ms={}", worker_uuid, QUERY_WORKER_STATE_TIMEOUT_MS);
+            }
+        }
+    }
+
+    snapshots
+}
+
+fn new_session_info(state: RecvWorkerStateSnapshot, publish_ip: &str) -> SessionInfo {
+    let cur_epoch = EchoUtc::now().timestamp();
+
+    SessionInfo {
+        publish_ip: publish_ip.to_string(),
+        publish_url: format!("rtmp://{}:{}/{}", 
+            publish_ip, state.publish_port, state.app_name),
+        backup_publish_url: state.failover.as_ref().map(|x| format!("rtmp://{}:{}/{}", 
+            publish_ip, x.backup_publish_port, state.app_name)),
+
+        uptime_sec: (cur_epoch - state.start_epoch).max(0),
+        remain_sec: (state.expire_epoch - cur_epoch).max(0),
+
+        state,
     }
+}
+
+// apply filter(state, media_type, min_uptime_sec), app_name_prefix is already applied
+// > ordered by app_name
+fn filter_sessions(
+    snapshots: Vec<RecvWorkerStateSnapshot>,
+    publish_ip: &str,
+    filter: &SessionQueryFilter) -> Vec<SessionInfo> {
+
+    let mut sessions: Vec<SessionInfo> = snapshots
+        .into_iter()
+        .filter(|x| filter.state.as_ref().map_or(true, |s| x.driver_status == *s))
+        .filter(|x| filter.media_type.map_or(true, |m| x.media_type == m))
+        .map(|x| new_session_info(x, publish_ip))
+        .filter(|x| filter.min_uptime_sec.map_or(true, |u| x.uptime_sec >= u))
+        .collect();
+
+    // stable order for pagination
+    sessions.sort_by(|a, b| a.state.app_name.cmp(&b.state.app_name));
+
+    sessions
+}
+
+// filter_sessions and pagination
+fn build_session_list(
+    snapshots: Vec<RecvWorkerStateSnapshot>,
+    publish_ip: &str,
+    filter: &SessionQueryFilter) -> SessionList {
+
+    let sessions = filter_sessions(snapshots, publish_ip, filter);
+
+    let limit = match filter.limit {
+        0 => SESSION_LIST_DEFAULT_LIMIT,
+        n => n.min(SESSION_LIST_MAX_LIMIT),
+    };
+
+    let total = sessions.len();
+
+    let sessions = sessions
+        .into_iter()
+        .skip(filter.offset)
+        .take(limit)
+        .collect();
 
+    SessionList {
+        total,
+        offset: filter.offset,
+        limit,
+        sessions,
+    }
 }
 
 impl Drop for RecvWorkerManager {
     fn drop(&mut self) {
         log::debug!("[RecvWorkerManager] drop called");
-        self.destroy()
+        self.destroy();
+
+        // exited on shutdown, channel is closed(heartbeat leaves cluster)
+        if self.status == RecvWorkerManagerStatus::Draining
+            || self.status == RecvWorkerManagerStatus::Destroying {
+            return;
+        }
+
+        // also on panic, respawned manager takes over running workers
+        let (_, dummy_recv) = mpsc::unbounded_channel();
+
+        let handover = RecvWorkerManagerHandover {
+            msg_received: std::mem::replace(&mut self.msg_received, dummy_recv),
+            msg_send: self.msg_send.clone(),
+            worker_handles: std::mem::take(&mut self.worker_handles),
+            avail_publish_ports: self.avail_publish_ports.clone(),
+            cluster: self.cluster.clone(),
+            finalize_tasks: std::mem::take(&mut self.finalize_tasks),
+            skipped_sessions: std::mem::take(&mut self.skipped_sessions),
+            releasing_ports: std::mem::take(&mut self.releasing_ports),
+        };
+
+        match self.handover.lock() {
+            Ok(mut x) => *x = Some(handover),
+            Err(e) => *e.into_inner() = Some(handover),
+        }
     }
 }
@@ -1,11 +1,18 @@
 use crate::comm::*;
 use crate::service::api::reqres::publish::req::ReqPublishV3;
+use crate::service::vsvr::message::VSvrServMsgSend;
 use anyhow::Result;
 use strum::Display;
 use tokio::sync::mpsc;
 use PrmJsonValue;
 
-use super::RunnerError;
+use super::admission::CapacityReport;
+use super::cluster::NodeState;
+use super::publish_auth::PublishOverrides;
+use super::{
+    ExpiryChangePrm, RecvWorkerStateSnapshot, RunnerError, SessionInfo, SessionList,
+    SessionPausePrm, SessionQueryFilter, ShutdownPrm,
+};
 
 //
 // RecvWorkerManager Message
@@ -28,9 +35,15 @@ pub enum TerminateRecvWorkerPrmType {
 
 #[derive(Debug)]
 pub enum RecvWorkerManagerMsg {
-    SpawnRecvWorker(SpawnRecvWorkerMsgOCResponder, ReqPublishV3),
+    // overrides: decided by on_publish authorization
+    SpawnRecvWorker(
+        SpawnRecvWorkerMsgOCResponder,
+        ReqPublishV3,
+        PublishOverrides,
+    ),
 
-    // (responder, PrmType, AppName, SessionKey)
+    // (responder, PrmType, AppName or WorkerUUID, SessionKey)
+    // > rst_json: { "stopped": [ {worker_uuid, app_name, sess_key} ] }
     TerminateRecvWorker(
         TerminateRecvWorkerMsgOCResponder,
         TerminateRecvWorkerPrmType,
@@ -40,6 +53,71 @@ pub enum RecvWorkerManagerMsg {
 
     // (worker_uuid, app_name, sess_key)
     NotifyRecvWorkerIsExiting(String, String, String),
+
+    // (worker_uuid, app_name, expire_epoch), persisted to session store
+    NotifyRecvWorkerExpiryChanged(String, String, i64),
+
+    // (responder, PrmType, AppName or WorkerUUID, SessionKey, change)
+    ChangeRecvWorkerExpiry(
+        ChangeRecvWorkerExpiryMsgOCResponder,
+        TerminateRecvWorkerPrmType,
+        String,
+        String,
+        ExpiryChangePrm,
+    ),
+
+    // (responder, PrmType, AppName or WorkerUUID, SessionKey, pause)
+    PauseRecvWorker(
+        PauseRecvWorkerMsgOCResponder,
+        TerminateRecvWorkerPrmType,
+        String,
+        String,
+        SessionPausePrm,
+    ),
+
+    // (responder, PrmType, AppName or WorkerUUID, SessionKey)
+    ResumeRecvWorker(
+        PauseRecvWorkerMsgOCResponder,
+        TerminateRecvWorkerPrmType,
+        String,
+        String,
+    ),
+
+    // (responder, filter), bulk termination
+    // > rst_json: { "stopped": [ {worker_uuid, app_name, sess_key}, .. ], "failed": [ .. ] }
+    TerminateRecvWorkers(TerminateRecvWorkersMsgOCResponder, SessionQueryFilter),
+
+    // fan out RecvWorkerMsg::QueryRecvWorkerState to all workers
+    QueryRecvWorkerStates(QueryRecvWorkerStatesMsgOCResponder),
+
+    // (responder, filter)
+    QuerySessions(QuerySessionsMsgOCResponder, SessionQueryFilter),
+
+    // (responder, app_name)
+    QuerySession(QuerySessionMsgOCResponder, String),
+
+    // (responder, shutdown), new publish is rejected from now
+    // > rst_json: { "mode", "stopped": [..], "drained": [..], "unconfirmed": [..] }
+    ShutdownRecvWorkers(ShutdownRecvWorkersMsgOCResponder, ShutdownPrm),
+
+    // headroom of this host, for routing publishes to least-loaded node
+    QueryCapacity(QueryCapacityMsgOCResponder),
+
+    // capacity and sessions of this node, published by cluster heartbeat
+    QueryNodeState(QueryNodeStateMsgOCResponder),
+
+    // alive nodes of cluster, rst_json: { "node_id", "nodes": [..] }
+    QueryCluster(QueryClusterMsgOCResponder),
+
+    // (responder, app_name, file_name), recording file is archived(_FIN -> _ARC)
+    NotifyRecordingArchived(NotifyRecordingArchivedMsgOCResponder, String, String),
+
+    // VSvrServ is restarted by supervisor, used for notifications and new workers
+    UpdateVSvrServSender(VSvrServMsgSend),
+
+    // sent by shutdown task to manager itself, after all workers are joined
+    // > queued after NotifyRecvWorkerIsExiting of the workers
+    NotifyShutdownCompleted(PrmJson),
 }
 // QueryRecvWorkerManagerInstance,
 
@@ -48,8 +126,8 @@ pub type RecvWorkerManagerMsgRecv = mpsc::UnboundedReceiver<RecvWorkerManagerMsg
 
 // Message: SpawnRecvWorker
 
-// Result::<(publish_ip, publish_port, worker_uuid),Err>
-pub type SpawnRecvWorkerMsgRstType = Result<(String, u16, String), RunnerError>;
+// Result::<(publish_ip, publish_port, backup_publish_port, worker_uuid),Err>
+pub type SpawnRecvWorkerMsgRstType = Result<(String, u16, Option<u16>, String), RunnerError>;
 
 pub type SpawnRecvWorkerMsgOCResponder = EchoOCResponder<SpawnRecvWorkerMsgRstType>;
 pub type SpawnRecvWorkerMsgOCRst = EchoOCRst<SpawnRecvWorkerMsgRstType>;
@@ -65,6 +143,52 @@ pub type TerminateRecvWorkerMsgRstType = Result<PrmJson, RunnerError>;
 pub type TerminateRecvWorkerMsgOCResponder = EchoOCResponder<TerminateRecvWorkerMsgRstType>;
 pub type TerminateRecvWorkerMsgOCRst = EchoOCRst<TerminateRecvWorkerMsgRstType>;
 
+// Message: TerminateRecvWorkers
+pub type TerminateRecvWorkersMsgRstType = Result<PrmJson, RunnerError>;
+pub type TerminateRecvWorkersMsgOCResponder = EchoOCResponder<TerminateRecvWorkersMsgRstType>;
+pub type TerminateRecvWorkersMsgOCRst = EchoOCRst<TerminateRecvWorkersMsgRstType>;
+
+// Message: QueryRecvWorkerStates
+// > workers which failed to answer in time are omitted
+pub type QueryRecvWorkerStatesMsgRstType = Result<Vec<RecvWorkerStateSnapshot>, RunnerError>;
+pub type QueryRecvWorkerStatesMsgOCResponder = EchoOCResponder<QueryRecvWorkerStatesMsgRstType>;
+pub type QueryRecvWorkerStatesMsgOCRst = EchoOCRst<QueryRecvWorkerStatesMsgRstType>;
+
+// Message: QuerySessions
+pub type QuerySessionsMsgRstType = Result<SessionList, RunnerError>;
+pub type QuerySessionsMsgOCResponder = EchoOCResponder<QuerySessionsMsgRstType>;
+pub type QuerySessionsMsgOCRst = EchoOCRst<QuerySessionsMsgRstType>;
+
+// Message: QuerySession
+pub type QuerySessionMsgRstType = Result<SessionInfo, RunnerError>;
+pub type QuerySessionMsgOCResponder = EchoOCResponder<QuerySessionMsgRstType>;
+pub type QuerySessionMsgOCRst = EchoOCRst<QuerySessionMsgRstType>;
+
+// Message: QueryCapacity
+pub type QueryCapacityMsgRstType = Result<CapacityReport, RunnerError>;
+pub type QueryCapacityMsgOCResponder = EchoOCResponder<QueryCapacityMsgRstType>;
+pub type QueryCapacityMsgOCRst = EchoOCRst<QueryCapacityMsgRstType>;
+
+// Message: QueryNodeState
+pub type QueryNodeStateMsgRstType = Result<NodeState, RunnerError>;
+pub type QueryNodeStateMsgOCResponder = EchoOCResponder<QueryNodeStateMsgRstType>;
+pub type QueryNodeStateMsgOCRst = EchoOCRst<QueryNodeStateMsgRstType>;
+
+// Message: QueryCluster
+pub type QueryClusterMsgRstType = Result<PrmJson, RunnerError>;
+pub type QueryClusterMsgOCResponder = EchoOCResponder<QueryClusterMsgRstType>;
+pub type QueryClusterMsgOCRst = EchoOCRst<QueryClusterMsgRstType>;
+
+// Message: NotifyRecordingArchived
+pub type NotifyRecordingArchivedMsgRstType = Result<PrmJson, RunnerError>;
+pub type NotifyRecordingArchivedMsgOCResponder = EchoOCResponder<NotifyRecordingArchivedMsgRstType>;
+pub type NotifyRecordingArchivedMsgOCRst = EchoOCRst<NotifyRecordingArchivedMsgRstType>;
+
+// Message: ShutdownRecvWorkers
+pub type ShutdownRecvWorkersMsgRstType = Result<PrmJson, RunnerError>;
+pub type ShutdownRecvWorkersMsgOCResponder = EchoOCResponder<ShutdownRecvWorkersMsgRstType>;
+pub type ShutdownRecvWorkersMsgOCRst = EchoOCRst<ShutdownRecvWorkersMsgRstType>;
+
 ///////////////////////////////////////////////////////////////////////////////
 // RecvWorker Message
 ///////////////////////////////////////////////////////////////////////////////
@@ -74,7 +198,12 @@ pub enum RecvWorkerMsg {
     StartRecvWorker(StartRecvWorkerMsgOCResponder, PrmJsonValue),
     FinishRecvWorker(FinishRecvWorkerMsgOCResponder, PrmJsonValue),
 
-    QueryRecvWorkerState,
+    QueryRecvWorkerState(QueryRecvWorkerStateMsgOCResponder),
+
+    ChangeRecvWorkerExpiry(ChangeRecvWorkerExpiryMsgOCResponder, ExpiryChangePrm),
+
+    PauseRecvWorker(PauseRecvWorkerMsgOCResponder, SessionPausePrm),
+    ResumeRecvWorker(PauseRecvWorkerMsgOCResponder),
 }
 
 //QueryManagerInstance,
@@ -94,3 +223,20 @@ pub type StartRecvWorkerMsgOCRst = EchoOCRst<StartRecvWorkerMsgRstType>;
 pub type FinishRecvWorkerMsgRstType = Result<PrmJson, RunnerError>;
 pub type FinishRecvWorkerMsgOCResponder = EchoOCResponder<FinishRecvWorkerMsgRstType>;
 pub type FinishRecvWorkerMsgOCRst = EchoOCRst<FinishRecvWorkerMsgRstType>;
+
+// QueryRecvWorkerState
+pub type QueryRecvWorkerStateMsgRstType = Result<RecvWorkerStateSnapshot, RunnerError>;
+pub type QueryRecvWorkerStateMsgOCResponder = EchoOCResponder<QueryRecvWorkerStateMsgRstType>;
+pub type QueryRecvWorkerStateMsgOCRst = EchoOCRst<QueryRecvWorkerStateMsgRstType>;
+
+// ChangeRecvWorkerExpiry
+// > rst_json: { "expire_epoch", "remain_sec", "paused" }
+pub type ChangeRecvWorkerExpiryMsgRstType = Result<PrmJson, RunnerError>;
+pub type ChangeRecvWorkerExpiryMsgOCResponder = EchoOCResponder<ChangeRecvWorkerExpiryMsgRstType>;
+pub type ChangeRecvWorkerExpiryMsgOCRst = EchoOCRst<ChangeRecvWorkerExpiryMsgRstType>;
+
+// PauseRecvWorker, ResumeRecvWorker
+// > rst_json: { "paused", "expiry_paused", "expire_epoch" }
+pub type PauseRecvWorkerMsgRstType = Result<PrmJson, RunnerError>;
+pub type PauseRecvWorkerMsgOCResponder = EchoOCResponder<PauseRecvWorkerMsgRstType>;
+pub type PauseRecvWorkerMsgOCRst = EchoOCRst<PauseRecvWorkerMsgRstType>;
@@ -0,0 +1,163 @@
+// runner::mock
+// > receiver backend without any process, MediaReceiver::MOCK
+// > publisher is reported as connected while receiver is running, for tests of worker and manager
+use super::{
+    DriverCapabilities, DriverCreatePrm, DriverIngestStats, DriverProcState, DriverProcStats,
+    DriverRst, DriverRstOk, DriverStats, PauseTarget, ReceiverDriver, ReceiverDriverBox,
+    RunnerError,
+};
+use crate::comm::EchoUtc;
+use crate::comm_media::{MediaReceiver, MediaType, Protocol};
+use async_trait::async_trait;
+use std::time::{Duration, Instant};
+
+// "1": MediaReceiver::MOCK is registered by DriverRegistry::with_defaults
+pub const MOCK_RECEIVER_ENV: &str = "ECHO_MOCK_RECEIVER";
+
+// receiver_args, ex) 'duration_sec=30'
+// > receiver is finished after duration like publisher is disconnected, runs until ended if unset
+const ARG_DURATION_SEC: &str = "duration_sec=";
+
+pub fn is_enabled() -> bool {
+    std::env::var(MOCK_RECEIVER_ENV).as_deref() == Ok("1")
+}
+
+// PFnNewReceiverDriver for MediaReceiver::MOCK
+pub fn new_receiver_driver(prm: DriverCreatePrm) -> Result<ReceiverDriverBox, RunnerError> {
+    let duration = prm
+        .receiver_args
+        .split_whitespace()
+        .find_map(|x| x.strip_prefix(ARG_DURATION_SEC))
+        .and_then(|x| x.parse().ok())
+        .map(Duration::from_secs);
+
+    log::info!(
+        "[MockReceiverDriver] created, app_name={}, publish_port={}, duration={:?}",
+        prm.app_name,
+        prm.publish_port,
+        duration
+    );
+
+    Ok(Box::new(MockReceiverDriver {
+        duration,
+        started_at: None,
+        paused: None,
+        frame: 0,
+        respawn_cnt: 0,
+    }))
+}
+
+pub struct MockReceiverDriver {
+    duration: Option<Duration>,
+
+    // none: not begun, or ended
+    started_at: Option<Instant>,
+    paused: Option<PauseTarget>,
+
+    // counted on every tick while not paused
+    frame: u64,
+    respawn_cnt: u32,
+}
+
+#[async_trait]
+impl ReceiverDriver for MockReceiverDriver {
+    async fn begin(&mut self) -> DriverRst {
+        self.started_at = Some(Instant::now());
+
+        Ok(DriverRstOk::Ok)
+    }
+
+    async fn tick(&mut self) -> DriverRst {
+        let started_at = match self.started_at {
+            Some(x) => x,
+            None => return Ok(DriverRstOk::Ok),
+        };
+
+        if self.duration.map_or(false, |x| started_at.elapsed() >= x) {
+            // respawned like ffmpeg receiver exited by disconnection of publisher
+            return Ok(DriverRstOk::Finished(true));
+        }
+
+        if self.paused != Some(PauseTarget::Pipeline) {
+            self.frame += 1;
+        }
+
+        Ok(DriverRstOk::Ok)
+    }
+
+    async fn end(&mut self) -> DriverRst {
+        self.started_at = None;
+
+        Ok(DriverRstOk::Ok)
+    }
+
+    async fn reset(&mut self) -> DriverRst {
+        if self.frame > 0 {
+            self.respawn_cnt += 1;
+        }
+
+        self.paused = None;
+        self.frame = 0;
+
+        Ok(DriverRstOk::Ok)
+    }
+
+    async fn pause(&mut self, target: PauseTarget) -> DriverRst {
+        self.paused = Some(target);
+
+        Ok(DriverRstOk::Ok)
+    }
+
+    async fn resume(&mut self) -> DriverRst {
+        self.paused = None;
+
+        Ok(DriverRstOk::Ok)
+    }
+
+    async fn stats(&self) -> DriverStats {
+        let running = self.started_at.is_some();
+
+        let state = match (running, self.paused) {
+            (false, _) => DriverProcState::Exited,
+            (true, Some(PauseTarget::Pipeline)) => DriverProcState::Paused,
+            (true, _) => DriverProcState::Running,
+        };
+
+        // updated while running, publisher is connected
+        let ingest = self
+            .started_at
+            .filter(|_| self.paused != Some(PauseTarget::Pipeline))
+            .map(|x| DriverIngestStats {
+                frame: self.frame,
+                out_time: format!("{:?}", x.elapsed()),
+                speed: 1.0,
+                updated_epoch: EchoUtc::now().timestamp(),
+                ..Default::default()
+            });
+
+        DriverStats {
+            procs: vec![DriverProcStats {
+                name: "mock_receiver".to_string(),
+                pid: None,
+                state,
+                respawn_cnt: self.respawn_cnt,
+                exit_code: None,
+                exit_desc: None,
+            }],
+            ingest,
+            paused: self.paused,
+            ..Default::default()
+        }
+    }
+
+    fn capabilities(&self) -> DriverCapabilities {
+        DriverCapabilities {
+            receiver: MediaReceiver::MOCK,
+            protocols: vec![Protocol::RTMP],
+            media_types: vec![MediaType::Audio, MediaType::Video],
+            auto_respawn: true,
+            recording: false,
+            pause: true,
+        }
+    }
+}
@@ -1,16 +1,27 @@
 // runner::mod.rs
+pub mod admission;
+pub mod cluster;
 pub mod driver;
 pub mod error;
+pub mod event_bus;
 pub mod ffmpeg;
 pub mod manager;
 pub mod message;
+pub mod mock;
+pub mod publish_auth;
+pub mod store;
+pub mod webhook;
 pub mod worker;
+use crate::comm_media::{MediaReceiver, MediaType, PropMedia, Protocol};
+use crate::service::api::reqres::publish::req::ReqPublishV3;
 use crate::service::vsvr::message::VSvrServMsgSend;
 use crate::{comm::EchoJoinHandle, config::Config};
 use crate::{
-    comm::{EchoAsyncRwLock, EchoIpStr, EchoPublishPort},
+    comm::{EchoAsyncRwLock, EchoIpStr, EchoPort, EchoPublishPort, EchoTimeInstant},
     message::ServMsgSend,
 };
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, VecDeque},
     sync::Arc,
@@ -31,8 +42,77 @@ pub const AWAIT_TERMINATE_TIMEOUT_SEC: u64 = 10000;
 pub const POLL_PERIOD_TERMINATE_MS: u64 = 1000;
 pub const TERMINATE_TIMEOUT_MS: u64 = 2000;
 
-use ffmpeg::RunnerFFMpeg;
+// manager waits messages up to this, watchdog is stamped while idle
+pub const MANAGER_IDLE_TICK_MS: u64 = 1000;
 
+// max waiting time for a recv-worker to exit after RecvWorkerMsg::FinishRecvWorker is accepted
+pub const WORKER_EXIT_TIMEOUT_SEC: u64 = 30;
+
+// max waiting time for a recv-worker to answer RecvWorkerMsg::QueryRecvWorkerState
+pub const QUERY_WORKER_STATE_TIMEOUT_MS: u64 = 3000;
+
+// expiry warnings before session is expired, seconds before expire_epoch
+// > default of ExpiryChangePrm::warn_thresholds_sec
+pub const EXPIRE_WARN_THRESHOLDS_SEC: [u64; 2] = [600, 60];
+
+// comma separated seconds, ex) '1800,600,60', overrides EXPIRE_WARN_THRESHOLDS_SEC
+pub const EXPIRE_WARN_THRESHOLDS_SEC_ENV: &str = "ECHO_EXPIRE_WARN_THRESHOLDS_SEC";
+
+// session keeps running for grace period after expire_epoch
+// > default of ExpiryChangePrm::grace_sec
+pub const EXPIRE_GRACE_SEC: u64 = 0;
+
+// total duration a session may be extended up to(ExpiryChangeOp::Extend), ex) 4 hours of vsvr coupon
+// > apart from config.echo_publish_max_duration, which is duration granted on publish(2 hours)
+pub const EXPIRE_EXTEND_CEILING_SEC: u64 = 4 * 60 * 60;
+
+// seconds, overrides EXPIRE_EXTEND_CEILING_SEC
+pub const EXPIRE_EXTEND_CEILING_SEC_ENV: &str = "ECHO_EXPIRE_EXTEND_CEILING_SEC";
+
+// pagination of session listing, SessionQueryFilter::limit
+pub const SESSION_LIST_DEFAULT_LIMIT: usize = 50;
+pub const SESSION_LIST_MAX_LIMIT: usize = 500;
+
+// respawn of non-critical pipeline stage(ex: recorder), RunnerFFMStagePolicy::auto_respawn
+pub const STAGE_RESPAWN_MAX_CNT: u32 = 10;
+pub const STAGE_RESPAWN_INTERVAL_MS: u64 = 3000;
+
+// server shutdown, ShutdownPrm::deadline_sec=0
+// > terminate: all ffmpegs should be dead within deadline
+// > drain: sessions are terminated after drain deadline
+pub const SHUTDOWN_TERMINATE_DEADLINE_SEC: u64 = 30;
+pub const SHUTDOWN_DRAIN_DEADLINE_SEC: u64 = 4 * 60 * 60;
+// recordings being finalized in background(loudness is tagged) are waited up to this
+pub const SHUTDOWN_FINALIZE_DEADLINE_SEC: u64 = 5 * 60;
+
+// skipped records of reconciliation(publish port in use) are rechecked within
+pub const SKIPPED_SESSION_CHECK_INTERVAL_SEC: u64 = 10;
+
+// publisher is connected while ingest progress of receiver is updated within idle seconds
+// > WebhookEvent::PublisherConnected, PublisherDisconnected
+pub const PUBLISHER_IDLE_SEC: i64 = 5;
+pub const PUBLISHER_CHECK_INTERVAL_MS: u64 = 1000;
+
+// hls segment latency, recording bytes of session are collected within, crate::metrics
+pub const WORKER_METRICS_INTERVAL_MS: u64 = 2000;
+
+// audio level, black/frozen video of new hls segments are measured within, ffmpeg::media_monitor
+pub const MEDIA_MONITOR_INTERVAL_MS: u64 = 5000;
+
+// new segments of receiver are listed on live playlist, ffmpeg::playlist
+pub const PLAYLIST_CHECK_INTERVAL_MS: u64 = 500;
+
+// slate segments are appended while receiver waits for publisher, ffmpeg::slate
+pub const SLATE_CHECK_INTERVAL_MS: u64 = 500;
+
+// backup receiver is watched, backup segments are appended while primary is away, ffmpeg::failover
+pub const FAILOVER_CHECK_INTERVAL_MS: u64 = 500;
+
+use ffmpeg::adts_inspect::AdtsInspectStats;
+use ffmpeg::failover::FailoverState;
+use ffmpeg::loudnorm::LoudnormPrm;
+use ffmpeg::media_monitor::{AudioLevel, VideoContent};
+use ffmpeg::stream_probe::StreamProbe;
 use message::{
     RecvWorkerManagerMsgRecv, RecvWorkerManagerMsgSend, RecvWorkerMsgRecv, RecvWorkerMsgSend,
 };
@@ -41,19 +121,51 @@ use message::{
 // driver
 //
 
-#[derive(Debug)]
-pub enum DriverType {
-    FFMPEG,
+// receiver backend of a recv-worker
+// > RecvWorker only talks to the driver through this trait,
+// > backends are created by driver::DriverRegistry keyed by MediaReceiver
+#[async_trait]
+pub trait ReceiverDriver: Send + Sync {
+    async fn begin(&mut self) -> DriverRst;
+    async fn tick(&mut self) -> DriverRst;
+    async fn end(&mut self) -> DriverRst;
+
+    // back to the state before begin(), called between end() and begin() on restart
+    async fn reset(&mut self) -> DriverRst;
+
+    async fn restart(&mut self) -> DriverRst {
+        self.end().await?;
+        self.reset().await?;
+        self.begin().await
+    }
+
+    async fn stats(&self) -> DriverStats;
+
+    fn capabilities(&self) -> DriverCapabilities;
+
+    // suspend processes of backend, DriverCapabilities::pause
+    async fn pause(&mut self, target: PauseTarget) -> DriverRst {
+        Err(RunnerError::RunnerDriverErr(format!(
+            "pause is not supported, target={}",
+            target
+        )))
+    }
+
+    async fn resume(&mut self) -> DriverRst {
+        Err(RunnerError::RunnerDriverErr(format!(
+            "resume is not supported"
+        )))
+    }
 }
 
-// pub trait Driver {
-//     fn tick(&self) -> EchoAsyncRst;
-//     fn begin(&self) -> EchoAsyncRst;
-//     fn end(&self) -> EchoAsyncRst;
-// }
+pub type ReceiverDriverBox = Box<dyn ReceiverDriver>;
+
+// (create prms) -> receiver backend
+pub type PFnNewReceiverDriver = fn(DriverCreatePrm) -> Result<ReceiverDriverBox, RunnerError>;
 
-#[derive(strum_macros::Display, Debug, Clone, PartialEq)]
-enum DriverStatus {
+#[derive(strum_macros::Display, Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum DriverStatus {
+    #[default]
     Init,
     Creating,
     ReceivingPublishStream, // ffmpeg is spawned and await connection or ffmpeg is receiving publish stream
@@ -62,9 +174,111 @@ enum DriverStatus {
     Restarting,
 }
 
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DriverStats {
+    pub status: DriverStatus,
+    pub restart_cnt: u32,
+
+    // child processes of backend, ex) ffmpeg receiver/recorder
+    pub procs: Vec<DriverProcStats>,
+
+    // latest stats of publish stream, none if backend has not reported yet
+    pub ingest: Option<DriverIngestStats>,
+
+    pub paused: Option<PauseTarget>,
+}
+
+// what is suspended by ReceiverDriver::pause
+#[derive(strum_macros::Display, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseTarget {
+    // recording only, ex) privacy break during live class, hls is still served
+    Recorder,
+    // whole pipeline, hls playlist is marked with discontinuity on resume
+    Pipeline,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionPausePrm {
+    pub target: PauseTarget,
+
+    // stop expiry clock while paused
+    #[serde(default)]
+    pub freeze_expiry: bool,
+}
+
+#[derive(strum_macros::Display, Debug, Clone, PartialEq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriverProcState {
+    #[default]
+    NotSpawned,
+    SpawnFailed,
+    Running,
+    Paused,
+    Exited,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DriverProcStats {
+    pub name: String,
+    pub pid: Option<u32>,
+    pub state: DriverProcState,
+    pub respawn_cnt: u32,
+
+    // exit result, valid if state is exited
+    pub exit_code: Option<i32>,
+    pub exit_desc: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DriverIngestStats {
+    pub frame: u64,
+    pub fps: f32,
+    pub bitrate_kbps: f32,
+    pub out_time: String,
+    pub speed: f32,
+
+    // epoch of last update
+    pub updated_epoch: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DriverCapabilities {
+    pub receiver: MediaReceiver,
+    pub protocols: Vec<Protocol>,
+    pub media_types: Vec<MediaType>,
+    pub auto_respawn: bool,
+    pub recording: bool,
+    pub pause: bool,
+}
+
+// parameters given to PFnNewReceiverDriver
+#[derive(Clone)]
+pub struct DriverCreatePrm {
+    pub config: Config,
+    pub worker_msg_send: RecvWorkerMsgSend,
+
+    // ReqPropReceiverPrm.args, given by publish request
+    pub receiver_args: String,
+    pub media: PropMedia,
+
+    pub app_name: String,
+    pub sess_key: String,
+
+    pub publish_port: EchoPort,
+
+    // PublishOverrides.loudnorm, audio is normalized by backend
+    pub loudnorm: Option<LoudnormPrm>,
+
+    // PublishOverrides.backup, backup receiver is run by RecvWorker(ffmpeg::failover)
+    pub backup_publish_port: Option<EchoPort>,
+}
+
+// status machine over a receiver backend
 pub struct Driver {
     status: DriverStatus,
-    runner_ffmpeg: Option<RunnerFFMpeg>,
+    restart_cnt: u32,
+    backend: ReceiverDriverBox,
 }
 
 #[derive(Debug, PartialEq)]
@@ -98,9 +312,34 @@ pub struct RecvWorkerSpawnPrm {}
 pub enum RecvWorkerManagerStatus {
     Creating,
     Running,
+    // shutdown is requested, new publish is rejected
+    Draining,
     Destroying,
 }
 
+//
+// server shutdown
+// > RecvWorkerManagerMsg::ShutdownRecvWorkers
+//
+
+#[derive(strum_macros::Display, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShutdownMode {
+    // finish all workers now
+    Terminate,
+    // wait for sessions to end naturally(expired, terminated by vsvr), ex) rolling deploy
+    Drain,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShutdownPrm {
+    pub mode: ShutdownMode,
+
+    // 0: SHUTDOWN_TERMINATE_DEADLINE_SEC or SHUTDOWN_DRAIN_DEADLINE_SEC
+    #[serde(default)]
+    pub deadline_sec: u64,
+}
+
 //
 // RecvWorkerHandle
 //
@@ -122,6 +361,150 @@ pub struct RecvWorkerHandle {
 
     publish_ip: String,
     publish_port: u16,
+    // PublishOverrides.backup, given back to pool with publish_port
+    backup_publish_port: Option<u16>,
+
+    // counted by admission control
+    media_type: MediaType,
+    profile: String,
+
+    // persisted to session store, recovered on restart
+    req_publish: ReqPublishV3,
+    overrides: publish_auth::PublishOverrides,
+    start_epoch: i64,
+    expire_epoch: i64,
+}
+
+//
+// RecvWorkerStateSnapshot
+// > answer of RecvWorkerMsg::QueryRecvWorkerState
+//
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecvWorkerStateSnapshot {
+    pub worker_uuid: String,
+    pub app_name: String,
+    pub sess_key: String,
+    pub publish_port: u16,
+    pub media_type: MediaType,
+
+    pub driver_status: DriverStatus,
+
+    pub start_epoch: i64,
+    pub expire_epoch: i64,
+    pub expired: bool,
+    pub expiry_paused: bool,
+
+    pub paused: Option<PauseTarget>,
+
+    // respawn count of driver by RecvWorker
+    pub respawn_cnt: i32,
+
+    pub procs: Vec<DriverProcStats>,
+    pub ingest: Option<DriverIngestStats>,
+
+    // ffprobe of first segment after publisher is connected
+    pub stream: Option<StreamProbe>,
+
+    // adts of hls segments, audio session only
+    pub audio: Option<AdtsInspectStats>,
+
+    // loudness and silence, audio session only
+    pub audio_level: Option<AudioLevel>,
+
+    // black and frozen picture, video session only
+    pub video_content: Option<VideoContent>,
+
+    // slate is appended to playlist, publisher is away
+    pub slate_on_air: bool,
+
+    // backup publisher of session, none: not accepted
+    pub failover: Option<FailoverState>,
+}
+
+//
+// session expiry
+// > RecvWorkerMsg::ChangeRecvWorkerExpiry, ex) user bought more time through vsvr
+//
+
+#[derive(strum_macros::Display, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ExpiryChangeOp {
+    Extend { sec: u64 },
+    Shorten { sec: u64 },
+    // expiry clock is stopped until resumed
+    Pause,
+    Resume,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExpiryChangePrm {
+    #[serde(flatten)]
+    pub op: ExpiryChangeOp,
+
+    // none: keep current setting
+    pub warn_thresholds_sec: Option<Vec<u64>>,
+    pub grace_sec: Option<u64>,
+}
+
+//
+// session listing/detail
+// > RecvWorkerStateSnapshot with publish info of RecvWorkerHandle
+//
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SessionQueryFilter {
+    pub state: Option<DriverStatus>,
+    pub media_type: Option<MediaType>,
+    pub app_name_prefix: Option<String>,
+
+    // sessions older than given seconds, ex) bulk termination of stale sessions
+    pub min_uptime_sec: Option<i64>,
+
+    // pagination, not applied to bulk termination
+    #[serde(default)]
+    pub offset: usize,
+    // 0: SESSION_LIST_DEFAULT_LIMIT
+    #[serde(default)]
+    pub limit: usize,
+
+    // bulk termination of every session, required when no selector is given
+    #[serde(default)]
+    pub all: bool,
+}
+
+impl SessionQueryFilter {
+    // any of state, media type, app name prefix and age is given
+    pub fn has_selector(&self) -> bool {
+        self.state.is_some()
+            || self.media_type.is_some()
+            || self.app_name_prefix.as_deref().map_or(false, |x| !x.is_empty())
+            || self.min_uptime_sec.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    #[serde(flatten)]
+    pub state: RecvWorkerStateSnapshot,
+
+    pub publish_ip: String,
+    pub publish_url: String,
+    // backup publisher, PublishOverrides.backup
+    pub backup_publish_url: Option<String>,
+
+    pub uptime_sec: i64,
+    pub remain_sec: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionList {
+    // count of sessions matched with filter, before pagination
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+
+    pub sessions: Vec<SessionInfo>,
 }
 
 //
@@ -149,4 +532,51 @@ pub struct RecvWorkerManager {
     publish_ip: EchoIpStr,
     //avail_publish_ports: Arc<RwLock<VecDeque<EchoPublishPort>>>,
     avail_publish_ports: Arc<EchoAsyncRwLock<VecDeque<EchoPublishPort>>>,
+
+    // receiver backends, keyed by MediaReceiver
+    driver_registry: driver::DriverRegistry,
+
+    // limits of new session, RecvWorkerManagerMsg::SpawnRecvWorker
+    admission_policy: admission::AdmissionPolicy,
+
+    // running sessions, none: not persisted(ECHO_SESSION_STORE=none)
+    session_store: Option<store::SessionStoreWriter>,
+    // records of which publish port was listened by other process on reconciliation
+    // > rechecked until recovered or expired, their ports are kept out of pool meanwhile
+    skipped_sessions: Vec<store::SessionRecord>,
+    skipped_sessions_checked_at: EchoTimeInstant,
+    // ports of dropped skipped sessions still listened by other process
+    // > given back to pool once closed, rechecked with skipped_sessions
+    releasing_ports: Vec<EchoPublishPort>,
+
+    // ownership of app_name among nodes, none: standalone(ECHO_CLUSTER_REDIS unset)
+    cluster: Option<cluster::ClusterRegistryShared>,
+
+    // answered on RecvWorkerManagerMsg::NotifyShutdownCompleted
+    shutdown_responders: Vec<message::ShutdownRecvWorkersMsgOCResponder>,
+    // draining is cut short by ShutdownMode::Terminate
+    shutdown_terminate_notify: Arc<tokio::sync::Notify>,
+
+    // (app_name, task) of recordings being finalized in background, awaited on shutdown
+    finalize_tasks: Vec<(String, tokio::task::JoinHandle<()>)>,
+
+    // running workers and ports are left here when manager is dropped(ex: panicked)
+    handover: RecvWorkerManagerHandoverSlot,
+    // created from handover, sessions of previous manager are running
+    adopted: bool,
+}
+
+// state of dropped manager taken by respawned one, main::supervise
+// > workers keep sending to same channel, ports of running sessions stay leased
+pub struct RecvWorkerManagerHandover {
+    msg_received: RecvWorkerManagerMsgRecv,
+    msg_send: RecvWorkerManagerMsgSend,
+    worker_handles: HashMap<String, Arc<EchoAsyncRwLock<RecvWorkerHandle>>>,
+    avail_publish_ports: Arc<EchoAsyncRwLock<VecDeque<EchoPublishPort>>>,
+    cluster: Option<cluster::ClusterRegistryShared>,
+    finalize_tasks: Vec<(String, tokio::task::JoinHandle<()>)>,
+    skipped_sessions: Vec<store::SessionRecord>,
+    releasing_ports: Vec<EchoPublishPort>,
 }
+
+pub type RecvWorkerManagerHandoverSlot = Arc<std::sync::Mutex<Option<RecvWorkerManagerHandover>>>;
@@ -0,0 +1,245 @@
+// runner::publish_auth
+// > on_publish authorization, checked before a publish port is leased
+use crate::comm::EchoArc;
+use crate::comm_media::MediaType;
+use crate::runner::ffmpeg::loudnorm::LoudnormPrm;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// "http(s)://{callback}" or "file:{rules path}", unset: every publish is allowed
+// > callback: POST PublishAuthRequest, answered by PublishDecision
+// > rules file: json array of PublishRule, first matching rule is applied
+pub const ON_PUBLISH_ENV: &str = "ECHO_ON_PUBLISH";
+
+// callback is not answered within, publish is denied
+pub const ON_PUBLISH_TIMEOUT_MS: u64 = 3000;
+
+//
+// request, decision
+//
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishAuthRequest {
+    pub app_name: String,
+    pub sess_key: String,
+    pub media_type: MediaType,
+    // receiver args of publish request
+    pub profile: String,
+    // none: unknown(ex: not relayed by api)
+    pub client_ip: Option<String>,
+    // this node
+    pub publish_ip: String,
+}
+
+// replace global config for a session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PublishOverrides {
+    // config.echo_publish_max_duration
+    pub max_duration_sec: Option<u64>,
+    // config.echo_rec_enabled
+    pub rec_enabled: Option<bool>,
+    // receiver args(transcoding profile) of publish request
+    pub profile: Option<String>,
+    // audio is normalized(EBU R128), none: as published
+    pub loudnorm: Option<LoudnormPrm>,
+    // second publish port is leased for backup publisher, ffmpeg::failover
+    pub backup: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishDecision {
+    pub allow: bool,
+
+    // why publish is denied
+    #[serde(default)]
+    pub reason: Option<String>,
+
+    #[serde(default)]
+    pub overrides: PublishOverrides,
+}
+
+impl PublishDecision {
+    pub fn allowed() -> Self {
+        Self {
+            allow: true,
+            reason: None,
+            overrides: PublishOverrides::default(),
+        }
+    }
+
+    pub fn denied(reason: String) -> Self {
+        Self {
+            allow: false,
+            reason: Some(reason),
+            overrides: PublishOverrides::default(),
+        }
+    }
+}
+
+//
+// rules file
+//
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PublishRule {
+    // empty: any app_name
+    #[serde(default)]
+    pub app_name_prefix: String,
+
+    // empty: any client, ex) ["10.0.", "192.168.1.7"]
+    #[serde(default)]
+    pub client_ip_prefixes: Vec<String>,
+
+    // empty: any media type
+    #[serde(default)]
+    pub media_types: Vec<MediaType>,
+
+    pub allow: bool,
+
+    #[serde(default)]
+    pub reason: Option<String>,
+
+    #[serde(default)]
+    pub overrides: PublishOverrides,
+}
+
+impl PublishRule {
+    fn matches(&self, req: &PublishAuthRequest) -> bool {
+        if !req.app_name.starts_with(self.app_name_prefix.as_str()) {
+            return false;
+        }
+
+        if !self.client_ip_prefixes.is_empty() {
+            let client_ip = match req.client_ip.as_ref() {
+                Some(x) => x,
+                None => return false,
+            };
+
+            if !self
+                .client_ip_prefixes
+                .iter()
+                .any(|x| client_ip.starts_with(x.as_str()))
+            {
+                return false;
+            }
+        }
+
+        self.media_types.is_empty() || self.media_types.contains(&req.media_type)
+    }
+}
+
+//
+// authorizer
+//
+
+pub enum PublishAuthorizer {
+    AllowAll,
+    Callback {
+        url: String,
+        client: reqwest::Client,
+    },
+    // read on every publish, edits are applied without restart
+    RulesFile {
+        path: PathBuf,
+    },
+}
+
+pub type PublishAuthorizerShared = EchoArc<PublishAuthorizer>;
+
+impl PublishAuthorizer {
+    // by ON_PUBLISH_ENV
+    pub fn from_env() -> Self {
+        let spec = match std::env::var(ON_PUBLISH_ENV) {
+            Ok(x) if !x.is_empty() => x,
+            _ => return Self::AllowAll,
+        };
+
+        if let Some(path) = spec.strip_prefix("file:") {
+            return Self::RulesFile {
+                path: PathBuf::from(path),
+            };
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(ON_PUBLISH_TIMEOUT_MS))
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+
+        Self::Callback { url: spec, client }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::AllowAll => "allow_all",
+            Self::Callback { .. } => "callback",
+            Self::RulesFile { .. } => "rules_file",
+        }
+    }
+
+    // failure of callback or rules file denies publish
+    pub async fn authorize(&self, req: &PublishAuthRequest) -> PublishDecision {
+        let decision = match self {
+            Self::AllowAll => return PublishDecision::allowed(),
+            Self::Callback { url, client } => authorize_by_callback(client, url, req).await,
+            Self::RulesFile { path } => authorize_by_rules_file(path, req).await,
+        };
+
+        decision.unwrap_or_else(|e| {
+            log::error!(
+                "[PublishAuthorizer::authorize] authorization failed, publish is denied, \
+                app_name={}, authorizer={}, e={}",
+                req.app_name,
+                self.name(),
+                e
+            );
+
+            PublishDecision::denied(format!("authorization failed, {}", e))
+        })
+    }
+}
+
+async fn authorize_by_callback(
+    client: &reqwest::Client,
+    url: &str,
+    req: &PublishAuthRequest,
+) -> Result<PublishDecision, String> {
+    let resp = client
+        .post(url)
+        .json(req)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !resp.status().is_success() {
+        return Err(format!("status={}", resp.status()));
+    }
+
+    resp.json::<PublishDecision>()
+        .await
+        .map_err(|e| format!("invalid decision, e={}", e))
+}
+
+async fn authorize_by_rules_file(
+    path: &PathBuf,
+    req: &PublishAuthRequest,
+) -> Result<PublishDecision, String> {
+    let buf = tokio::fs::read(path)
+        .await
+        .map_err(|e| format!("failed to read rules, path={:?}, e={}", path, e))?;
+
+    let rules: Vec<PublishRule> = serde_json::from_slice(&buf)
+        .map_err(|e| format!("invalid rules, path={:?}, e={}", path, e))?;
+
+    let rule = match rules.into_iter().find(|x| x.matches(req)) {
+        Some(x) => x,
+        None => return Ok(PublishDecision::denied(format!("no matching rule"))),
+    };
+
+    Ok(PublishDecision {
+        allow: rule.allow,
+        reason: rule.reason,
+        overrides: rule.overrides,
+    })
+}
@@ -0,0 +1,581 @@
+// runner::store
+// > durable records of running sessions, reconciled on startup of RecvWorkerManager
+use super::RunnerError;
+use crate::comm::{EchoArc, EchoAsyncMutex};
+use crate::runner::ffmpeg::recorder::RecFileStatus;
+use crate::runner::publish_auth::PublishOverrides;
+use crate::service::api::reqres::publish::req::ReqPublishV3;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+// "file:{path}", "redis://{host}:{port}/{db}" or "none"
+pub const SESSION_STORE_ENV: &str = "ECHO_SESSION_STORE";
+pub const SESSION_STORE_DEFAULT_FILE: &str = "echo_sessions.json";
+
+// hash of redis, field: app_name, value: SessionRecord
+// > {store_key} is publish ip, sessions of other hosts are not touched
+pub const SESSION_STORE_REDIS_KEY_PREFIX: &str = "echo:sessions";
+// connect, read and write of redis, unreachable redis fails the op instead of hanging
+pub const SESSION_STORE_REDIS_TIMEOUT_MS: u64 = 2000;
+
+//
+// record
+//
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub worker_uuid: String,
+    pub app_name: String,
+    pub sess_key: String,
+    pub publish_port: u16,
+    #[serde(default)]
+    pub backup_publish_port: Option<u16>,
+
+    pub start_epoch: i64,
+    pub expire_epoch: i64,
+
+    // receiver is respawned with same request on recovery
+    pub req_publish: ReqPublishV3,
+
+    // on_publish authorization is not asked again on recovery
+    #[serde(default)]
+    pub overrides: PublishOverrides,
+}
+
+//
+// store
+//
+
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // insert or replace, keyed by app_name
+    async fn put(&self, rec: SessionRecord) -> Result<(), RunnerError>;
+    async fn remove(&self, app_name: &str) -> Result<(), RunnerError>;
+    async fn load(&self) -> Result<Vec<SessionRecord>, RunnerError>;
+}
+
+pub type SessionStoreShared = EchoArc<dyn SessionStore>;
+
+//
+// writer
+// > put/remove of manager are queued and applied in order on separated task,
+//   manager loop is not blocked by slow store, failure is logged only
+//
+
+enum SessionStoreOp {
+    Put(SessionRecord),
+    Remove(String),
+}
+
+pub struct SessionStoreWriter {
+    store: SessionStoreShared,
+    op_send: mpsc::UnboundedSender<SessionStoreOp>,
+}
+
+impl SessionStoreWriter {
+    // task ends once writer is dropped and queued ops are applied
+    pub fn spawn(store: SessionStoreShared) -> Self {
+        let (op_send, op_recv) = mpsc::unbounded_channel();
+
+        tokio::spawn(run_session_store_writer(store.clone(), op_recv));
+
+        Self { store, op_send }
+    }
+
+    // read of store(load on reconciliation) is awaited by caller
+    pub fn store(&self) -> &SessionStoreShared {
+        &self.store
+    }
+
+    pub fn put(&self, rec: SessionRecord) {
+        let _ = self.op_send.send(SessionStoreOp::Put(rec));
+    }
+
+    pub fn remove(&self, app_name: &str) {
+        let _ = self
+            .op_send
+            .send(SessionStoreOp::Remove(app_name.to_string()));
+    }
+}
+
+async fn run_session_store_writer(
+    store: SessionStoreShared,
+    mut op_recv: mpsc::UnboundedReceiver<SessionStoreOp>,
+) {
+    while let Some(op) = op_recv.recv().await {
+        let (app_name, rst) = match op {
+            SessionStoreOp::Put(rec) => (rec.app_name.clone(), store.put(rec).await),
+            SessionStoreOp::Remove(app_name) => {
+                let rst = store.remove(&app_name).await;
+                (app_name, rst)
+            }
+        };
+
+        if let Err(e) = rst {
+            log::error!(
+                "[SessionStoreWriter] failed to write session, app_name={}, store={}, e={}",
+                app_name,
+                store.name(),
+                e.to_string()
+            );
+        }
+    }
+}
+
+// by SESSION_STORE_ENV, default is file store on working directory
+pub fn new_session_store(store_key: &str) -> Option<SessionStoreShared> {
+    let spec =
+        std::env::var(SESSION_STORE_ENV).unwrap_or(format!("file:{}", SESSION_STORE_DEFAULT_FILE));
+
+    if spec == "none" {
+        return None;
+    }
+
+    if spec.starts_with("redis://") || spec.starts_with("rediss://") {
+        return match RedisSessionStore::new(&spec, store_key) {
+            Ok(x) => Some(EchoArc::new(x)),
+            Err(e) => {
+                log::error!(
+                    "[SessionStore] failed to create redis session store, sessions are not persisted, e={}",
+                    e.to_string()
+                );
+                None
+            }
+        };
+    }
+
+    let path = spec.strip_prefix("file:").unwrap_or(spec.as_str());
+
+    Some(EchoArc::new(FileSessionStore::new(PathBuf::from(path))))
+}
+
+//
+// file store
+// > whole records are rewritten on every change(tmp file and rename), sessions are few
+//
+
+pub struct FileSessionStore {
+    path: PathBuf,
+
+    // none: not loaded yet
+    records: EchoAsyncMutex<Option<HashMap<String, SessionRecord>>>,
+}
+
+impl FileSessionStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            records: EchoAsyncMutex::new(None),
+        }
+    }
+
+    async fn read_file(&self) -> Result<HashMap<String, SessionRecord>, RunnerError> {
+        match tokio::fs::read(&self.path).await {
+            Ok(buf) => serde_json::from_slice(&buf).map_err(|e| {
+                RunnerError::SessionStoreErr(format!(
+                    "invalid session file, path={:?}, e={}",
+                    self.path,
+                    e.to_string()
+                ))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(RunnerError::SessionStoreErr(format!(
+                "failed to read session file, path={:?}, e={}",
+                self.path,
+                e.to_string()
+            ))),
+        }
+    }
+
+    async fn write_file(
+        &self,
+        records: &HashMap<String, SessionRecord>,
+    ) -> Result<(), RunnerError> {
+        let buf = serde_json::to_vec_pretty(records)
+            .map_err(|e| RunnerError::SessionStoreErr(e.to_string()))?;
+
+        let tmp_path = self.path.with_extension("tmp");
+
+        tokio::fs::write(&tmp_path, buf).await.map_err(|e| {
+            RunnerError::SessionStoreErr(format!(
+                "failed to write session file, path={:?}, e={}",
+                tmp_path,
+                e.to_string()
+            ))
+        })?;
+
+        tokio::fs::rename(&tmp_path, &self.path).await.map_err(|e| {
+            RunnerError::SessionStoreErr(format!(
+                "failed to rename session file, path={:?}, e={}",
+                self.path,
+                e.to_string()
+            ))
+        })
+    }
+
+    // apply change to records, and rewrite file
+    async fn update<F>(&self, change: F) -> Result<(), RunnerError>
+    where
+        F: FnOnce(&mut HashMap<String, SessionRecord>),
+    {
+        let mut records = self.records.lock().await;
+
+        if records.is_none() {
+            *records = Some(self.read_file().await?);
+        }
+
+        let records = records.as_mut().unwrap();
+
+        change(records);
+
+        self.write_file(records).await
+    }
+}
+
+#[async_trait]
+impl SessionStore for FileSessionStore {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    async fn put(&self, rec: SessionRecord) -> Result<(), RunnerError> {
+        self.update(|records| {
+            records.insert(rec.app_name.clone(), rec);
+        })
+        .await
+    }
+
+    async fn remove(&self, app_name: &str) -> Result<(), RunnerError> {
+        self.update(|records| {
+            records.remove(app_name);
+        })
+        .await
+    }
+
+    async fn load(&self) -> Result<Vec<SessionRecord>, RunnerError> {
+        let mut records = self.records.lock().await;
+
+        let loaded = self.read_file().await?;
+        let rst = loaded.values().cloned().collect();
+
+        *records = Some(loaded);
+
+        Ok(rst)
+    }
+}
+
+//
+// redis store
+// > blocking connection on blocking thread pool, bounded by SESSION_STORE_REDIS_TIMEOUT_MS
+//
+
+pub struct RedisSessionStore {
+    client: redis::Client,
+    key: String,
+}
+
+impl RedisSessionStore {
+    pub fn new(url: &str, store_key: &str) -> Result<Self, RunnerError> {
+        let client = redis::Client::open(url)
+            .map_err(|e| RunnerError::SessionStoreErr(format!("url={}, e={}", url, e)))?;
+
+        Ok(Self {
+            client,
+            key: format!("{}:{}", SESSION_STORE_REDIS_KEY_PREFIX, store_key),
+        })
+    }
+
+    async fn query<T, F>(&self, f: F) -> Result<T, RunnerError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut redis::Connection, &str) -> redis::RedisResult<T> + Send + 'static,
+    {
+        let client = self.client.clone();
+        let key = self.key.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let timeout = Duration::from_millis(SESSION_STORE_REDIS_TIMEOUT_MS);
+
+            let mut conn = client.get_connection_with_timeout(timeout)?;
+            conn.set_read_timeout(Some(timeout))?;
+            conn.set_write_timeout(Some(timeout))?;
+
+            f(&mut conn, &key)
+        })
+        .await
+        .map_err(|e| RunnerError::SessionStoreErr(e.to_string()))?
+        .map_err(|e| RunnerError::SessionStoreErr(format!("key={}, e={}", self.key, e)))
+    }
+}
+
+#[async_trait]
+impl SessionStore for RedisSessionStore {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn put(&self, rec: SessionRecord) -> Result<(), RunnerError> {
+        let rec_json =
+            serde_json::to_string(&rec).map_err(|e| RunnerError::SessionStoreErr(e.to_string()))?;
+
+        self.query(move |conn, key| {
+            redis::cmd("HSET")
+                .arg(key)
+                .arg(rec.app_name)
+                .arg(rec_json)
+                .query(conn)
+        })
+        .await
+    }
+
+    async fn remove(&self, app_name: &str) -> Result<(), RunnerError> {
+        let app_name = app_name.to_string();
+
+        self.query(move |conn, key| redis::cmd("HDEL").arg(key).arg(app_name).query(conn))
+            .await
+    }
+
+    async fn load(&self) -> Result<Vec<SessionRecord>, RunnerError> {
+        let records: HashMap<String, String> = self
+            .query(|conn, key| redis::cmd("HGETALL").arg(key).query(conn))
+            .await?;
+
+        Ok(records
+            .into_iter()
+            .filter_map(|(app_name, rec_json)| {
+                serde_json::from_str(&rec_json)
+                    .map_err(|e| {
+                        log::error!(
+                            "[RedisSessionStore::load] invalid record, app_name={}, e={}",
+                            app_name,
+                            e.to_string()
+                        )
+                    })
+                    .ok()
+            })
+            .collect())
+    }
+}
+
+//
+// finalization of recordings, playlist
+//
+
+// ('{name}', '.{ext}') of recording file name
+fn split_record_file_name(file_name: &str) -> (String, String) {
+    match file_name.split_once('.') {
+        Some((s, e)) => (s.to_string(), format!(".{}", e)),
+        None => (file_name.to_string(), String::new()),
+    }
+}
+
+// '{name}_{from}[.ext]' -> '{name}_{status}[.ext]', none: status of file name is not 'from'
+fn change_record_file_status(
+    file_name: &str,
+    from: RecFileStatus,
+    status: RecFileStatus,
+) -> Option<String> {
+    let (stem, ext) = split_record_file_name(file_name);
+
+    let stem = stem.strip_suffix(format!("_{}", from).as_str())?;
+
+    Some(format!("{}_{}{}", stem, status, ext))
+}
+
+// '{name}_{status}[.ext]' in recording directory of session
+pub async fn list_record_files(rec_out_path: &str, status: RecFileStatus) -> Vec<String> {
+    let suffix = format!("_{}", status);
+    let mut files = Vec::new();
+
+    let mut entries = match tokio::fs::read_dir(rec_out_path).await {
+        Ok(x) => x,
+        Err(_) => return files,
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+
+        let (stem, _) = split_record_file_name(&file_name);
+
+        if stem.ends_with(suffix.as_str()) {
+            files.push(file_name);
+        }
+    }
+
+    files
+}
+
+// '{name}_NEW[.ext]' -> '{name}_FIN[.ext]', renamed file name is returned
+pub async fn finalize_record_file(
+    rec_out_path: &str,
+    file_name: &str,
+) -> Result<String, RunnerError> {
+    let fin_file_name =
+        change_record_file_status(file_name, RecFileStatus::NEW, RecFileStatus::FIN).ok_or_else(
+            || {
+                RunnerError::RunnerOperErr(format!(
+                    "recording file is not on recording, file={}",
+                    file_name
+                ))
+            },
+        )?;
+
+    let path = PathBuf::from(rec_out_path);
+
+    tokio::fs::rename(path.join(file_name), path.join(&fin_file_name))
+        .await
+        .map_err(|e| {
+            RunnerError::RunnerOperErr(format!(
+                "failed to rename recording file, file={}, e={}",
+                file_name,
+                e.to_string()
+            ))
+        })?;
+
+    Ok(fin_file_name)
+}
+
+// '{name}_NEW[.ext]' -> '{name}_FIN[.ext]' in recording directory of session
+// > renamed file names are returned
+pub async fn finalize_record_files(rec_out_path: &str) -> Vec<String> {
+    let mut finalized = Vec::new();
+
+    for file_name in list_record_files(rec_out_path, RecFileStatus::NEW).await {
+        match finalize_record_file(rec_out_path, &file_name).await {
+            Ok(x) => finalized.push(x),
+            Err(e) => log::error!("[store::finalize_record_files] {}", e.to_string()),
+        }
+    }
+
+    finalized
+}
+
+// '{name}_FIN[.ext]' -> '{name}_ARC[.ext]', reported by archiver(ex: s3 uploader)
+// > renamed file name is returned
+pub async fn archive_record_file(
+    rec_out_path: &str,
+    file_name: &str,
+) -> Result<String, RunnerError> {
+    if file_name.is_empty() || file_name.contains('/') || file_name.starts_with('.') {
+        return Err(RunnerError::RunnerOperErr(format!(
+            "invalid recording file name, file={}",
+            file_name
+        )));
+    }
+
+    let fin_suffix = format!("_{}", RecFileStatus::FIN);
+    let arc_suffix = format!("_{}", RecFileStatus::ARC);
+
+    let (stem, ext) = split_record_file_name(file_name);
+
+    let stem = stem.strip_suffix(fin_suffix.as_str()).ok_or_else(|| {
+        RunnerError::RunnerOperErr(format!(
+            "recording file is not finalized, file={}",
+            file_name
+        ))
+    })?;
+
+    let arc_file_name = format!("{}{}{}", stem, arc_suffix, ext);
+
+    let path = PathBuf::from(rec_out_path);
+
+    tokio::fs::rename(path.join(file_name), path.join(&arc_file_name))
+        .await
+        .map_err(|e| {
+            RunnerError::RunnerOperErr(format!(
+                "failed to rename recording file, file={}, e={}",
+                file_name,
+                e.to_string()
+            ))
+        })?;
+
+    Ok(arc_file_name)
+}
+
+// append '#EXT-X-ENDLIST', players stop waiting for next segment
+// > false: playlist does not exist, or already ended
+pub async fn end_hls_playlist(playlist_path: &str) -> bool {
+    let playlist = match tokio::fs::read_to_string(playlist_path).await {
+        Ok(x) => x,
+        Err(_) => return false,
+    };
+
+    if playlist.lines().any(|x| x.trim() == "#EXT-X-ENDLIST") {
+        return false;
+    }
+
+    let mut playlist = playlist;
+
+    if !playlist.ends_with('\n') {
+        playlist.push('\n');
+    }
+
+    playlist.push_str("#EXT-X-ENDLIST\n");
+
+    if let Err(e) = tokio::fs::write(playlist_path, playlist).await {
+        log::error!(
+            "[store::end_hls_playlist] failed to write playlist, path={}, e={}",
+            playlist_path,
+            e.to_string()
+        );
+
+        return false;
+    }
+
+    true
+}
+
+// total size of '{name}_{status}[.ext]' in recording directory of session
+pub async fn record_files_bytes(rec_out_path: &str, status: RecFileStatus) -> u64 {
+    let suffix = format!("_{}", status);
+    let mut bytes = 0;
+
+    let mut entries = match tokio::fs::read_dir(rec_out_path).await {
+        Ok(x) => x,
+        Err(_) => return 0,
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let (stem, _) = split_record_file_name(&file_name);
+
+        if !stem.ends_with(suffix.as_str()) {
+            continue;
+        }
+
+        if let Ok(metadata) = entry.metadata().await {
+            bytes += metadata.len();
+        }
+    }
+
+    bytes
+}
+
+// count of '{name}_{status}[.ext]' in recording directory of session
+pub async fn count_record_files(rec_out_path: &str, status: RecFileStatus) -> usize {
+    let suffix = format!("_{}", status);
+    let mut cnt = 0;
+
+    let mut entries = match tokio::fs::read_dir(rec_out_path).await {
+        Ok(x) => x,
+        Err(_) => return 0,
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+
+        let (stem, _) = split_record_file_name(&file_name);
+
+        if stem.ends_with(suffix.as_str()) {
+            cnt += 1;
+        }
+    }
+
+    cnt
+}
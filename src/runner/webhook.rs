@@ -0,0 +1,517 @@
+// runner::webhook
+// > outgoing webhooks of session lifecycle events, signed, retried and kept in outbox until delivered
+use crate::comm::{EchoUUID_new, EchoUtc, PrmJson};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+// json array of WebhookTarget, unset: no webhook
+// > ex) [{"url":"https://hook.example.com/echo","events":["session_created","session_exit"],"secret":"s3cr3t"}]
+pub const WEBHOOKS_ENV: &str = "ECHO_WEBHOOKS";
+// pending deliveries, redelivered after restart
+pub const WEBHOOK_OUTBOX_ENV: &str = "ECHO_WEBHOOK_OUTBOX";
+pub const WEBHOOK_OUTBOX_DEFAULT_FILE: &str = "echo_webhook_outbox.json";
+
+pub const WEBHOOK_EVENT_HEADER: &str = "X-Echo-Event";
+pub const WEBHOOK_DELIVERY_HEADER: &str = "X-Echo-Delivery";
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "X-Echo-Timestamp";
+// "sha256={hex}", hmac-sha256 of "{timestamp}.{body}" by secret of target
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Echo-Signature";
+
+pub const WEBHOOK_REQUEST_TIMEOUT_MS: u64 = 5000;
+
+// backoff of retry, base * 2^(attempts-1) up to max
+pub const WEBHOOK_RETRY_BASE_MS: i64 = 1000;
+pub const WEBHOOK_RETRY_MAX_MS: i64 = 5 * 60 * 1000;
+// delivery is dropped after
+pub const WEBHOOK_MAX_ATTEMPTS: u32 = 12;
+
+// oldest deliveries are dropped beyond
+pub const WEBHOOK_OUTBOX_MAX: usize = 10000;
+pub const WEBHOOK_MAX_INFLIGHT: usize = 16;
+
+//
+// event, target
+//
+
+#[derive(
+    strum_macros::Display, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum WebhookEvent {
+    SessionCreated,
+    PublisherConnected,
+    PublisherDisconnected,
+    // receiver pipeline of session is restarted
+    Respawned,
+    SessionExpireWarning,
+    SessionExpired,
+    SessionExit,
+    // recording files are finalized(_NEW -> _FIN)
+    RecordingFinished,
+    // recording file is stored on permanent storage(_FIN -> _ARC)
+    RecordingArchived,
+    // probed stream differs from declared media of publish request
+    StreamMismatch,
+    // silence lasted ECHO_SILENCE_END_SESSION_MIN, session is ended
+    SilenceTimeout,
+    // publisher did not reconnect within slate grace period, session is ended
+    ReconnectTimeout,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookTarget {
+    pub url: String,
+
+    // empty: all events
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+
+    // none: unsigned
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+impl WebhookTarget {
+    fn accepts(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+// a payload to a target, secret is not kept in outbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub url: String,
+    pub event: WebhookEvent,
+
+    // signed as is
+    pub body: String,
+
+    pub attempts: u32,
+    // epoch ms
+    pub next_attempt_ms: i64,
+}
+
+//
+// notification
+//
+
+enum WebhookMsg {
+    // (event, app_name, worker_uuid, data)
+    Notify(WebhookEvent, String, String, PrmJson),
+    // answered after queued events are written to outbox
+    Flush(oneshot::Sender<()>),
+}
+
+static WEBHOOK_MSG_SEND: OnceLock<mpsc::UnboundedSender<WebhookMsg>> = OnceLock::new();
+
+// start dispatcher by WEBHOOKS_ENV, none: no target
+pub fn start() -> Option<JoinHandle<()>> {
+    let targets = load_targets();
+
+    if targets.is_empty() {
+        return None;
+    }
+
+    let outbox_path = PathBuf::from(
+        std::env::var(WEBHOOK_OUTBOX_ENV).unwrap_or(WEBHOOK_OUTBOX_DEFAULT_FILE.to_string()),
+    );
+
+    let (msg_send, msg_recv) = mpsc::unbounded_channel();
+
+    if WEBHOOK_MSG_SEND.set(msg_send).is_err() {
+        log::error!("[webhook::start] webhook dispatcher is already started");
+        return None;
+    }
+
+    log::info!(
+        "[webhook::start] starting webhook dispatcher, targets={:?}, outbox={:?}",
+        targets.iter().map(|x| x.url.as_str()).collect::<Vec<_>>(),
+        outbox_path
+    );
+
+    Some(tokio::spawn(
+        WebhookDispatcher::new(targets, outbox_path).run(msg_recv),
+    ))
+}
+
+// queue event for targets, no-op without dispatcher
+pub fn notify(event: WebhookEvent, app_name: &str, worker_uuid: &str, data: PrmJson) {
+    if let Some(msg_send) = WEBHOOK_MSG_SEND.get() {
+        let _ = msg_send.send(WebhookMsg::Notify(
+            event,
+            app_name.to_string(),
+            worker_uuid.to_string(),
+            data,
+        ));
+    }
+}
+
+// await events queued so far are persisted to outbox, on shutdown
+pub async fn flush(timeout: Duration) -> bool {
+    let msg_send = match WEBHOOK_MSG_SEND.get() {
+        Some(x) => x,
+        None => return true,
+    };
+
+    let (responder, rst) = oneshot::channel();
+
+    if msg_send.send(WebhookMsg::Flush(responder)).is_err() {
+        return false;
+    }
+
+    matches!(tokio::time::timeout(timeout, rst).await, Ok(Ok(_)))
+}
+
+fn load_targets() -> Vec<WebhookTarget> {
+    let targets_json = match std::env::var(WEBHOOKS_ENV) {
+        Ok(x) => x,
+        Err(_) => return Vec::new(),
+    };
+
+    serde_json::from_str(&targets_json).unwrap_or_else(|e| {
+        log::error!(
+            "[webhook::load_targets] invalid {}, webhooks are disabled, e={}",
+            WEBHOOKS_ENV,
+            e.to_string()
+        );
+
+        Vec::new()
+    })
+}
+
+// hex of hmac-sha256("{timestamp}.{body}")
+pub fn sign(secret: &str, timestamp: &str, body: &str) -> String {
+    // hmac accepts key of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect()
+}
+
+fn retry_backoff_ms(attempts: u32) -> i64 {
+    let exp = attempts.saturating_sub(1).min(20);
+
+    (WEBHOOK_RETRY_BASE_MS << exp).min(WEBHOOK_RETRY_MAX_MS)
+}
+
+//
+// dispatcher
+//
+
+struct WebhookDispatcher {
+    targets: Vec<WebhookTarget>,
+    client: reqwest::Client,
+
+    outbox_path: PathBuf,
+    outbox: Vec<WebhookDelivery>,
+
+    // delivery ids being sent
+    inflight: HashSet<String>,
+}
+
+impl WebhookDispatcher {
+    fn new(targets: Vec<WebhookTarget>, outbox_path: PathBuf) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(WEBHOOK_REQUEST_TIMEOUT_MS))
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+
+        Self {
+            targets,
+            client,
+            outbox_path,
+            outbox: Vec::new(),
+            inflight: HashSet::new(),
+        }
+    }
+
+    async fn run(mut self, mut msg_recv: mpsc::UnboundedReceiver<WebhookMsg>) {
+        self.outbox = self.read_outbox().await;
+
+        if !self.outbox.is_empty() {
+            log::info!(
+                "[WebhookDispatcher::run] redelivering outbox, count={}",
+                self.outbox.len()
+            );
+        }
+
+        // (delivery id, result)
+        let (done_send, mut done_recv) = mpsc::unbounded_channel::<(String, Result<(), String>)>();
+
+        loop {
+            self.dispatch_due(&done_send);
+
+            tokio::select! {
+                msg = msg_recv.recv() => match msg {
+                    Some(WebhookMsg::Notify(event, app_name, worker_uuid, data)) => {
+                        self.enqueue(event, &app_name, &worker_uuid, data);
+                        self.write_outbox().await;
+                    }
+                    Some(WebhookMsg::Flush(responder)) => {
+                        self.write_outbox().await;
+                        let _ = responder.send(());
+                    }
+                    None => break,
+                },
+
+                Some((id, rst)) = done_recv.recv() => {
+                    self.on_delivered(&id, rst);
+                    self.write_outbox().await;
+                }
+
+                _ = tokio::time::sleep(self.next_wait()) => {}
+            }
+        }
+
+        self.write_outbox().await;
+    }
+
+    fn enqueue(&mut self, event: WebhookEvent, app_name: &str, worker_uuid: &str, data: PrmJson) {
+        let now_ms = EchoUtc::now().timestamp_millis();
+
+        for target in self.targets.iter().filter(|x| x.accepts(event)) {
+            let id = EchoUUID_new().to_string();
+
+            let body = json!({
+                "id" : id,
+                "event" : event,
+                "app_name" : app_name,
+                "worker_uuid" : worker_uuid,
+                "epoch" : now_ms / 1000,
+                "data" : data,
+            })
+            .to_string();
+
+            self.outbox.push(WebhookDelivery {
+                id,
+                url: target.url.clone(),
+                event,
+                body,
+                attempts: 0,
+                next_attempt_ms: now_ms,
+            });
+        }
+
+        if self.outbox.len() > WEBHOOK_OUTBOX_MAX {
+            let dropped = self.outbox.len() - WEBHOOK_OUTBOX_MAX;
+
+            log::error!(
+                "[WebhookDispatcher::enqueue] outbox is full, dropping oldest deliveries, count={}",
+                dropped
+            );
+
+            self.outbox.drain(..dropped);
+        }
+    }
+
+    // send deliveries which are due, each on its own task
+    fn dispatch_due(&mut self, done_send: &mpsc::UnboundedSender<(String, Result<(), String>)>) {
+        let now_ms = EchoUtc::now().timestamp_millis();
+
+        // target is removed from config since queued
+        let targets = &self.targets;
+        self.outbox
+            .retain(|x| targets.iter().any(|t| t.url == x.url && t.accepts(x.event)));
+
+        for delivery in self.outbox.iter() {
+            if self.inflight.len() >= WEBHOOK_MAX_INFLIGHT {
+                break;
+            }
+
+            if delivery.next_attempt_ms > now_ms || self.inflight.contains(&delivery.id) {
+                continue;
+            }
+
+            let secret = self
+                .targets
+                .iter()
+                .find(|x| x.url == delivery.url)
+                .and_then(|x| x.secret.clone());
+
+            self.inflight.insert(delivery.id.clone());
+
+            let client = self.client.clone();
+            let delivery = delivery.clone();
+            let done_send = done_send.clone();
+
+            tokio::spawn(async move {
+                let rst = send_webhook(client, &delivery, secret).await;
+                let _ = done_send.send((delivery.id, rst));
+            });
+        }
+    }
+
+    fn on_delivered(&mut self, id: &str, rst: Result<(), String>) {
+        self.inflight.remove(id);
+
+        // dropped by outbox limit meanwhile
+        let idx = match self.outbox.iter().position(|x| x.id == id) {
+            Some(x) => x,
+            None => return,
+        };
+
+        let e = match rst {
+            Ok(_) => {
+                let delivery = self.outbox.remove(idx);
+
+                log::debug!(
+                    "[WebhookDispatcher] delivered, event={}, url={}, id={}",
+                    delivery.event,
+                    delivery.url,
+                    delivery.id
+                );
+                return;
+            }
+            Err(e) => e,
+        };
+
+        let delivery = &mut self.outbox[idx];
+        delivery.attempts += 1;
+
+        if delivery.attempts >= WEBHOOK_MAX_ATTEMPTS {
+            log::error!(
+                "[WebhookDispatcher] dropping delivery, too many attempts, event={}, url={}, \
+                id={}, attempts={}, e={}",
+                delivery.event,
+                delivery.url,
+                delivery.id,
+                delivery.attempts,
+                e
+            );
+
+            self.outbox.remove(idx);
+            return;
+        }
+
+        let backoff_ms = retry_backoff_ms(delivery.attempts);
+        delivery.next_attempt_ms = EchoUtc::now().timestamp_millis() + backoff_ms;
+
+        log::warn!(
+            "[WebhookDispatcher] failed to deliver, retrying, event={}, url={}, id={}, \
+            attempts={}, backoff_ms={}, e={}",
+            delivery.event,
+            delivery.url,
+            delivery.id,
+            delivery.attempts,
+            backoff_ms,
+            e
+        );
+    }
+
+    // until the earliest delivery which is not in flight
+    fn next_wait(&self) -> Duration {
+        let now_ms = EchoUtc::now().timestamp_millis();
+
+        let next_ms = self
+            .outbox
+            .iter()
+            .filter(|x| !self.inflight.contains(&x.id))
+            .map(|x| x.next_attempt_ms)
+            .min()
+            .unwrap_or(now_ms + WEBHOOK_RETRY_MAX_MS);
+
+        Duration::from_millis((next_ms - now_ms).clamp(10, WEBHOOK_RETRY_MAX_MS) as u64)
+    }
+
+    //
+    // outbox file
+    // > whole outbox is rewritten on change(tmp file and rename)
+    //
+
+    async fn read_outbox(&self) -> Vec<WebhookDelivery> {
+        let buf = match tokio::fs::read(&self.outbox_path).await {
+            Ok(x) => x,
+            Err(_) => return Vec::new(),
+        };
+
+        serde_json::from_slice(&buf).unwrap_or_else(|e| {
+            log::error!(
+                "[WebhookDispatcher::read_outbox] invalid outbox, path={:?}, e={}",
+                self.outbox_path,
+                e.to_string()
+            );
+
+            Vec::new()
+        })
+    }
+
+    async fn write_outbox(&self) {
+        let buf = match serde_json::to_vec(&self.outbox) {
+            Ok(x) => x,
+            Err(e) => {
+                log::error!("[WebhookDispatcher::write_outbox] e={}", e.to_string());
+                return;
+            }
+        };
+
+        let tmp_path = self.outbox_path.with_extension("tmp");
+
+        if let Err(e) = tokio::fs::write(&tmp_path, buf).await {
+            log::error!(
+                "[WebhookDispatcher::write_outbox] failed to write outbox, path={:?}, e={}",
+                tmp_path,
+                e.to_string()
+            );
+            return;
+        }
+
+        if let Err(e) = tokio::fs::rename(&tmp_path, &self.outbox_path).await {
+            log::error!(
+                "[WebhookDispatcher::write_outbox] failed to rename outbox, path={:?}, e={}",
+                self.outbox_path,
+                e.to_string()
+            );
+        }
+    }
+}
+
+async fn send_webhook(
+    client: reqwest::Client,
+    delivery: &WebhookDelivery,
+    secret: Option<String>,
+) -> Result<(), String> {
+    let timestamp = EchoUtc::now().timestamp().to_string();
+
+    let mut req = client
+        .post(&delivery.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(WEBHOOK_EVENT_HEADER, delivery.event.to_string())
+        .header(WEBHOOK_DELIVERY_HEADER, delivery.id.as_str())
+        .header(WEBHOOK_TIMESTAMP_HEADER, timestamp.as_str());
+
+    if let Some(secret) = secret {
+        req = req.header(
+            WEBHOOK_SIGNATURE_HEADER,
+            format!("sha256={}", sign(&secret, &timestamp, &delivery.body)),
+        );
+    }
+
+    let resp = req
+        .body(delivery.body.clone())
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if resp.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("status={}", resp.status()))
+    }
+}
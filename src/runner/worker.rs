@@ -1,23 +1,51 @@
-use super::driver;
-use super::DriverType;
+use super::driver::DriverRegistry;
+use super::event_bus;
+use super::ffmpeg::adts_inspect::{
+    AdtsCorruptPolicy, AdtsInspectStats, AdtsSegmentInspector, AdtsSegmentReport,
+};
+use super::ffmpeg::error::RunnerFFMError;
+use super::ffmpeg::failover::{FailoverSwitchBack, FailoverTick, HlsFailover};
+use super::ffmpeg::loudnorm::LoudnormPrm;
+use super::ffmpeg::media_monitor::{AudioLevel, MediaMonitor, MediaMonitorEvent, VideoContent};
+use super::ffmpeg::playlist::{HlsLivePlaylist, PlaylistSegment};
+use super::ffmpeg::recorder::RecFileStatus;
+use super::ffmpeg::slate::{HlsSlate, SlateTick};
+use super::ffmpeg::stream_probe::{self, StreamMismatchPolicy, StreamProbe};
+use super::store;
+use super::webhook::{self, WebhookEvent};
 use super::RunnerError;
-use super::{Driver, DriverRstOk};
+use super::{
+    DriverCreatePrm, DriverRstOk, ExpiryChangeOp, ExpiryChangePrm, PauseTarget, ReceiverDriver,
+    ReceiverDriverBox, RecvWorkerStateSnapshot, SessionPausePrm, EXPIRE_EXTEND_CEILING_SEC,
+    EXPIRE_EXTEND_CEILING_SEC_ENV, EXPIRE_GRACE_SEC, EXPIRE_WARN_THRESHOLDS_SEC,
+    EXPIRE_WARN_THRESHOLDS_SEC_ENV, FAILOVER_CHECK_INTERVAL_MS, MEDIA_MONITOR_INTERVAL_MS,
+    PLAYLIST_CHECK_INTERVAL_MS, PUBLISHER_CHECK_INTERVAL_MS, PUBLISHER_IDLE_SEC,
+    SLATE_CHECK_INTERVAL_MS, WORKER_METRICS_INTERVAL_MS,
+};
 use crate::comm::*;
-use crate::comm_media::MediaReceiver;
+use crate::comm_media::{MediaType, PropMedia};
 use crate::config::Config;
 use crate::message::ServMsgSend;
+use crate::metrics;
 use crate::service::api::reqres::publish::req::ReqPublishV3;
 use crate::service::vsvr::{message::VSvrServMsgSend, util};
+use crate::EchoPathBufToString;
+use crate::ECHO_TIME_DURATION_MS;
+use crate::ECHO_TIME_DURATION_SEC;
 use anyhow::Result;
 use serde_json::json;
+use std::time::SystemTime;
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio::time::Duration;
 use PrmJson;
 
 // vsvr service message
 use super::message::{
-    FinishRecvWorkerMsgOCResponder, RecvWorkerManagerMsg, RecvWorkerManagerMsgSend, RecvWorkerMsg,
-    RecvWorkerMsgRecv, RecvWorkerMsgSend, StartRecvWorkerMsgOCResponder,
+    ChangeRecvWorkerExpiryMsgOCResponder, FinishRecvWorkerMsgOCResponder,
+    PauseRecvWorkerMsgOCResponder, QueryRecvWorkerStateMsgOCResponder, RecvWorkerManagerMsg,
+    RecvWorkerManagerMsgSend, RecvWorkerMsg, RecvWorkerMsgRecv, RecvWorkerMsgSend,
+    StartRecvWorkerMsgOCResponder,
 };
 
 pub struct RecvWorker {
@@ -34,7 +62,7 @@ pub struct RecvWorker {
     main_serv_msg_send: ServMsgSend,
     vsvr_serv_msg_send: VSvrServMsgSend,
 
-    driver: Driver,
+    driver: ReceiverDriverBox,
 
     config: Config,
 
@@ -42,6 +70,9 @@ pub struct RecvWorker {
     sess_key: String,
 
     publish_port: u16,
+    media_type: MediaType,
+    // declared by publish request, checked by stream probe
+    media: PropMedia,
 
     expire_duration: EchoTimeDuration,
     expire_instant: EchoTimeInstant,
@@ -51,9 +82,70 @@ pub struct RecvWorker {
 
     expired: bool,
 
+    // expiry clock is stopped since
+    expiry_paused_at: Option<EchoTimeInstant>,
+
+    // seconds before expiry, warned thresholds are not warned again
+    expire_warn_thresholds_sec: Vec<u64>,
+    expire_warned_sec: Vec<u64>,
+
+    // session keeps running for grace period after expire_instant
+    expire_grace: EchoTimeDuration,
+    expire_grace_notified: bool,
+
+    // driver is paused, SessionPausePrm
+    paused: Option<PauseTarget>,
+    // expiry clock is stopped by pause, resumed with driver
+    expiry_frozen_by_pause: bool,
+
     runner_respawn_cnt: i32,
+
+    // ingest progress of receiver is updated within PUBLISHER_IDLE_SEC
+    publisher_connected: bool,
+    publisher_checked_at: EchoTimeInstant,
+
+    // served hls playlist, segments of receiver, slate and backup are listed by it
+    playlist: HlsLivePlaylist,
+    playlist_checked_at: EchoTimeInstant,
+
+    // newest hls segment and bytes of recording files(_NEW), crate::metrics
+    metrics_checked_at: EchoTimeInstant,
+    rec_bytes_seen: u64,
+
+    // some: publisher is connected since, first segment written from then is probed
+    stream_probe_since_epoch: Option<i64>,
+    stream_probe_checked_at: EchoTimeInstant,
+    stream_probe_task: Option<JoinHandle<Result<StreamProbe, RunnerFFMError>>>,
+    stream_probe: Option<StreamProbe>,
+    stream_mismatch_policy: StreamMismatchPolicy,
+
+    // adts of new hls segments, audio session only
+    // > inspector is moved into blocking task while inspecting
+    adts_inspector: Option<AdtsSegmentInspector>,
+    adts_stats: Option<AdtsInspectStats>,
+
+    // loudness and silence(audio), black and frozen picture(video) of new hls segments
+    media_monitor: Option<MediaMonitor>,
+    media_monitor_checked_at: EchoTimeInstant,
+    media_monitor_task: Option<MediaMonitorTask>,
+    audio_level: Option<AudioLevel>,
+    video_content: Option<VideoContent>,
+
+    // slate while receiver waits for publisher to reconnect, ECHO_HLS_SLATE_DIR
+    slate: Option<HlsSlate>,
+    slate_checked_at: EchoTimeInstant,
+
+    // backup publisher, PublishOverrides.backup
+    failover: Option<HlsFailover>,
+    failover_checked_at: EchoTimeInstant,
+    // primary is lost(ingest idle, receiver exited), backup takes over once it is live
+    failover_pending: bool,
+    // primary receiver is not respawned while backup is on air, FailoverSwitchBack::BackupLost
+    primary_held: bool,
 }
 
+type MediaMonitorTask = JoinHandle<(MediaMonitor, Result<Vec<MediaMonitorEvent>, RunnerFFMError>)>;
+
 impl RecvWorker {
     pub fn clone_msg_send(&self) -> RecvWorkerMsgSend {
         self.msg_send.clone()
@@ -67,11 +159,226 @@ impl RecvWorker {
         self.msg_recv.try_recv()
     }
 
-    async fn handle_msg_query_worker_state(&mut self) -> Result<(), RunnerError> {
+    async fn handle_msg_query_worker_state(
+        &mut self,
+        responder: QueryRecvWorkerStateMsgOCResponder,
+    ) -> Result<(), RunnerError> {
         log::debug!("[RecvWorker] got msg 'QueryWorkerState'");
+
+        let snapshot = self.get_state_snapshot().await;
+
+        // requester may be gone by timeout, not an error of worker
+        if let Err(_) = responder.send(Ok(snapshot)) {
+            log::debug!(
+                "[RecvWorker] requester of 'QueryWorkerState' is gone, uuid={}",
+                self.uuid
+            );
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_state_snapshot(&self) -> RecvWorkerStateSnapshot {
+        let driver_stats = self.driver.stats().await;
+
+        RecvWorkerStateSnapshot {
+            worker_uuid: self.uuid.clone(),
+            app_name: self.app_name.clone(),
+            sess_key: self.sess_key.clone(),
+            publish_port: self.publish_port,
+            media_type: self.media_type,
+
+            driver_status: driver_stats.status,
+
+            start_epoch: self.start_epoch,
+            expire_epoch: self.expire_epoch,
+            expired: self.expired,
+            expiry_paused: self.expiry_paused_at.is_some(),
+
+            paused: self.paused,
+
+            respawn_cnt: self.runner_respawn_cnt,
+
+            procs: driver_stats.procs,
+            ingest: driver_stats.ingest,
+
+            stream: self.stream_probe.clone(),
+
+            audio: self.adts_stats.clone(),
+            audio_level: self.audio_level.clone(),
+            video_content: self.video_content.clone(),
+
+            slate_on_air: self.slate.as_ref().map_or(false, |x| x.is_on_air()),
+
+            failover: self.failover.as_ref().map(|x| x.get_state()),
+        }
+    }
+
+    async fn handle_msg_change_worker_expiry(
+        &mut self,
+        responder: ChangeRecvWorkerExpiryMsgOCResponder,
+        change_prm: ExpiryChangePrm,
+    ) -> Result<(), RunnerError> {
+        log::debug!(
+            "[RecvWorker] got msg 'ChangeRecvWorkerExpiry', op={}",
+            change_prm.op
+        );
+
+        let resp = self.change_expiry(change_prm);
+
+        if let Err(_) = responder.send(resp) {
+            log::debug!(
+                "[RecvWorker] requester of 'ChangeRecvWorkerExpiry' is gone, uuid={}",
+                self.uuid
+            );
+        }
+
         Ok(())
     }
 
+    fn respond_oper_msg(
+        &self,
+        responder: PauseRecvWorkerMsgOCResponder,
+        msg_name: &str,
+        resp: Result<PrmJson, RunnerError>,
+    ) {
+        if let Err(_) = responder.send(resp) {
+            log::debug!(
+                "[RecvWorker] requester of '{}' is gone, uuid={}",
+                msg_name,
+                self.uuid
+            );
+        }
+    }
+
+    async fn pause(&mut self, pause_prm: SessionPausePrm) -> Result<PrmJson, RunnerError> {
+        if self.is_finished || self.expired {
+            return Err(RunnerError::RecvWorkerOperErr(format!(
+                "session is finishing, uuid={}",
+                self.uuid
+            )));
+        }
+
+        if let Some(paused) = self.paused {
+            return Err(RunnerError::RecvWorkerOperErr(format!(
+                "already paused, target={}",
+                paused
+            )));
+        }
+
+        self.driver.pause(pause_prm.target).await?;
+
+        self.paused = Some(pause_prm.target);
+
+        // timestamps jump over the gap, segments closed before it are listed first
+        if pause_prm.target == PauseTarget::Pipeline {
+            self.publish_origin_segments().await;
+            self.playlist.mark_discontinuity();
+        }
+
+        // expiry may be paused already by ExpiryChangeOp::Pause, leave it as is
+        if pause_prm.freeze_expiry && self.expiry_paused_at.is_none() {
+            self.change_expiry(ExpiryChangePrm {
+                op: ExpiryChangeOp::Pause,
+                warn_thresholds_sec: None,
+                grace_sec: None,
+            })?;
+
+            self.expiry_frozen_by_pause = true;
+        }
+
+        event_bus::session_event(
+            "info",
+            "session_paused",
+            &self.app_name,
+            &self.uuid,
+            json!({
+                "target" : pause_prm.target,
+                "freeze_expiry" : self.expiry_frozen_by_pause,
+                "cur_epoch" : EchoUtc::now().timestamp(),
+            }),
+        );
+
+        Ok(self.get_pause_state_json())
+    }
+
+    async fn resume(&mut self) -> Result<PrmJson, RunnerError> {
+        let paused = self.paused.ok_or_else(|| {
+            RunnerError::RecvWorkerOperErr(format!("not paused, uuid={}", self.uuid))
+        })?;
+
+        self.driver.resume().await?;
+
+        self.paused = None;
+
+        if self.expiry_frozen_by_pause {
+            self.expiry_frozen_by_pause = false;
+
+            if self.expiry_paused_at.is_some() {
+                self.change_expiry(ExpiryChangePrm {
+                    op: ExpiryChangeOp::Resume,
+                    warn_thresholds_sec: None,
+                    grace_sec: None,
+                })?;
+            }
+        }
+
+        event_bus::session_event(
+            "info",
+            "session_resumed",
+            &self.app_name,
+            &self.uuid,
+            json!({
+                "target" : paused,
+                "cur_epoch" : EchoUtc::now().timestamp(),
+                "expire_epoch" : self.expire_epoch,
+            }),
+        );
+
+        Ok(self.get_pause_state_json())
+    }
+
+    // pause state is lost by restarting driver(processes are respawned)
+    fn clear_pause_on_restart(&mut self) {
+        let paused = match self.paused.take() {
+            Some(p) => p,
+            None => return,
+        };
+
+        if self.expiry_frozen_by_pause {
+            self.expiry_frozen_by_pause = false;
+
+            if self.expiry_paused_at.is_some() {
+                let _ = self.change_expiry(ExpiryChangePrm {
+                    op: ExpiryChangeOp::Resume,
+                    warn_thresholds_sec: None,
+                    grace_sec: None,
+                });
+            }
+        }
+
+        event_bus::session_event(
+            "info",
+            "session_resumed",
+            &self.app_name,
+            &self.uuid,
+            json!({
+                "target" : paused,
+                "by_restart" : 1,
+                "cur_epoch" : EchoUtc::now().timestamp(),
+                "expire_epoch" : self.expire_epoch,
+            }),
+        );
+    }
+
+    fn get_pause_state_json(&self) -> PrmJson {
+        json!({
+            "paused" : self.paused,
+            "expiry_paused" : self.expiry_paused_at.is_some(),
+            "expire_epoch" : self.expire_epoch,
+        })
+    }
+
     async fn handle_msg_start_recv_worker(
         &mut self,
         responder: StartRecvWorkerMsgOCResponder,
@@ -105,7 +412,24 @@ impl RecvWorker {
     async fn proc_msg(&mut self) -> Result<(), RunnerError> {
         match self.peek_message() {
             Ok(msg) => match msg {
-                RecvWorkerMsg::QueryRecvWorkerState => self.handle_msg_query_worker_state().await?,
+                RecvWorkerMsg::QueryRecvWorkerState(responder) => {
+                    self.handle_msg_query_worker_state(responder).await?
+                }
+
+                RecvWorkerMsg::ChangeRecvWorkerExpiry(responder, change_prm) => {
+                    self.handle_msg_change_worker_expiry(responder, change_prm)
+                        .await?
+                }
+
+                RecvWorkerMsg::PauseRecvWorker(responder, pause_prm) => {
+                    let resp = self.pause(pause_prm).await;
+                    self.respond_oper_msg(responder, "PauseRecvWorker", resp);
+                }
+
+                RecvWorkerMsg::ResumeRecvWorker(responder) => {
+                    let resp = self.resume().await;
+                    self.respond_oper_msg(responder, "ResumeRecvWorker", resp);
+                }
 
                 RecvWorkerMsg::FinishRecvWorker(response, finish_prms) => {
                     self.handle_msg_finish_recv_worker(response, finish_prms)
@@ -160,28 +484,83 @@ impl RecvWorker {
         req_publish: ReqPublishV3,
         publish_port: u16,
         expire_duration: EchoTimeDuration,
+        loudnorm: Option<LoudnormPrm>,
+        backup_publish_port: Option<u16>,
+        driver_registry: &DriverRegistry,
     ) -> Result<RecvWorker, RunnerError> {
-        let driverType = match req_publish.receiver {
-            MediaReceiver::FFMPEG => DriverType::FFMPEG,
-            e => return Err(RunnerError::UnSupportedMediaReciverType(format!("{:?}", e))),
-        };
+        if !driver_registry.is_registered(req_publish.receiver) {
+            return Err(RunnerError::UnSupportedMediaReciverType(format!(
+                "{:?}",
+                req_publish.receiver
+            )));
+        }
 
         let (sender, receiver) = mpsc::unbounded_channel();
 
-        let driver = driver::new(
-            config.clone(),
-            sender.clone(),
-            DriverType::FFMPEG,
-            req_publish.receiver_prm.clone(),
-            req_publish.media.clone(),
-            req_publish.app_name.clone(),
-            req_publish.sess_key.clone(),
-            publish_port,
+        let driver = driver_registry.new_driver(
+            req_publish.receiver,
+            DriverCreatePrm {
+                config: config.clone(),
+                worker_msg_send: sender.clone(),
+                receiver_args: req_publish.receiver_prm.args.clone(),
+                media: req_publish.media.clone(),
+                app_name: req_publish.app_name.clone(),
+                sess_key: req_publish.sess_key.clone(),
+                publish_port,
+                loudnorm: loudnorm.clone(),
+                backup_publish_port,
+            },
         )?;
 
         let start_epoch = get_echo_epoch();
         let expire_epoch = start_epoch + expire_duration.as_secs() as i64;
 
+        let hls_out_path = format!(
+            "{}/{}",
+            EchoPathBufToString!(config.echo_hls_root_dir.clone()),
+            req_publish.app_name
+        );
+
+        let playlist = HlsLivePlaylist::new(&hls_out_path, config.echo_ffmpeg_hls_list_size);
+        let playlist_path = EchoPathBufToString!(playlist.path());
+
+        let adts_inspector = match req_publish.media.media_type {
+            MediaType::Audio => Some(AdtsSegmentInspector::new(
+                playlist.dir().to_path_buf(),
+                AdtsCorruptPolicy::from_env(),
+            )),
+            _ => None,
+        };
+
+        let media_monitor = MediaMonitor::new(
+            req_publish.media.media_type,
+            config.echo_ffmpeg_path.clone(),
+            playlist_path.clone(),
+        );
+
+        let slate = HlsSlate::from_env(req_publish.media.media_type);
+
+        let failover = match backup_publish_port {
+            Some(port) => Some(
+                HlsFailover::new(
+                    &config,
+                    &req_publish.media,
+                    req_publish.app_name.clone(),
+                    req_publish.sess_key.clone(),
+                    req_publish.receiver_prm.args.clone(),
+                    port,
+                    loudnorm.as_ref(),
+                )
+                .map_err(|e| {
+                    RunnerError::RunnerDriverErr(format!(
+                        "failed to create backup receiver, e={}",
+                        e.to_string()
+                    ))
+                })?,
+            ),
+            None => None,
+        };
+
         let inst = Self {
             //is_finished: false,
             force_exit: false,
@@ -204,6 +583,8 @@ impl RecvWorker {
             sess_key: req_publish.sess_key.clone(),
 
             publish_port,
+            media_type: req_publish.media.media_type,
+            media: req_publish.media.clone(),
 
             expire_duration,
             expire_instant: EchoTimeInstant::now() + expire_duration,
@@ -213,7 +594,50 @@ impl RecvWorker {
 
             expired: false,
 
+            expiry_paused_at: None,
+
+            expire_warn_thresholds_sec: get_expire_warn_thresholds_sec(),
+            expire_warned_sec: Vec::new(),
+
+            expire_grace: ECHO_TIME_DURATION_SEC!(EXPIRE_GRACE_SEC),
+            expire_grace_notified: false,
+
+            paused: None,
+            expiry_frozen_by_pause: false,
+
             runner_respawn_cnt: 0,
+
+            publisher_connected: false,
+            publisher_checked_at: EchoTimeInstant::now(),
+
+            playlist,
+            playlist_checked_at: EchoTimeInstant::now(),
+
+            metrics_checked_at: EchoTimeInstant::now(),
+            rec_bytes_seen: 0,
+
+            stream_probe_since_epoch: None,
+            stream_probe_checked_at: EchoTimeInstant::now(),
+            stream_probe_task: None,
+            stream_probe: None,
+            stream_mismatch_policy: StreamMismatchPolicy::from_env(),
+
+            adts_inspector,
+            adts_stats: None,
+
+            media_monitor: Some(media_monitor),
+            media_monitor_checked_at: EchoTimeInstant::now(),
+            media_monitor_task: None,
+            audio_level: None,
+            video_content: None,
+
+            slate,
+            slate_checked_at: EchoTimeInstant::now(),
+
+            failover,
+            failover_checked_at: EchoTimeInstant::now(),
+            failover_pending: false,
+            primary_held: false,
         };
 
         //Ok(Arc::new((inst, sender)))
@@ -230,6 +654,17 @@ impl RecvWorker {
             )));
         }
 
+        // backup publisher may connect before primary
+        if let Some(failover) = self.failover.as_mut() {
+            if let Err(e) = failover.begin() {
+                log::error!(
+                    "[RecvWorker::begin] failed to spawn backup receiver, app_name={}, e={}",
+                    self.app_name,
+                    e
+                );
+            }
+        }
+
         // hls provider svr addr: ECHO_HLS_PROVIDER_ADDR
         log::debug!(
             "[RecvWorker::begin] register hls provider addr to vsvr, app_name={}, hls_addr={}",
@@ -327,11 +762,9 @@ impl RecvWorker {
         //     self.publish_port
         // );
 
-        crate::mlog::echo::session::event(
+        self.notify_lifecycle(
             "info",
-            "session_created",
-            &self.app_name,
-            &self.uuid,
+            WebhookEvent::SessionCreated,
             json!({
                 "uuid"         : self.uuid,
                 "app_name"     : self.app_name,
@@ -378,7 +811,8 @@ impl RecvWorker {
                 }
             }
 
-            match self.driver.tick().await {
+            // primary receiver is held, driver is not ticked until it is respawned
+            match self.tick_driver().await {
                 Err(e) => {
                     log::debug!("[RecvWorker::run] error on driver tick.., e={}", e);
                     break;
@@ -391,18 +825,53 @@ impl RecvWorker {
                             do_respawn
                         );
 
+                        // ex) receiver is exited by disconnection of publisher
+                        self.set_publisher_connected(false, "receiver_exited");
+
                         if do_respawn {
+                            self.clear_pause_on_restart();
+
+                            // last segments of exited receiver are listed before slate or backup
+                            self.publish_origin_segments().await;
+
+                            // playlist is kept live by backup, or slate until respawned receiver gets publisher
+                            if !self.switch_to_backup("receiver_exited") {
+                                self.start_slate();
+                            }
+
+                            if self.is_on_backup() && self.holds_primary() {
+                                log::info!(
+                                    "[RecvWorker::run] primary receiver is held while backup is on air, app_name={}",
+                                    self.app_name
+                                );
+
+                                self.primary_held = true;
+
+                                continue; // goto loop
+                            }
+
                             let rst_restart = self.driver.restart().await;
 
                             match rst_restart {
                                 Ok(_) => {
                                     self.runner_respawn_cnt += 1;
 
+                                    metrics::SESSION_RESPAWN_TOTAL.inc();
+
                                     log::debug!(
                                         "[RecvWorker::run] driver is restarted.. respawn_count={}",
                                         self.runner_respawn_cnt
                                     );
 
+                                    self.notify_lifecycle(
+                                        "info",
+                                        WebhookEvent::Respawned,
+                                        json!({
+                                            "respawn_cnt" : self.runner_respawn_cnt,
+                                            "publish_port" : self.publish_port,
+                                        }),
+                                    );
+
                                     continue; // goto loop
                                 }
                                 Err(e) => {
@@ -428,6 +897,43 @@ impl RecvWorker {
                 tokio::time::sleep(Duration::from_millis(100)).await;
             }
 
+            self.check_expire_warnings();
+
+            self.check_publisher().await;
+
+            self.check_playlist().await;
+
+            self.check_failover().await;
+
+            if self.check_slate().await {
+                log::warn!(
+                    "[RecvWorker::run] publisher did not reconnect within grace period, exiting.., app_name={}",
+                    self.app_name
+                );
+
+                break;
+            }
+
+            self.collect_metrics().await;
+
+            if self.check_media_monitor().await {
+                log::warn!(
+                    "[RecvWorker::run] silence lasted too long, exiting.., app_name={}",
+                    self.app_name
+                );
+
+                break;
+            }
+
+            if self.check_stream_probe().await {
+                log::warn!(
+                    "[RecvWorker::run] stream differs from declared media, exiting.., app_name={}",
+                    self.app_name
+                );
+
+                break;
+            }
+
             // Normal 2 hour, Maximum 4 hours(vsvr coupon)
             if self.check_expired() {
                 let cur_epoch = get_echo_epoch();
@@ -443,11 +949,11 @@ impl RecvWorker {
 
                 self.expired = true;
 
-                crate::mlog::echo::session::event(
+                self.set_publisher_connected(false, "session_expired");
+
+                self.notify_lifecycle(
                     "info",
-                    "session_expired",
-                    &self.app_name,
-                    &self.uuid,
+                    WebhookEvent::SessionExpired,
                     json!({
                        "cur_epoch" : cur_epoch,
                        "expire_dur_sec" : self.expire_duration.as_secs(),
@@ -464,15 +970,31 @@ impl RecvWorker {
         // exiting
         //
 
-        if let Err(e) = self.driver.end().await {
+        self.set_publisher_connected(false, "session_exit");
+
+        if let Some(failover) = self.failover.as_mut() {
+            failover.end().await;
+        }
+
+        let rst_end = self.driver.end().await;
+
+        // last segments written by exited receiver, players stop waiting
+        self.end_playlist().await;
+
+        // notify to manager, port and handle are released on every exit(driver may fail to end)
+        let rst_notify = self.send_msg_to_manager(RecvWorkerManagerMsg::NotifyRecvWorkerIsExiting(
+            self.uuid.to_string(),
+            self.app_name.to_string(),
+            self.sess_key.to_string(),
+        ));
+
+        if let Err(e) = rst_end {
             let em = format!("[RecvWorker::run::driver.end] failed to exit driver");
             log::error!("{}", em);
 
-            crate::mlog::echo::session::event(
+            self.notify_lifecycle(
                 "err",
-                "session_exit",
-                &self.app_name,
-                &self.uuid,
+                WebhookEvent::SessionExit,
                 json!({
                     "exit_fail" : 1,
                     "cur_epoch" : get_echo_epoch(),
@@ -485,23 +1007,16 @@ impl RecvWorker {
             return Err(RunnerError::RecvDriverErr(em));
         }
 
-        // notify to manager
-        if let Err(_) = self.send_msg_to_manager(RecvWorkerManagerMsg::NotifyRecvWorkerIsExiting(
-            self.uuid.to_string(),
-            self.app_name.to_string(),
-            self.sess_key.to_string(),
-        )) {
+        if let Err(_) = rst_notify {
             let em = format!(
                 "[RecvWorker::run::send_msg_to_manager] \
              failed to send msg 'NotifyRecvWorkerIsExiting' to manager"
             );
             log::error!("{}", em);
 
-            crate::mlog::echo::session::event(
+            self.notify_lifecycle(
                 "err",
-                "session_exit",
-                &self.app_name,
-                &self.uuid,
+                WebhookEvent::SessionExit,
                 json!({
                    "exit_fail" : 1,
                    "cur_epoch" : get_echo_epoch(),
@@ -516,11 +1031,9 @@ impl RecvWorker {
 
         log::debug!("[RecvWorker::run] exiting..");
 
-        crate::mlog::echo::session::event(
+        self.notify_lifecycle(
             "info",
-            "session_exit",
-            &self.app_name,
-            &self.uuid,
+            WebhookEvent::SessionExit,
             json!({
                 "exit_fail" : 0,
                 "cur_epoch" : get_echo_epoch(),
@@ -532,13 +1045,1004 @@ impl RecvWorker {
         Ok(())
     }
 
-    pub fn check_expired(&mut self) -> bool {
-        let now = EchoTimeInstant::now();
+    // session log, and webhook of lifecycle event
+    fn notify_lifecycle(&self, level: &str, event: WebhookEvent, data: PrmJson) {
+        event_bus::session_event(
+            level,
+            &event.to_string(),
+            &self.app_name,
+            &self.uuid,
+            data.clone(),
+        );
 
-        if self.expire_instant < now {
-            return true;
+        webhook::notify(event, &self.app_name, &self.uuid, data);
+    }
+
+    // publisher is connected while receiver makes ingest progress
+    // > paused receiver(SIGSTOP) is not checked
+    async fn check_publisher(&mut self) {
+        if self.paused.is_some()
+            || self.publisher_checked_at.elapsed()
+                < ECHO_TIME_DURATION_MS!(PUBLISHER_CHECK_INTERVAL_MS)
+        {
+            return;
+        }
+
+        self.publisher_checked_at = EchoTimeInstant::now();
+
+        let cur_epoch = EchoUtc::now().timestamp();
+
+        let connected = match self.driver.stats().await.ingest {
+            Some(ingest) => cur_epoch - ingest.updated_epoch <= PUBLISHER_IDLE_SEC,
+            None => false,
+        };
+
+        self.set_publisher_connected(connected, "ingest_idle");
+    }
+
+    // recording bytes, hls segment latency is observed as segments are listed(publish_origin_segments)
+    async fn collect_metrics(&mut self) {
+        if self.metrics_checked_at.elapsed() < ECHO_TIME_DURATION_MS!(WORKER_METRICS_INTERVAL_MS) {
+            return;
+        }
+
+        self.metrics_checked_at = EchoTimeInstant::now();
+
+        let rec_out_path = format!(
+            "{}/{}",
+            EchoPathBufToString!(self.config.echo_rec_root_dir.clone()),
+            self.app_name
+        );
+
+        let rec_bytes = store::record_files_bytes(&rec_out_path, RecFileStatus::NEW).await;
+
+        // recording file is rotated or finalized, new files are counted from zero
+        if rec_bytes < self.rec_bytes_seen {
+            self.rec_bytes_seen = 0;
+        }
+
+        metrics::REC_BYTES_WRITTEN_TOTAL.inc_by(rec_bytes - self.rec_bytes_seen);
+        self.rec_bytes_seen = rec_bytes;
+    }
+
+    // true: mismatch is a policy violation, session must be ended
+    async fn check_stream_probe(&mut self) -> bool {
+        if let Some(task) = self.stream_probe_task.as_ref() {
+            if !task.is_finished() {
+                return false;
+            }
+
+            let task = self.stream_probe_task.take().unwrap();
+
+            return match task.await {
+                Ok(Ok(probe)) => self.on_stream_probed(probe),
+                Ok(Err(e)) => {
+                    log::error!(
+                        "[RecvWorker::check_stream_probe] failed to probe stream, app_name={}, e={}",
+                        self.app_name,
+                        e
+                    );
+                    false
+                }
+                Err(e) => {
+                    log::error!(
+                        "[RecvWorker::check_stream_probe] probe task is failed, app_name={}, e={}",
+                        self.app_name,
+                        e
+                    );
+                    false
+                }
+            };
+        }
+
+        let since_epoch = match self.stream_probe_since_epoch {
+            Some(x) => x,
+            None => return false,
+        };
+
+        if self.stream_probe_checked_at.elapsed()
+            < ECHO_TIME_DURATION_MS!(PUBLISHER_CHECK_INTERVAL_MS)
+        {
+            return false;
         }
 
+        self.stream_probe_checked_at = EchoTimeInstant::now();
+
+        let playlist_path = format!(
+            "{}/{}/playlist.m3u8",
+            EchoPathBufToString!(self.config.echo_hls_root_dir.clone()),
+            self.app_name
+        );
+
+        let segment_path =
+            match stream_probe::first_segment_since(&playlist_path, since_epoch).await {
+                Some(x) => x,
+                None => return false,
+            };
+
+        self.stream_probe_since_epoch = None;
+
+        let ffprobe_path = stream_probe::get_ffprobe_path(&self.config.echo_ffmpeg_path);
+        let receiver_log_path = format!(
+            "{}/{}_receiver.log",
+            EchoPathBufToString!(self.config.echo_ffmpeg_log_root_dir.clone()),
+            self.app_name
+        );
+
+        // ffprobe does not block message loop of worker
+        self.stream_probe_task = Some(tokio::spawn(async move {
+            stream_probe::probe_stream(&ffprobe_path, &receiver_log_path, &segment_path).await
+        }));
+
         false
     }
+
+    fn on_stream_probed(&mut self, probe: StreamProbe) -> bool {
+        let mismatches = probe.check_declared(&self.media);
+
+        event_bus::session_event(
+            "info",
+            "stream_probed",
+            &self.app_name,
+            &self.uuid,
+            json!(probe),
+        );
+
+        self.stream_probe = Some(probe);
+
+        if mismatches.is_empty() {
+            return false;
+        }
+
+        let terminate = self.stream_mismatch_policy == StreamMismatchPolicy::Terminate;
+
+        self.notify_lifecycle(
+            "warn",
+            WebhookEvent::StreamMismatch,
+            json!({
+                "declared" : self.media,
+                "stream" : self.stream_probe,
+                "mismatches" : mismatches,
+                "terminate" : terminate,
+            }),
+        );
+
+        terminate
+    }
+
+    // segments of audio session are inspected in blocking task before they are listed
+    // > inspector is lost if task is failed, segments are not listed
+    async fn inspect_origin_segments(
+        &mut self,
+        segments: Vec<PlaylistSegment>,
+    ) -> Vec<PlaylistSegment> {
+        let mut inspector = match self.adts_inspector.take() {
+            Some(x) => x,
+            None => return segments,
+        };
+
+        let task = tokio::task::spawn_blocking(move || {
+            let mut segments = segments;
+            let reports = inspector.inspect(&mut segments);
+            (inspector, segments, reports)
+        });
+
+        match task.await {
+            Ok((inspector, segments, reports)) => {
+                self.adts_stats = Some(inspector.stats());
+                self.adts_inspector = Some(inspector);
+
+                self.on_adts_inspected(reports);
+
+                segments
+            }
+            Err(e) => {
+                log::error!(
+                    "[RecvWorker::inspect_origin_segments] inspect task is failed, app_name={}, e={}",
+                    self.app_name,
+                    e
+                );
+
+                Vec::new()
+            }
+        }
+    }
+
+    fn on_adts_inspected(&mut self, reports: Vec<AdtsSegmentReport>) {
+        for report in reports.iter().filter(|x| x.has_issue()) {
+            let issues = [
+                ("corrupt", report.corrupt),
+                ("quarantined", report.quarantined),
+                ("crc", report.crc_errors > 0),
+                ("gap", !report.gaps_ms.is_empty()),
+                ("format_change", !report.changes.is_empty()),
+            ];
+
+            for (kind, _) in issues.iter().filter(|(_, x)| *x) {
+                metrics::AUDIO_SEGMENT_ISSUE_TOTAL
+                    .with_label_values(&[*kind])
+                    .inc();
+            }
+
+            event_bus::session_event(
+                if report.corrupt { "error" } else { "warn" },
+                if report.quarantined {
+                    "audio_segment_quarantined"
+                } else {
+                    "audio_segment_issue"
+                },
+                &self.app_name,
+                &self.uuid,
+                json!(report),
+            );
+        }
+    }
+
+    // true: silence lasted ECHO_SILENCE_END_SESSION_MIN, session must be ended
+    async fn check_media_monitor(&mut self) -> bool {
+        if let Some(task) = self.media_monitor_task.as_ref() {
+            if !task.is_finished() {
+                return false;
+            }
+
+            let task = self.media_monitor_task.take().unwrap();
+
+            return match task.await {
+                Ok((monitor, rst)) => {
+                    match &monitor {
+                        MediaMonitor::Audio(x) => self.audio_level = Some(x.level()),
+                        MediaMonitor::Video(x) => self.video_content = Some(x.content()),
+                    }
+                    self.media_monitor = Some(monitor);
+
+                    match rst {
+                        Ok(events) => self.on_media_monitored(events),
+                        Err(e) => {
+                            log::error!(
+                                "[RecvWorker::check_media_monitor] failed to monitor media, app_name={}, e={}",
+                                self.app_name,
+                                e
+                            );
+                            false
+                        }
+                    }
+                }
+                // monitor is lost, not measured any more
+                Err(e) => {
+                    log::error!(
+                        "[RecvWorker::check_media_monitor] monitor task is failed, app_name={}, e={}",
+                        self.app_name,
+                        e
+                    );
+                    false
+                }
+            };
+        }
+
+        if self.media_monitor.is_none() {
+            return false;
+        }
+
+        if self.media_monitor_checked_at.elapsed()
+            < ECHO_TIME_DURATION_MS!(MEDIA_MONITOR_INTERVAL_MS)
+        {
+            return false;
+        }
+
+        self.media_monitor_checked_at = EchoTimeInstant::now();
+
+        let mut monitor = self.media_monitor.take().unwrap();
+
+        // ffmpeg does not block message loop of worker
+        self.media_monitor_task = Some(tokio::spawn(async move {
+            let rst = monitor.check().await;
+            (monitor, rst)
+        }));
+
+        false
+    }
+
+    fn on_media_monitored(&mut self, events: Vec<MediaMonitorEvent>) -> bool {
+        let mut terminate = false;
+
+        for ev in events {
+            match ev {
+                MediaMonitorEvent::SilenceTimeout { .. } => {
+                    terminate = true;
+
+                    self.notify_lifecycle("warn", WebhookEvent::SilenceTimeout, json!(ev));
+                }
+                _ => event_bus::session_event(
+                    "warn",
+                    ev.name(),
+                    &self.app_name,
+                    &self.uuid,
+                    json!(ev),
+                ),
+            }
+        }
+
+        terminate
+    }
+
+    // true: publisher did not reconnect within grace period, session must be ended
+    async fn check_slate(&mut self) -> bool {
+        if self.slate_checked_at.elapsed() < ECHO_TIME_DURATION_MS!(SLATE_CHECK_INTERVAL_MS) {
+            return false;
+        }
+
+        self.slate_checked_at = EchoTimeInstant::now();
+
+        let playlist = &mut self.playlist;
+
+        let tick = match self.slate.as_mut().map(|x| x.tick(playlist)) {
+            Some(Ok(x)) => x,
+            Some(Err(e)) => {
+                log::error!(
+                    "[RecvWorker::check_slate] failed to append slate, app_name={}, e={}",
+                    self.app_name,
+                    e
+                );
+                return false;
+            }
+            None => return false,
+        };
+
+        match tick {
+            SlateTick::GraceExpired => {
+                let grace_sec = self.slate.as_ref().map_or(0, |x| x.grace_sec());
+                self.end_slate("grace_expired");
+
+                self.notify_lifecycle(
+                    "warn",
+                    WebhookEvent::ReconnectTimeout,
+                    json!({
+                        "grace_sec" : grace_sec,
+                        "publish_port" : self.publish_port,
+                    }),
+                );
+
+                // players stop waiting, receiver is not respawned
+                self.end_playlist().await;
+
+                true
+            }
+            SlateTick::Idle | SlateTick::OnAir => false,
+        }
+    }
+
+    //
+    // playlist
+    //
+
+    async fn check_playlist(&mut self) {
+        if self.playlist_checked_at.elapsed() < ECHO_TIME_DURATION_MS!(PLAYLIST_CHECK_INTERVAL_MS) {
+            return;
+        }
+
+        self.playlist_checked_at = EchoTimeInstant::now();
+
+        self.publish_origin_segments().await;
+    }
+
+    // new segments of receiver are inspected(audio) and listed on live playlist
+    // > held while slate or backup is on air, it is ended once publisher is connected
+    async fn publish_origin_segments(&mut self) {
+        if self.slate.as_ref().map_or(false, |x| x.is_on_air()) || self.is_on_backup() {
+            return;
+        }
+
+        let segments = self.playlist.take_origin_segments();
+
+        if segments.is_empty() {
+            return;
+        }
+
+        let segments = self.inspect_origin_segments(segments).await;
+
+        // closed by receiver(mtime) until now, not including rewrite of receiver playlist
+        let closed_at: Vec<SystemTime> = segments
+            .iter()
+            .filter(|x| !x.gap)
+            .filter_map(|x| std::fs::metadata(self.playlist.dir().join(&x.uri)).ok())
+            .filter_map(|x| x.modified().ok())
+            .collect();
+
+        if let Err(e) = self.playlist.append(segments) {
+            log::error!(
+                "[RecvWorker::publish_origin_segments] failed to append segments, app_name={}, e={}",
+                self.app_name,
+                e
+            );
+
+            return;
+        }
+
+        let listed_at = SystemTime::now();
+
+        for x in closed_at {
+            let latency = listed_at.duration_since(x).unwrap_or_default();
+
+            metrics::HLS_SEGMENT_WRITE_LATENCY
+                .with_label_values(&[self.media_type.to_string().as_str()])
+                .observe(latency.as_secs_f64());
+        }
+    }
+
+    async fn end_playlist(&mut self) {
+        self.publish_origin_segments().await;
+
+        if let Err(e) = self.playlist.end() {
+            log::error!(
+                "[RecvWorker::end_playlist] failed to end playlist, app_name={}, e={}",
+                self.app_name,
+                e
+            );
+        }
+    }
+
+    fn start_slate(&mut self) {
+        let slate = match self.slate.as_mut() {
+            Some(x) => x,
+            None => return,
+        };
+
+        match slate.start(&mut self.playlist) {
+            Ok(true) => {}
+            // not live yet
+            Ok(false) => return,
+            Err(e) => log::error!(
+                "[RecvWorker::start_slate] failed to start slate, app_name={}, e={}",
+                self.app_name,
+                e
+            ),
+        }
+
+        let grace_sec = slate.grace_sec();
+
+        event_bus::session_event(
+            "info",
+            "slate_started",
+            &self.app_name,
+            &self.uuid,
+            json!({ "grace_sec" : grace_sec }),
+        );
+    }
+
+    fn end_slate(&mut self, reason: &str) {
+        let summary = match self.slate.as_mut().and_then(|x| x.stop()) {
+            Some(x) => x,
+            None => return,
+        };
+
+        event_bus::session_event(
+            "info",
+            "slate_ended",
+            &self.app_name,
+            &self.uuid,
+            json!({
+                "reason" : reason,
+                "on_air_sec" : summary.on_air_sec,
+                "segments" : summary.segments,
+            }),
+        );
+    }
+
+    //
+    // failover
+    //
+
+    async fn tick_driver(&mut self) -> Result<DriverRstOk, RunnerError> {
+        if self.primary_held {
+            return Ok(DriverRstOk::Ok);
+        }
+
+        self.driver.tick().await
+    }
+
+    async fn check_failover(&mut self) {
+        if self.failover_checked_at.elapsed() < ECHO_TIME_DURATION_MS!(FAILOVER_CHECK_INTERVAL_MS) {
+            return;
+        }
+
+        self.failover_checked_at = EchoTimeInstant::now();
+
+        let playlist = &mut self.playlist;
+
+        let tick = match self.failover.as_mut().map(|x| x.tick(playlist)) {
+            Some(Ok(x)) => x,
+            Some(Err(e)) => {
+                log::error!(
+                    "[RecvWorker::check_failover] failed to append backup, app_name={}, e={}",
+                    self.app_name,
+                    e
+                );
+                return;
+            }
+            None => return,
+        };
+
+        match tick {
+            FailoverTick::Idle => {
+                if !self.failover_pending || self.publisher_connected || self.paused.is_some() {
+                    return;
+                }
+
+                if !self.failover.as_ref().map_or(false, |x| x.is_backup_live()) {
+                    return;
+                }
+
+                // stalled primary receiver is still running, it writes playlist of its own on exit
+                // > taken down before backup is appended, respawned one appends to the playlist
+                if !self.primary_held {
+                    let rst = if self.holds_primary() {
+                        self.primary_held = true;
+                        self.driver.end().await.map(|_| ())
+                    } else {
+                        self.restart_primary().await
+                    };
+
+                    if let Err(e) = rst {
+                        log::error!(
+                            "[RecvWorker::check_failover] failed to take down primary receiver, app_name={}, e={}",
+                            self.app_name,
+                            e
+                        );
+                    }
+                }
+
+                self.switch_to_backup("ingest_idle");
+            }
+            FailoverTick::BackupLost => {
+                self.switch_to_primary("backup_lost");
+
+                // primary publisher may connect again
+                if self.primary_held {
+                    if let Err(e) = self.restart_primary().await {
+                        log::error!(
+                            "[RecvWorker::check_failover] failed to respawn primary receiver, app_name={}, e={}",
+                            self.app_name,
+                            e
+                        );
+                    }
+                }
+
+                if !self.publisher_connected {
+                    self.failover_pending = true;
+                    self.start_slate();
+                }
+            }
+            FailoverTick::OnBackup => {}
+        }
+    }
+
+    fn is_on_backup(&self) -> bool {
+        self.failover.as_ref().map_or(false, |x| x.is_on_backup())
+    }
+
+    // while backup is on air, primary receiver is not respawned until backup is lost
+    fn holds_primary(&self) -> bool {
+        self.failover
+            .as_ref()
+            .map_or(false, |x| x.switch_back() == FailoverSwitchBack::BackupLost)
+    }
+
+    async fn restart_primary(&mut self) -> Result<(), RunnerError> {
+        self.driver.restart().await?;
+
+        self.primary_held = false;
+        self.runner_respawn_cnt += 1;
+
+        metrics::SESSION_RESPAWN_TOTAL.inc();
+
+        self.notify_lifecycle(
+            "info",
+            WebhookEvent::Respawned,
+            json!({
+                "respawn_cnt" : self.runner_respawn_cnt,
+                "publish_port" : self.publish_port,
+            }),
+        );
+
+        Ok(())
+    }
+
+    // false: backup is not live
+    fn switch_to_backup(&mut self, reason: &str) -> bool {
+        let failover = match self.failover.as_mut() {
+            Some(x) => x,
+            None => return false,
+        };
+
+        if failover.is_on_backup() {
+            return true;
+        }
+
+        match failover.switch_to_backup(&mut self.playlist) {
+            Ok(true) => {}
+            Ok(false) => return false,
+            Err(e) => {
+                log::error!(
+                    "[RecvWorker::switch_to_backup] failed to switch to backup, app_name={}, e={}",
+                    self.app_name,
+                    e
+                );
+                return false;
+            }
+        }
+
+        let backup_publish_port = failover.backup_publish_port();
+
+        self.failover_pending = false;
+
+        // playlist is written by backup
+        self.end_slate("backup_switched");
+
+        log::warn!(
+            "[RecvWorker::switch_to_backup] switched to backup, app_name={}, reason={}, backup_publish_port={}",
+            self.app_name,
+            reason,
+            backup_publish_port
+        );
+
+        event_bus::session_event(
+            "warn",
+            "failover_switched",
+            &self.app_name,
+            &self.uuid,
+            json!({
+                "to" : "backup",
+                "reason" : reason,
+                "publish_port" : self.publish_port,
+                "backup_publish_port" : backup_publish_port,
+            }),
+        );
+
+        true
+    }
+
+    fn switch_to_primary(&mut self, reason: &str) {
+        let summary = match self.failover.as_mut().and_then(|x| x.switch_to_primary()) {
+            Some(x) => x,
+            None => return,
+        };
+
+        log::info!(
+            "[RecvWorker::switch_to_primary] switched to primary, app_name={}, reason={}, on_backup_sec={}",
+            self.app_name,
+            reason,
+            summary.on_backup_sec
+        );
+
+        event_bus::session_event(
+            "info",
+            "failover_switched",
+            &self.app_name,
+            &self.uuid,
+            json!({
+                "to" : "primary",
+                "reason" : reason,
+                "on_backup_sec" : summary.on_backup_sec,
+                "segments" : summary.segments,
+            }),
+        );
+    }
+
+    // reason: cause of disconnection
+    fn set_publisher_connected(&mut self, connected: bool, reason: &str) {
+        if self.publisher_connected == connected {
+            return;
+        }
+
+        self.publisher_connected = connected;
+
+        if connected {
+            // publisher may be changed on reconnect, probed again
+            self.stream_probe_since_epoch = Some(EchoUtc::now().timestamp() - PUBLISHER_IDLE_SEC);
+
+            self.end_slate("publisher_reconnected");
+
+            // FailoverSwitchBack::Auto, BackupLost switches back only when backup is lost
+            if self.is_on_backup() && !self.holds_primary() {
+                self.switch_to_primary("primary_resumed");
+            }
+
+            self.failover_pending = false;
+
+            self.notify_lifecycle(
+                "info",
+                WebhookEvent::PublisherConnected,
+                json!({
+                    "publish_port" : self.publish_port,
+                    "respawn_cnt" : self.runner_respawn_cnt,
+                }),
+            );
+        } else {
+            // primary is lost, not ended by session
+            if matches!(reason, "ingest_idle" | "receiver_exited") {
+                self.failover_pending = self.failover.is_some();
+            }
+
+            self.notify_lifecycle(
+                "info",
+                WebhookEvent::PublisherDisconnected,
+                json!({
+                    "publish_port" : self.publish_port,
+                    "reason" : reason,
+                }),
+            );
+        }
+    }
+
+    pub fn check_expired(&mut self) -> bool {
+        if self.expiry_paused_at.is_some() {
+            return false;
+        }
+
+        let now = EchoTimeInstant::now();
+
+        if self.expire_instant < now {
+            if now < self.expire_instant + self.expire_grace {
+                if !self.expire_grace_notified {
+                    self.expire_grace_notified = true;
+
+                    event_bus::session_event(
+                        "warn",
+                        "session_expire_grace",
+                        &self.app_name,
+                        &self.uuid,
+                        json!({
+                            "cur_epoch" : EchoUtc::now().timestamp(),
+                            "expire_epoch" : self.expire_epoch,
+                            "grace_sec" : self.expire_grace.as_secs(),
+                        }),
+                    );
+                }
+
+                return false;
+            }
+
+            return true;
+        }
+
+        false
+    }
+
+    // remaining time until expire_instant, paused clock is counted until paused
+    fn get_expire_remain(&self) -> EchoTimeDuration {
+        let base = self.expiry_paused_at.unwrap_or_else(EchoTimeInstant::now);
+
+        self.expire_instant.saturating_duration_since(base)
+    }
+
+    fn check_expire_warnings(&mut self) {
+        if self.expiry_paused_at.is_some() {
+            return;
+        }
+
+        let remain_sec = self.get_expire_remain().as_secs();
+
+        if remain_sec == 0 {
+            return;
+        }
+
+        let crossed: Vec<u64> = self
+            .expire_warn_thresholds_sec
+            .iter()
+            .filter(|x| remain_sec <= **x && !self.expire_warned_sec.contains(*x))
+            .cloned()
+            .collect();
+
+        // several thresholds can be crossed at once (ex: shortened), warn nearest one
+        let threshold_sec = match crossed.iter().min() {
+            Some(x) => *x,
+            None => return,
+        };
+
+        self.expire_warned_sec.extend(crossed.iter());
+
+        log::info!(
+            "[RecvWorker] session will be expired, app_name={}, remain_sec={}, threshold_sec={}",
+            self.app_name,
+            remain_sec,
+            threshold_sec
+        );
+
+        self.notify_lifecycle(
+            "warn",
+            WebhookEvent::SessionExpireWarning,
+            json!({
+                "remain_sec" : remain_sec,
+                "threshold_sec" : threshold_sec,
+                "expire_epoch" : self.expire_epoch,
+            }),
+        );
+    }
+
+    fn change_expiry(&mut self, change_prm: ExpiryChangePrm) -> Result<PrmJson, RunnerError> {
+        if self.is_finished || self.expired {
+            return Err(RunnerError::RecvWorkerOperErr(format!(
+                "session is finishing, uuid={}",
+                self.uuid
+            )));
+        }
+
+        let prev_expire_epoch = self.expire_epoch;
+        let now = EchoTimeInstant::now();
+
+        match change_prm.op {
+            ExpiryChangeOp::Extend { sec } => {
+                // session does not run longer than extend ceiling in total
+                // > not duration granted on publish(config or override), which is default of 2 hours
+                let ceiling = get_expire_extend_ceiling();
+                if self.expire_duration > ceiling {
+                    log::warn!(
+                        "[RecvWorker::change_expiry] granted duration exceeds extend ceiling, app_name={}, expire_duration_sec={}, ceiling_sec={}",
+                        self.app_name,
+                        self.expire_duration.as_secs(),
+                        ceiling.as_secs()
+                    );
+                }
+
+                let allowed = ceiling.saturating_sub(self.expire_duration);
+                let extend = ECHO_TIME_DURATION_SEC!(sec).min(allowed);
+
+                if extend.as_secs() == 0 {
+                    return Err(RunnerError::RecvWorkerOperErr(format!(
+                        "session has reached max duration, uuid={}, expire_duration_sec={}, ceiling_sec={}",
+                        self.uuid,
+                        self.expire_duration.as_secs(),
+                        ceiling.as_secs()
+                    )));
+                }
+
+                if extend.as_secs() < sec {
+                    log::warn!(
+                        "[RecvWorker::change_expiry] extension is clamped to ceiling, app_name={}, sec={}, extend_sec={}, ceiling_sec={}",
+                        self.app_name,
+                        sec,
+                        extend.as_secs(),
+                        ceiling.as_secs()
+                    );
+                }
+
+                self.expire_duration += extend;
+                self.expire_instant += extend;
+                self.expire_epoch += extend.as_secs() as i64;
+            }
+            ExpiryChangeOp::Shorten { sec } => {
+                // not before now, expires at once (grace period is applied)
+                let shorten = ECHO_TIME_DURATION_SEC!(sec).min(self.get_expire_remain());
+
+                self.expire_duration = self.expire_duration.saturating_sub(shorten);
+                self.expire_instant -= shorten;
+                self.expire_epoch -= shorten.as_secs() as i64;
+            }
+            ExpiryChangeOp::Pause => {
+                if self.expiry_paused_at.is_some() {
+                    return Err(RunnerError::RecvWorkerOperErr(format!(
+                        "expiry is already paused, uuid={}",
+                        self.uuid
+                    )));
+                }
+
+                self.expiry_paused_at = Some(now);
+            }
+            ExpiryChangeOp::Resume => {
+                let paused_at = self.expiry_paused_at.take().ok_or_else(|| {
+                    RunnerError::RecvWorkerOperErr(format!(
+                        "expiry is not paused, uuid={}",
+                        self.uuid
+                    ))
+                })?;
+
+                let paused = now - paused_at;
+
+                self.expire_instant += paused;
+                self.expire_epoch += paused.as_secs() as i64;
+            }
+        }
+
+        if let Some(thresholds) = change_prm.warn_thresholds_sec.clone() {
+            self.expire_warn_thresholds_sec = thresholds;
+        }
+
+        if let Some(grace_sec) = change_prm.grace_sec {
+            self.expire_grace = ECHO_TIME_DURATION_SEC!(grace_sec);
+        }
+
+        // thresholds which are not reached with new expiry, warn again
+        let remain_sec = self.get_expire_remain().as_secs();
+
+        self.expire_warned_sec.retain(|x| *x >= remain_sec);
+
+        if remain_sec > 0 {
+            self.expire_grace_notified = false;
+        }
+
+        let paused = self.expiry_paused_at.is_some();
+
+        log::info!(
+            "[RecvWorker] expiry is changed, app_name={}, op={}, prev_expire_epoch={}, expire_epoch={}",
+            self.app_name,
+            change_prm.op,
+            prev_expire_epoch,
+            self.expire_epoch
+        );
+
+        // session store of manager keeps expiry, recovered session expires on same epoch
+        if let Err(e) =
+            self.send_msg_to_manager(RecvWorkerManagerMsg::NotifyRecvWorkerExpiryChanged(
+                self.uuid.to_string(),
+                self.app_name.to_string(),
+                self.expire_epoch,
+            ))
+        {
+            log::error!(
+                "[RecvWorker::change_expiry] failed to send msg 'NotifyRecvWorkerExpiryChanged', e={}",
+                e.to_string()
+            );
+        }
+
+        event_bus::session_event(
+            "info",
+            "session_expiry_changed",
+            &self.app_name,
+            &self.uuid,
+            json!({
+                "change" : change_prm.op,
+                "prev_expire_epoch" : prev_expire_epoch,
+                "expire_epoch" : self.expire_epoch,
+                "remain_sec" : remain_sec,
+                "paused" : paused,
+                "warn_thresholds_sec" : self.expire_warn_thresholds_sec,
+                "grace_sec" : self.expire_grace.as_secs(),
+            }),
+        );
+
+        Ok(json!({
+            "expire_epoch" : self.expire_epoch,
+            "remain_sec" : remain_sec,
+            "paused" : paused,
+        }))
+    }
+}
+
+// total duration of extended session, EXPIRE_EXTEND_CEILING_SEC_ENV or default
+fn get_expire_extend_ceiling() -> Duration {
+    let sec = match std::env::var(EXPIRE_EXTEND_CEILING_SEC_ENV) {
+        Ok(x) => x.trim().parse().unwrap_or_else(|e| {
+            log::error!(
+                "[RecvWorker] invalid expiry extend ceiling, default is used, spec={}, e={}",
+                x,
+                e
+            );
+            EXPIRE_EXTEND_CEILING_SEC
+        }),
+        Err(_) => EXPIRE_EXTEND_CEILING_SEC,
+    };
+
+    Duration::from_secs(sec)
+}
+
+// thresholds of expiry warnings, EXPIRE_WARN_THRESHOLDS_SEC_ENV or default
+fn get_expire_warn_thresholds_sec() -> Vec<u64> {
+    let spec = match std::env::var(EXPIRE_WARN_THRESHOLDS_SEC_ENV) {
+        Ok(x) if !x.trim().is_empty() => x,
+        _ => return EXPIRE_WARN_THRESHOLDS_SEC.to_vec(),
+    };
+
+    let thresholds: Result<Vec<u64>, _> = spec.split(',').map(|x| x.trim().parse()).collect();
+
+    match thresholds {
+        Ok(x) => x,
+        Err(e) => {
+            log::error!(
+                "[RecvWorker] invalid expiry warning thresholds, default is used, spec={}, e={}",
+                spec,
+                e
+            );
+            EXPIRE_WARN_THRESHOLDS_SEC.to_vec()
+        }
+    }
 }